@@ -0,0 +1,277 @@
+// `#[derive(SortableKey)]` and `#[derive(SortableField)]` for sortable_rocksdb_keys, enabled with the `derive` feature of that crate.
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Fields, Index};
+
+struct KeyField {
+    name: String,
+    access: TokenStream2,
+    ty: syn::Type,
+    options: KeyOptions,
+}
+
+#[derive(Default)]
+struct KeyOptions {
+    descending: bool,
+    nulls_last: Option<bool>,
+}
+
+fn parse_key_attributes(field: &syn::Field) -> syn::Result<KeyOptions> {
+    let mut options = KeyOptions::default();
+    for attr in &field.attrs {
+        if !attr.path().is_ident("key") {
+            continue;
+        }
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("desc") {
+                options.descending = true;
+                Ok(())
+            } else if meta.path.is_ident("asc") {
+                options.descending = false;
+                Ok(())
+            } else if meta.path.is_ident("nulls_first") || meta.path.is_ident("nulls_last") {
+                // the schema would expect a presence marker that a field of any other type does not write
+                if !is_option(&field.ty) {
+                    return Err(meta.error("`nulls_first` and `nulls_last` can only be used on `Option` fields"));
+                }
+                options.nulls_last = Some(meta.path.is_ident("nulls_last"));
+                Ok(())
+            } else {
+                Err(meta.error("unknown key attribute, expected `desc`, `asc`, `nulls_first` or `nulls_last`"))
+            }
+        })?;
+    }
+    Ok(options)
+}
+
+// `Option<T>`, also written with its path, a type alias of an option is not recognized
+fn is_option(ty: &syn::Type) -> bool {
+    match ty {
+        syn::Type::Path(path) if path.qself.is_none() => path.path.segments.last().is_some_and(|segment| {
+            segment.ident == "Option" && matches!(&segment.arguments, syn::PathArguments::AngleBracketed(arguments) if arguments.args.len() == 1)
+        }),
+        _ => false,
+    }
+}
+
+fn key_fields(input: &DeriveInput) -> syn::Result<Vec<KeyField>> {
+    let fields = match &input.data {
+        Data::Struct(data) => &data.fields,
+        _ => return Err(syn::Error::new_spanned(&input.ident, "SortableKey can only be derived for structs")),
+    };
+    let mut result = Vec::new();
+    for (i, field) in fields.iter().enumerate() {
+        let (name, access) = match &field.ident {
+            Some(ident) => (ident.to_string(), quote!(#ident)),
+            None => {
+                let index = Index::from(i);
+                (i.to_string(), quote!(#index))
+            },
+        };
+        result.push(KeyField { name, access, ty: field.ty.clone(), options: parse_key_attributes(field)? });
+    }
+    if result.is_empty() {
+        return Err(syn::Error::new_spanned(&input.ident, "SortableKey needs at least one field"));
+    }
+    Ok(result)
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let fields = key_fields(&input)?;
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let krate = quote!(::rocks_sortable_keys);
+
+    let to_values = fields.iter().map(|field| {
+        let access = &field.access;
+        quote!(#krate::SortableField::to_encode_type(&self.#access))
+    });
+    let from_values = fields.iter().enumerate().map(|(i, field)| {
+        let access = &field.access;
+        let ty = &field.ty;
+        quote!(#access: #krate::next_field::<#ty>(&mut values, #i)?)
+    });
+    let schema_fields = fields.iter().map(|field| {
+        let name = &field.name;
+        let ty = &field.ty;
+        let desc = if field.options.descending { quote!(.desc()) } else { quote!() };
+        let nulls = match field.options.nulls_last {
+            Some(true) => quote!(.nulls_last()),
+            Some(false) => quote!(.nulls_first()),
+            None => quote!(),
+        };
+        quote!(.field(#name, <#ty as #krate::SortableField>::decode_type()) #desc #nulls)
+    });
+    let field_count = fields.len();
+    // a static in a generic impl is shared by all of its instances, so generic keys build the schema on every decode
+    let decode_key = if input.generics.params.is_empty() {
+        quote! {
+            fn decode_key(data: &[u8]) -> ::std::result::Result<Self, #krate::KeyError> {
+                static KEY_SCHEMA: ::std::sync::OnceLock<#krate::KeySchema> = ::std::sync::OnceLock::new();
+                let schema = KEY_SCHEMA.get_or_init(<Self as #krate::SortableKey>::key_schema);
+                #krate::KeyValues::from_encode_types(schema.decode_key(data)?)
+            }
+        }
+    } else {
+        quote!()
+    };
+    let construct = match &input.data {
+        Data::Struct(data) if matches!(data.fields, Fields::Unnamed(_)) => {
+            let values = fields.iter().enumerate().map(|(i, field)| {
+                let ty = &field.ty;
+                quote!(#krate::next_field::<#ty>(&mut values, #i)?)
+            });
+            quote!(#ident(#(#values),*))
+        },
+        _ => quote!(#ident { #(#from_values),* }),
+    };
+
+    Ok(quote! {
+        impl #impl_generics #krate::KeyValues for #ident #ty_generics #where_clause {
+            fn to_encode_types(&self) -> ::std::vec::Vec<#krate::EncodeType> {
+                ::std::vec![#(#to_values),*]
+            }
+
+            fn from_encode_types(values: ::std::vec::Vec<#krate::EncodeType>) -> ::std::result::Result<Self, #krate::KeyError> {
+                let mut values = values.into_iter();
+                let key = #construct;
+                #krate::no_more_fields(&mut values, #field_count)?;
+                ::std::result::Result::Ok(key)
+            }
+        }
+
+        impl #impl_generics #krate::SortableKey for #ident #ty_generics #where_clause {
+            fn key_schema() -> #krate::KeySchema {
+                #krate::KeySchema::new() #(#schema_fields)*
+            }
+
+            #decode_key
+        }
+
+        // a key can be a field of another key, it is then stored as a tuple
+        impl #impl_generics #krate::SortableField for #ident #ty_generics #where_clause {
+            fn decode_type() -> #krate::DecodeType {
+                #krate::DecodeType::DecodeTuple(<Self as #krate::SortableKey>::key_schema().decode_types())
+            }
+
+            fn to_encode_type(&self) -> #krate::EncodeType {
+                #krate::EncodeType::SortTuple(#krate::KeyValues::to_encode_types(self))
+            }
+
+            fn from_encode_type(value: #krate::EncodeType) -> ::std::option::Option<Self> {
+                match value {
+                    #krate::EncodeType::SortTuple(values) => #krate::KeyValues::from_encode_types(values).ok(),
+                    _ => ::std::option::Option::None,
+                }
+            }
+        }
+    })
+}
+
+/// Implements `KeyValues` and `SortableKey` for a struct. Every field type must implement `SortableField`,
+/// fields sort in declaration order and `#[key(desc)]` makes a field sort descending.
+/// The struct also implements `SortableField`, so it can be a field of another key, stored as a tuple.
+/// `#[key(nulls_last)]` sorts the `None` values of an `Option` field after the other values.
+#[proc_macro_derive(SortableKey, attributes(key))]
+pub fn derive_sortable_key(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match expand(input) {
+        Ok(tokens) => tokens.into(),
+        Err(error) => error.to_compile_error().into(),
+    }
+}
+
+fn expand_field(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let variants = match &input.data {
+        Data::Enum(data) => &data.variants,
+        _ => return Err(syn::Error::new_spanned(&input.ident, "SortableField can only be derived for enums")),
+    };
+    if variants.is_empty() || variants.len() > 256 {
+        return Err(syn::Error::new_spanned(&input.ident, "SortableField needs 1 to 256 variants"));
+    }
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let krate = quote!(::rocks_sortable_keys);
+
+    let mut variant_types = Vec::new();
+    let mut to_values = Vec::new();
+    let mut from_values = Vec::new();
+    for (i, variant) in variants.iter().enumerate() {
+        let name = &variant.ident;
+        let index = i as u8;
+        match &variant.fields {
+            Fields::Unnamed(fields) if fields.unnamed.len() == 1 => {
+                let ty = &fields.unnamed[0].ty;
+                variant_types.push(quote!(<#ty as #krate::SortableField>::decode_type()));
+                to_values.push(quote!(#ident::#name(value) => #krate::EncodeType::SortUnion(#index, ::std::boxed::Box::new(#krate::SortableField::to_encode_type(value)))));
+                from_values.push(quote!(#index => ::std::option::Option::Some(#ident::#name(<#ty as #krate::SortableField>::from_encode_type(*value)?))));
+            },
+            // a variant without a value holds an empty tuple
+            Fields::Unit => {
+                variant_types.push(quote!(#krate::DecodeType::DecodeTuple(::std::vec::Vec::new())));
+                to_values.push(quote!(#ident::#name => #krate::EncodeType::SortUnion(#index, ::std::boxed::Box::new(#krate::EncodeType::SortTuple(::std::vec::Vec::new())))));
+                from_values.push(quote!(#index => match *value {
+                    #krate::EncodeType::SortTuple(values) if values.is_empty() => ::std::option::Option::Some(#ident::#name),
+                    _ => ::std::option::Option::None,
+                }));
+            },
+            _ => return Err(syn::Error::new_spanned(variant, "SortableField variants must have no fields or one unnamed field")),
+        }
+    }
+
+    Ok(quote! {
+        impl #impl_generics #krate::SortableField for #ident #ty_generics #where_clause {
+            fn decode_type() -> #krate::DecodeType {
+                #krate::DecodeType::DecodeUnion(::std::vec![#(#variant_types),*])
+            }
+
+            fn to_encode_type(&self) -> #krate::EncodeType {
+                match self {
+                    #(#to_values),*
+                }
+            }
+
+            fn from_encode_type(value: #krate::EncodeType) -> ::std::option::Option<Self> {
+                match value {
+                    #krate::EncodeType::SortUnion(variant, value) => match variant {
+                        #(#from_values,)*
+                        _ => ::std::option::Option::None,
+                    },
+                    _ => ::std::option::Option::None,
+                }
+            }
+        }
+    })
+}
+
+/// Implements `SortableField` for an enum, stored as a `DecodeType::DecodeUnion` with one variant type per enum variant.
+/// Every variant has no fields or one unnamed field of a `SortableField` type. Values sort by the position of
+/// their variant in the enum first, so reordering the variants changes the order of existing keys.
+#[proc_macro_derive(SortableField)]
+pub fn derive_sortable_field(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match expand_field(input) {
+        Ok(tokens) => tokens.into(),
+        Err(error) => error.to_compile_error().into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nulls_order_needs_an_option() {
+        let error = expand(syn::parse_quote! {
+            struct Task {
+                #[key(nulls_last)]
+                finished: u64,
+            }
+        }).unwrap_err();
+        assert_eq!(error.to_string(), "`nulls_first` and `nulls_last` can only be used on `Option` fields");
+        assert!(expand(syn::parse_quote! { struct Task(#[key(desc, nulls_first)] Vec<u8>); }).is_err());
+        assert!(expand(syn::parse_quote! { struct Task(#[key(nulls_first)] ::std::option::Option<u64>); }).is_ok());
+        assert!(expand(syn::parse_quote! { struct Task(#[key(desc, nulls_last)] Option<String>); }).is_ok());
+    }
+}
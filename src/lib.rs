@@ -1,4 +1,51 @@
+//! Sortable, compound keys for RocksDB.
+//!
+//! A key is a list of `EncodeType` values, described by a list of `DecodeType`s. Keys written with
+//! `encode_keys` are ordered by installing `compare_bytes` as the RocksDB comparator, keys written
+//! with `encode_memcomparable` sort correctly with the default bytewise comparator.
+//!
+//! ```
+//! use rocks_sortable_keys::prelude::*;
+//! use std::cmp::Ordering;
+//!
+//! let the_types = vec![DecodeType::DecodeString, DecodeType::Reverse, DecodeType::DecodeU32];
+//! let key1 = encode_keys(&[EncodeType::SortString("a".to_string()), EncodeType::SortU32(1)]);
+//! let key2 = encode_keys(&[EncodeType::SortString("a".to_string()), EncodeType::SortU32(0)]);
+//! assert_eq!(compare_bytes(&the_types, &key1, &key2), Ordering::Less);
+//! ```
 mod rocks_sortable_keys;
+mod rockssort_bounds;
+mod rockssort_collation;
+mod rockssort_db;
+mod rockssort_decimal;
+mod rockssort_error;
 mod rockssort_float;
+mod rockssort_key;
+mod rockssort_memcmp;
+mod rockssort_prefix;
+mod rockssort_schema;
+mod rockssort_time;
 mod test_rocks;
 
+pub use rocks_sortable_keys::{compare, compare_bytes, compare_encoded, compare_encoded_with, compare_with, decode_byte_array, deserialize_decode_types, encode_keys, encode_prefix, serialize_decode_types, try_compare_encoded, try_compare_encoded_with, try_decode_byte_array, try_decode_prefix, try_deserialize_decode_types, try_validate_types, validate_types, Decode, DecodeType, Encode, EncodeType, NullOrder, PrefixOrder};
+pub use rockssort_bounds::{next_key, next_key_with, prefix_upper_bound, prefix_upper_bound_with, prev_key, prev_key_with};
+pub use rockssort_collation::{CollatedString, Collation};
+pub use rockssort_db::{SortedDb, SortedIter};
+pub use rockssort_decimal::{Decimal, ParseDecimalError};
+pub use rockssort_error::{DbError, KeyError};
+pub use rockssort_float::{F32struct, F32total, F64struct, F64total};
+#[doc(hidden)]
+pub use rockssort_key::{next_field, no_more_fields};
+pub use rockssort_key::{KeyValues, SortableField, SortableKey};
+pub use rockssort_memcmp::{decode_memcomparable, encode_memcomparable, try_decode_memcomparable};
+pub use rockssort_prefix::{prefix_extractor, prefix_len};
+pub use rockssort_schema::{CompareFn, KeyField, KeySchema};
+pub use rockssort_time::{Date, TimeUnit, Timestamp};
+
+#[cfg(feature = "derive")]
+pub use sortable_rocksdb_keys_derive::{SortableField, SortableKey};
+
+/// The types and functions needed to build, encode and compare keys.
+pub mod prelude {
+    pub use crate::{compare_bytes, decode_byte_array, encode_keys, encode_prefix, encode_memcomparable, decode_memcomparable, Decode, DecodeType, Encode, EncodeType, NullOrder, PrefixOrder, DbError, F32struct, F32total, F64struct, F64total, KeyError, KeySchema, SortedDb, KeyValues, SortableField, SortableKey, CollatedString, Collation, Date, Decimal, TimeUnit, Timestamp};
+}
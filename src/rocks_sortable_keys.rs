@@ -1,7 +1,12 @@
-#![allow(dead_code)]
 use std::cmp::Ordering;
 use crate::rockssort_float::F32struct;
 use crate::rockssort_float::F64struct; 
+use crate::rockssort_float::{F32total, F64total};
+use crate::rockssort_time::{Date, TimeUnit, Timestamp};
+use crate::rockssort_decimal::Decimal;
+use crate::rockssort_collation::{CollatedString, Collation};
+use std::time::Duration;
+use crate::rockssort_error::KeyError;
 // use enum_dispatch::enum_dispatch;
 
 // #[enum_dispatch]
@@ -10,40 +15,141 @@ pub trait Encode: std::fmt::Debug + Clone {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[non_exhaustive]
 pub enum EncodeType {
     SortU8(u8),
     SortU16(u16),
     SortU32(u32),
     SortU64(u64),
     SortU128(u128),
+    SortI8(i8),
+    SortI16(i16),
     SortI32(i32),
     SortI64(i64),
+    SortI128(i128),
     SortString(String),
     SortBytes(Vec<u8>),
     SortBool(bool),
     SortF32(F32struct),
     SortF64(F64struct),
+    SortF32Total(F32total),
+    SortF64Total(F64total),
+    SortTimestamp(Timestamp),
+    SortDate(Date),
+    SortDuration(Duration),
+    /// Bytes without a length prefix, the length is given by `DecodeType::DecodeFixedBytes`.
+    SortFixedBytes(Vec<u8>),
+    SortUuid([u8; 16]),
+    /// A u64 written in 1 to 9 bytes, small values take less space. Sorts like `SortU64`.
+    SortVarU64(u64),
+    /// An i64 written in 1 to 9 bytes, values close to zero take less space. Sorts like `SortI64`.
+    SortVarI64(i64),
+    /// An exact decimal number, see `Decimal` for the encoding.
+    SortDecimal(Decimal),
+    /// A string ordered by its collation, see `CollatedString`.
+    SortCollatedString(CollatedString),
+    /// A group of fields that sorts like a key of its own, the values of `DecodeType::DecodeTuple`.
+    SortTuple(Vec<EncodeType>),
+    /// The index of the variant in `DecodeType::DecodeUnion` and the value of that variant.
+    SortUnion(u8, Box<EncodeType>),
+    /// The elements of a `DecodeType::DecodeList` field.
+    SortList(Vec<EncodeType>),
+    /// A value of a nullable field, `None` is the null value.
+    SortOption(Option<Box<EncodeType>>),
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[non_exhaustive]
 pub enum DecodeType {
     DecodeU8,
     DecodeU16,
     DecodeU32,
     DecodeU64,
     DecodeU128,
+    DecodeI8,
+    DecodeI16,
     DecodeI32,
     DecodeI64,
+    DecodeI128,
     DecodeString,
     DecodeBytes,
     DecodeBool,
     DecodeF32,
     DecodeF64,
+    DecodeF32Total,
+    DecodeF64Total,
+    DecodeTimestamp(TimeUnit),
+    DecodeDate,
+    DecodeDuration,
+    DecodeFixedBytes(usize),
+    DecodeUuid,
+    DecodeVarU64,
+    DecodeVarI64,
+    DecodeDecimal,
+    /// A string ordered by the collation, the flag tells whether the sort key is stored in front of the string.
+    DecodeCollatedString(Collation, bool),
+    /// A group of fields, compared field by field like a compound key. The list may hold `Reverse` for its own fields,
+    /// a `Reverse` in front of the group flips the direction of every field in it.
+    DecodeTuple(Vec<DecodeType>),
+    /// A field that holds one of up to 256 variant types. Values sort by the index of their variant first,
+    /// then by the value, so the order of the variants is part of the schema.
+    DecodeUnion(Vec<DecodeType>),
+    /// Any number of values of the element type, compared element by element, a list sorts before the longer lists it starts.
+    DecodeList(Box<DecodeType>),
+    /// A nullable field holding the given type, where the nulls sort is set by `NullOrder`.
+    /// The position of the nulls does not change when the field is reversed, like `NULLS FIRST` / `NULLS LAST` in sql.
+    DecodeOption(Box<DecodeType>, NullOrder),
     Reverse
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum NullOrder {
+    NullsFirst,
+    NullsLast,
+}
+
+impl NullOrder {
+    // orders a null against a present value, independent of the direction of the field
+    #[inline]
+    pub(crate) fn place(self, key1_present: bool, key2_present: bool) -> Ordering {
+        match self {
+            NullOrder::NullsFirst => key1_present.cmp(&key2_present),
+            NullOrder::NullsLast => key2_present.cmp(&key1_present),
+        }
+    }
+}
+
+/// Where a key that holds only the leading fields of the schema sorts against the longer keys it starts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum PrefixOrder {
+    /// The shorter key sorts before them, so seeking to it finds the first key starting with its fields.
+    #[default]
+    ShorterFirst,
+    /// The shorter key sorts after them, so it is an inclusive upper bound for the keys starting with its fields.
+    ShorterLast,
+}
+
+impl PrefixOrder {
+    // orders two keys whose common fields are equal by which of them has more fields
+    #[inline]
+    pub(crate) fn place(self, key1_has_more: bool, key2_has_more: bool) -> Ordering {
+        match self {
+            PrefixOrder::ShorterFirst => key1_has_more.cmp(&key2_has_more),
+            PrefixOrder::ShorterLast => key2_has_more.cmp(&key1_has_more),
+        }
+    }
+}
+
+// marker byte in front of a nullable field
+const NULL_MARKER: u8 = 0;
+const PRESENT_MARKER: u8 = 1;
+// marker byte in front of every list element and after the last one, the end sorts before another element
+pub(crate) const LIST_ELEMENT: u8 = 1;
+pub(crate) const LIST_END: u8 = 0;
+
 pub trait Decode: std::fmt::Debug + Clone {
     fn decode(data: &[u8], the_type: DecodeType, pos: &mut usize) -> EncodeType;
+    fn try_decode(data: &[u8], the_type: DecodeType, pos: &mut usize) -> Result<EncodeType, KeyError>;
 }
 
 impl Encode for EncodeType {
@@ -56,8 +162,11 @@ impl Encode for EncodeType {
             EncodeType::SortU32(value) => value.to_be_bytes().to_vec(),
             EncodeType::SortU64(value) => value.to_be_bytes().to_vec(),
             EncodeType::SortU128(value) => value.to_be_bytes().to_vec(),
+            EncodeType::SortI8(value) => value.to_be_bytes().to_vec(),
+            EncodeType::SortI16(value) => value.to_be_bytes().to_vec(),
             EncodeType::SortI32(value) => value.to_be_bytes().to_vec(),
             EncodeType::SortI64(value) => value.to_be_bytes().to_vec(),
+            EncodeType::SortI128(value) => value.to_be_bytes().to_vec(),
             EncodeType::SortString(value) => {
                 let the_len = value.len() as u32;
                 [the_len.to_be_bytes().to_vec(), value.as_bytes().to_vec()].concat()
@@ -73,88 +182,268 @@ impl Encode for EncodeType {
                     vec![0]
                 }
             },
-            EncodeType::SortF32(value) => value.value.to_be_bytes().to_vec(),
-            EncodeType::SortF64(value) => value.value.to_be_bytes().to_vec(),
+            EncodeType::SortF32(value) => value.to_sortable_bits().to_be_bytes().to_vec(),
+            EncodeType::SortF64(value) => value.to_sortable_bits().to_be_bytes().to_vec(),
+            EncodeType::SortF32Total(value) => value.to_sortable_bits().to_be_bytes().to_vec(),
+            EncodeType::SortF64Total(value) => value.to_sortable_bits().to_be_bytes().to_vec(),
+            EncodeType::SortTimestamp(value) => value.since_epoch.to_be_bytes().to_vec(),
+            EncodeType::SortDate(value) => value.days_since_epoch.to_be_bytes().to_vec(),
+            EncodeType::SortDuration(value) => [value.as_secs().to_be_bytes().to_vec(), value.subsec_nanos().to_be_bytes().to_vec()].concat(),
+            EncodeType::SortFixedBytes(value) => value.clone(),
+            EncodeType::SortUuid(value) => value.to_vec(),
+            EncodeType::SortVarU64(value) => {
+                let mut result = Vec::new();
+                encode_var_u64(*value, &mut result);
+                result
+            },
+            EncodeType::SortVarI64(value) => {
+                let mut result = Vec::new();
+                encode_var_i64(*value, &mut result);
+                result
+            },
+            EncodeType::SortDecimal(value) => {
+                let mut result = Vec::new();
+                value.encode(&mut result);
+                result
+            },
+            EncodeType::SortCollatedString(value) => {
+                let mut result = Vec::new();
+                if value.stored_sort_key {
+                    let sort_key = value.sort_key();
+                    result.extend((sort_key.len() as u32).to_be_bytes());
+                    result.extend(sort_key.as_bytes());
+                }
+                result.extend((value.value.len() as u32).to_be_bytes());
+                result.extend(value.value.as_bytes());
+                result
+            },
+            EncodeType::SortTuple(values) => encode_keys(values),
+            EncodeType::SortUnion(variant, value) => [vec![*variant], value.encode()].concat(),
+            EncodeType::SortList(values) => {
+                let mut result = Vec::new();
+                for value in values {
+                    result.push(LIST_ELEMENT);
+                    result.extend(value.encode());
+                }
+                result.push(LIST_END);
+                result
+            },
+            EncodeType::SortOption(None) => vec![NULL_MARKER],
+            EncodeType::SortOption(Some(value)) => [vec![PRESENT_MARKER], value.encode()].concat(),
         }
     }
 }
 
+// Order preserving variable length integers, the encoded bytes sort like the values:
+//   unsigned   a header byte with the number of bytes n (0 to 8), then the value in n big endian bytes
+//   signed     zero and positive values as unsigned, with 0x80 + n as header,
+//              negative values with 0x80 - n as header and the n bytes of the absolute value inverted
+// Only the shortest form is valid, so equal values always have equal bytes.
+const VAR_SIGNED_ZERO: u8 = 0x80;
+
+#[inline]
+fn significant_bytes(value: u64) -> usize {
+    8 - value.leading_zeros() as usize / 8
+}
+
+#[inline]
+pub(crate) fn encode_var_u64(value: u64, out: &mut Vec<u8>) {
+    let len = significant_bytes(value);
+    out.push(len as u8);
+    out.extend(&value.to_be_bytes()[8 - len..]);
+}
+
+#[inline]
+pub(crate) fn encode_var_i64(value: i64, out: &mut Vec<u8>) {
+    let magnitude = value.unsigned_abs();
+    let len = significant_bytes(magnitude);
+    if value >= 0 {
+        out.push(VAR_SIGNED_ZERO + len as u8);
+        out.extend(&magnitude.to_be_bytes()[8 - len..]);
+    } else {
+        out.push(VAR_SIGNED_ZERO - len as u8);
+        out.extend(magnitude.to_be_bytes()[8 - len..].iter().map(|byte| !byte));
+    }
+}
+
+/// The number of bytes after the header byte of a variable length integer, `None` for an invalid header.
+#[inline]
+pub(crate) fn var_int_len(header: u8, signed: bool) -> Option<usize> {
+    let len = if signed { header.abs_diff(VAR_SIGNED_ZERO) } else { header };
+    if len > 8 {
+        return None;
+    }
+    Some(len as usize)
+}
+
+// `encoded` is the header followed by the bytes of the value
+#[inline]
+pub(crate) fn var_u64_from_bytes(encoded: &[u8]) -> Option<u64> {
+    let bytes = &encoded[1..];
+    if bytes.first() == Some(&0) {
+        return None;
+    }
+    Some(bytes.iter().fold(0u64, |value, byte| value << 8 | *byte as u64))
+}
+
+#[inline]
+pub(crate) fn var_i64_from_bytes(encoded: &[u8]) -> Option<i64> {
+    if encoded[0] >= VAR_SIGNED_ZERO {
+        return i64::try_from(var_u64_from_bytes(encoded)?).ok();
+    }
+    let bytes = &encoded[1..];
+    if bytes.first() == Some(&0xff) {
+        return None;
+    }
+    let magnitude = bytes.iter().fold(0u64, |value, byte| value << 8 | !*byte as u64);
+    i64::try_from(-(magnitude as i128)).ok()
+}
+
+// the header and the bytes of a variable length integer
+#[inline]
+fn take_var_int<'a>(data: &'a [u8], pos: &mut usize, signed: bool) -> Result<&'a [u8], KeyError> {
+    let start = *pos;
+    let header = fixed::<1>(data, pos)?[0];
+    let len = var_int_len(header, signed).ok_or(KeyError::InvalidEncoding { field: 0, offset: start })?;
+    take(data, pos, len)?;
+    Ok(&data[start..*pos])
+}
+
+#[inline]
+fn take<'a>(data: &'a [u8], pos: &mut usize, len: usize) -> Result<&'a [u8], KeyError> {
+    let available = data.len().saturating_sub(*pos);
+    if available < len {
+        return Err(KeyError::Truncated { field: 0, offset: *pos, needed: len, available });
+    }
+    let value = &data[*pos..*pos + len];
+    *pos += len;
+    Ok(value)
+}
+
+#[inline]
+fn fixed<const N: usize>(data: &[u8], pos: &mut usize) -> Result<[u8; N], KeyError> {
+    Ok(take(data, pos, N)?.try_into().unwrap())
+}
+
+#[inline]
+fn length_prefixed<'a>(data: &'a [u8], pos: &mut usize) -> Result<&'a [u8], KeyError> {
+    let the_len = u32::from_be_bytes(fixed(data, pos)?) as usize;
+    take(data, pos, the_len)
+}
+
+// the bytes of a decimal number, they sort like its value
+#[inline]
+fn take_decimal<'a>(data: &'a [u8], pos: &mut usize) -> Result<&'a [u8], KeyError> {
+    let len = Decimal::encoded_len(data.get(*pos..).unwrap_or_default(), 0).ok_or(KeyError::InvalidEncoding { field: 0, offset: *pos })?;
+    take(data, pos, len)
+}
+
+#[inline]
+fn utf8_string<'a>(data: &'a [u8], pos: &mut usize) -> Result<&'a str, KeyError> {
+    let start = *pos;
+    std::str::from_utf8(length_prefixed(data, pos)?).map_err(|_| KeyError::InvalidUtf8 { field: 0, offset: start })
+}
+
+#[inline]
+pub(crate) fn duration_from_parts(seconds: [u8; 8], nanos: [u8; 4], offset: usize) -> Result<Duration, KeyError> {
+    let nanos = u32::from_be_bytes(nanos);
+    if nanos >= 1_000_000_000 {
+        return Err(KeyError::InvalidEncoding { field: 0, offset });
+    }
+    Ok(Duration::new(u64::from_be_bytes(seconds), nanos))
+}
+
+#[inline]
+fn duration(data: &[u8], pos: &mut usize) -> Result<Duration, KeyError> {
+    let seconds = fixed(data, pos)?;
+    let offset = *pos;
+    duration_from_parts(seconds, fixed(data, pos)?, offset)
+}
+
 impl Decode for DecodeType {
 
 #[inline]
     fn decode(data: &[u8], the_type: DecodeType, pos: &mut usize) -> EncodeType {
-        match the_type {
-            DecodeType::DecodeU8 => {
-                let value = u8::from_be_bytes([data[*pos]]);
-                *pos += 1;
-                EncodeType::SortU8(value)
-            },
-            DecodeType::DecodeU16 => {
-                let value = u16::from_be_bytes([data[*pos], data[*pos + 1]]);
-                *pos += 2;
-                EncodeType::SortU16(value)
-            },
-            DecodeType::DecodeU32 => {
-                let value = u32::from_be_bytes([data[*pos], data[*pos + 1], data[*pos + 2], data[*pos + 3]]);
-                *pos += 4;
-                EncodeType::SortU32(value)
-            },
-            DecodeType::DecodeU64 => {
-                let value = u64::from_be_bytes([data[*pos], data[*pos + 1], data[*pos + 2], data[*pos + 3], data[*pos + 4], data[*pos + 5], data[*pos + 6], data[*pos + 7]]);
-                *pos += 8;
-                EncodeType::SortU64(value)
-            },
-            DecodeType::DecodeU128 => {
-                let value = u128::from_be_bytes([data[*pos], data[*pos + 1], data[*pos + 2], data[*pos + 3], data[*pos + 4], data[*pos + 5], data[*pos + 6], data[*pos + 7], data[*pos + 8], data[*pos + 9], data[*pos + 10], data[*pos + 11], data[*pos + 12], data[*pos + 13], data[*pos + 14], data[*pos + 15]]);
-                *pos += 16;
-                EncodeType::SortU128(value)
-            },
-            DecodeType::DecodeI32 => {
-                let value = i32::from_be_bytes([data[*pos], data[*pos + 1], data[*pos + 2], data[*pos + 3]]);
-                *pos += 4;
-                EncodeType::SortI32(value)
+        match Self::try_decode(data, the_type, pos) {
+            Ok(value) => value,
+            Err(error) => panic!("{}", error),
+        }
+    }
+
+#[inline]
+    fn try_decode(data: &[u8], the_type: DecodeType, pos: &mut usize) -> Result<EncodeType, KeyError> {
+        let value = match the_type {
+            DecodeType::DecodeU8 => EncodeType::SortU8(u8::from_be_bytes(fixed(data, pos)?)),
+            DecodeType::DecodeU16 => EncodeType::SortU16(u16::from_be_bytes(fixed(data, pos)?)),
+            DecodeType::DecodeU32 => EncodeType::SortU32(u32::from_be_bytes(fixed(data, pos)?)),
+            DecodeType::DecodeU64 => EncodeType::SortU64(u64::from_be_bytes(fixed(data, pos)?)),
+            DecodeType::DecodeU128 => EncodeType::SortU128(u128::from_be_bytes(fixed(data, pos)?)),
+            DecodeType::DecodeI8 => EncodeType::SortI8(i8::from_be_bytes(fixed(data, pos)?)),
+            DecodeType::DecodeI16 => EncodeType::SortI16(i16::from_be_bytes(fixed(data, pos)?)),
+            DecodeType::DecodeI32 => EncodeType::SortI32(i32::from_be_bytes(fixed(data, pos)?)),
+            DecodeType::DecodeI64 => EncodeType::SortI64(i64::from_be_bytes(fixed(data, pos)?)),
+            DecodeType::DecodeI128 => EncodeType::SortI128(i128::from_be_bytes(fixed(data, pos)?)),
+            DecodeType::DecodeString => EncodeType::SortString(utf8_string(data, pos)?.to_string()),
+            DecodeType::DecodeBytes => EncodeType::SortBytes(length_prefixed(data, pos)?.to_vec()),
+            DecodeType::DecodeBool => EncodeType::SortBool(fixed::<1>(data, pos)?[0] == 1),
+            DecodeType::DecodeF32 => EncodeType::SortF32(F32struct::from_sortable_bits(u32::from_be_bytes(fixed(data, pos)?))),
+            DecodeType::DecodeF64 => EncodeType::SortF64(F64struct::from_sortable_bits(u64::from_be_bytes(fixed(data, pos)?))),
+            DecodeType::DecodeF32Total => EncodeType::SortF32Total(F32total::from_sortable_bits(u32::from_be_bytes(fixed(data, pos)?))),
+            DecodeType::DecodeF64Total => EncodeType::SortF64Total(F64total::from_sortable_bits(u64::from_be_bytes(fixed(data, pos)?))),
+            DecodeType::DecodeTimestamp(unit) => EncodeType::SortTimestamp(Timestamp::new(i64::from_be_bytes(fixed(data, pos)?), unit)),
+            DecodeType::DecodeDate => EncodeType::SortDate(Date::new(i32::from_be_bytes(fixed(data, pos)?))),
+            DecodeType::DecodeDuration => EncodeType::SortDuration(duration(data, pos)?),
+            DecodeType::DecodeFixedBytes(len) => EncodeType::SortFixedBytes(take(data, pos, len)?.to_vec()),
+            DecodeType::DecodeUuid => EncodeType::SortUuid(fixed(data, pos)?),
+            DecodeType::DecodeVarU64 => {
+                let start = *pos;
+                EncodeType::SortVarU64(var_u64_from_bytes(take_var_int(data, pos, false)?).ok_or(KeyError::InvalidEncoding { field: 0, offset: start })?)
             },
-            DecodeType::DecodeI64 => {
-                let value = i64::from_be_bytes([data[*pos], data[*pos + 1], data[*pos + 2], data[*pos + 3], data[*pos + 4], data[*pos + 5], data[*pos + 6], data[*pos + 7]]);
-                *pos += 8;
-                EncodeType::SortI64(value)
+            DecodeType::DecodeVarI64 => {
+                let start = *pos;
+                EncodeType::SortVarI64(var_i64_from_bytes(take_var_int(data, pos, true)?).ok_or(KeyError::InvalidEncoding { field: 0, offset: start })?)
             },
-            DecodeType::DecodeString => {
-                let the_len = u32::from_be_bytes([data[*pos], data[*pos + 1], data[*pos + 2], data[*pos + 3]]) as usize;
-                *pos += 4;
-                let value = String::from_utf8(data[*pos..*pos + the_len].to_vec()).unwrap();
-                *pos += the_len;
-                EncodeType::SortString(value)
+            DecodeType::DecodeDecimal => {
+                let start = *pos;
+                EncodeType::SortDecimal(Decimal::decode(take_decimal(data, pos)?).ok_or(KeyError::InvalidEncoding { field: 0, offset: start })?)
             },
-            DecodeType::DecodeBytes => {
-                let the_len = u32::from_be_bytes([data[*pos], data[*pos + 1], data[*pos + 2], data[*pos + 3]]) as usize;
-                *pos += 4;
-                let value = data[*pos..*pos + the_len].to_vec();
-                *pos += the_len;
-                EncodeType::SortBytes(value)
+            DecodeType::DecodeCollatedString(collation, stored_sort_key) => {
+                let start = *pos;
+                let sort_key = if stored_sort_key { Some(length_prefixed(data, pos)?) } else { None };
+                let value = utf8_string(data, pos)?;
+                let value = CollatedString::new(value, collation, stored_sort_key);
+                // a stale sort key would put the key in the wrong place
+                if sort_key.is_some_and(|sort_key| sort_key != value.sort_key().as_bytes()) {
+                    return Err(KeyError::InvalidEncoding { field: 0, offset: start });
+                }
+                EncodeType::SortCollatedString(value)
             },
-            DecodeType::DecodeBool => {
-                let value = data[*pos] == 1;
-                *pos += 1;
-                EncodeType::SortBool(value)
+            DecodeType::DecodeTuple(inner) => EncodeType::SortTuple(decode_fields(data, &inner, pos)?),
+            DecodeType::DecodeUnion(variants) => {
+                let start = *pos;
+                let variant = fixed::<1>(data, pos)?[0];
+                let the_type = variants.get(variant as usize).ok_or(KeyError::InvalidEncoding { field: 0, offset: start })?;
+                EncodeType::SortUnion(variant, Box::new(Self::try_decode(data, the_type.clone(), pos)?))
             },
-            DecodeType::DecodeF32 => {
-                let value = f32::from_be_bytes([data[*pos], data[*pos + 1], data[*pos + 2], data[*pos + 3]]);
-                *pos += 4;
-                let f32_struct = F32struct::new(value);
-                EncodeType::SortF32(f32_struct)
+            DecodeType::DecodeList(element) => {
+                let mut values = Vec::new();
+                while list_marker(data, pos)? {
+                    values.push(Self::try_decode(data, (*element).clone(), pos)?);
+                }
+                EncodeType::SortList(values)
             },
-            DecodeType::DecodeF64 => {
-                let value = f64::from_be_bytes([data[*pos], data[*pos + 1], data[*pos + 2], data[*pos + 3], data[*pos + 4], data[*pos + 5], data[*pos + 6], data[*pos + 7]]);
-                *pos += 8;
-                let f64_struct = F64struct::new(value);
-                EncodeType::SortF64(f64_struct)
+            DecodeType::DecodeOption(inner, _) => {
+                let start = *pos;
+                match fixed::<1>(data, pos)?[0] {
+                    NULL_MARKER => EncodeType::SortOption(None),
+                    PRESENT_MARKER => EncodeType::SortOption(Some(Box::new(Self::try_decode(data, *inner, pos)?))),
+                    _ => return Err(KeyError::InvalidEncoding { field: 0, offset: start }),
+                }
             },
             // will never happend, just added for completeness
-            DecodeType::Reverse => {
-                EncodeType::SortU32(0)
-            },
-        }
+            DecodeType::Reverse => EncodeType::SortU32(0),
+        };
+        Ok(value)
     }
 } 
 
@@ -168,23 +457,95 @@ pub fn encode_keys<T: Encode>(keys: &[T]) -> Vec<u8> {
     encoded_data
 }
 
+// a `Reverse` only flips the field after it, so one at the end or in front of another would be lost silently
+pub(crate) fn check_reverse(the_types: &[DecodeType]) -> Result<(), KeyError> {
+    let mut is_reverse = false;
+    for (index, the_type) in the_types.iter().enumerate() {
+        if is_reverse && the_type == &DecodeType::Reverse {
+            return Err(KeyError::MisplacedReverse { index: index - 1 });
+        }
+        is_reverse = the_type == &DecodeType::Reverse;
+    }
+    match is_reverse {
+        true => Err(KeyError::MisplacedReverse { index: the_types.len() - 1 }),
+        false => Ok(()),
+    }
+}
+
 #[inline]
-pub fn decode_byte_array(data: &[u8], the_types: &Vec<DecodeType>) -> Vec<EncodeType> {
-    let mut pos = 0;
+fn decode_fields(data: &[u8], the_types: &[DecodeType], pos: &mut usize) -> Result<Vec<EncodeType>, KeyError> {
+    check_reverse(the_types)?;
     let mut decoded_data = Vec::new();
 
     for the_type in the_types {
         if the_type == &DecodeType::Reverse {
             continue;
         }
-        decoded_data.push(DecodeType::decode(data, the_type.clone(), &mut pos));
+        let field = decoded_data.len();
+        decoded_data.push(DecodeType::try_decode(data, the_type.clone(), pos).map_err(|e| e.at_field(field))?);
+    }
+    Ok(decoded_data)
+}
+
+#[inline]
+pub fn decode_byte_array(data: &[u8], the_types: &[DecodeType]) -> Vec<EncodeType> {
+    match decode_fields(data, the_types, &mut 0) {
+        Ok(decoded_data) => decoded_data,
+        Err(error) => panic!("{}", error),
+    }
+}
+
+/// Like `decode_byte_array`, but returns an error for truncated or corrupt keys, for keys with bytes after the last field
+/// and for a `DecodeType::Reverse` that is not followed by a field.
+#[inline]
+pub fn try_decode_byte_array(data: &[u8], the_types: &[DecodeType]) -> Result<Vec<EncodeType>, KeyError> {
+    let mut pos = 0;
+    let decoded_data = decode_fields(data, the_types, &mut pos)?;
+    if pos != data.len() {
+        return Err(KeyError::TrailingBytes { offset: pos, remaining: data.len() - pos });
+    }
+    Ok(decoded_data)
+}
+
+/// Decodes a key that may hold only the leading fields of the schema, as written by `encode_prefix`.
+pub fn try_decode_prefix(data: &[u8], the_types: &[DecodeType]) -> Result<Vec<EncodeType>, KeyError> {
+    check_reverse(the_types)?;
+    let mut pos = 0;
+    let mut decoded_data = Vec::new();
+    for the_type in the_types {
+        if pos == data.len() {
+            break;
+        }
+        if the_type == &DecodeType::Reverse {
+            continue;
+        }
+        let field = decoded_data.len();
+        decoded_data.push(DecodeType::try_decode(data, the_type.clone(), &mut pos).map_err(|e| e.at_field(field))?);
+    }
+    if pos != data.len() {
+        return Err(KeyError::TrailingBytes { offset: pos, remaining: data.len() - pos });
     }
-    decoded_data
+    Ok(decoded_data)
+}
+
+/// Encodes the values of the leading fields of the schema, e.g. as seek key for the first key starting with them.
+/// Fails like `try_validate_types` when a value does not match its field or there are more values than fields.
+pub fn encode_prefix(the_types: &[DecodeType], keys: &[EncodeType]) -> Result<Vec<u8>, KeyError> {
+    check_reverse(the_types)?;
+    let decode_types: Vec<DecodeType> = the_types.iter().filter(|x| **x != DecodeType::Reverse).take(keys.len()).cloned().collect();
+    try_validate_types(&decode_types, keys)?;
+    Ok(encode_keys(keys))
 }
 
 
+/// Compares two decoded keys. A key may hold only the leading fields of the schema, it then sorts before every key that starts with the same fields.
 #[inline]
-pub fn compare(the_types: &Vec<DecodeType>, key1: &[EncodeType], key2: &[EncodeType]) -> Ordering {
+pub fn compare(the_types: &[DecodeType], key1: &[EncodeType], key2: &[EncodeType]) -> Ordering {
+    compare_with(the_types, PrefixOrder::ShorterFirst, key1, key2)
+}
+
+/// Like `compare`, with keys that hold only the leading fields of the schema placed by `prefix_order`.
+pub fn compare_with(the_types: &[DecodeType], prefix_order: PrefixOrder, key1: &[EncodeType], key2: &[EncodeType]) -> Ordering {
     let mut pos = 0;
     let mut is_reverse = false;
     for the_type in the_types {
@@ -192,6 +553,9 @@ pub fn compare(the_types: &Vec<DecodeType>, key1: &[EncodeType], key2: &[EncodeT
             is_reverse = true;
             continue;
         }
+        if pos == key1.len() || pos == key2.len() {
+            return prefix_order.place(pos < key1.len(), pos < key2.len());
+        }
         let result = match the_type {
             DecodeType::DecodeU8 => {
                 let key1_value = match &key1[pos] {
@@ -268,6 +632,81 @@ pub fn compare(the_types: &Vec<DecodeType>, key1: &[EncodeType], key2: &[EncodeT
                     key1_value.cmp(key2_value)
                 }
             },
+            DecodeType::DecodeI8 => {
+                let key1_value = match &key1[pos] {
+                    EncodeType::SortI8(value) => value,
+                    _ => panic!("wrong type"),
+                };
+                let key2_value = match &key2[pos] {
+                    EncodeType::SortI8(value) => value,
+                    _ => panic!("wrong type"),
+                };
+                if is_reverse {
+                    key2_value.cmp(key1_value)
+                } else {
+                    key1_value.cmp(key2_value)
+                }
+            },
+            DecodeType::DecodeI16 => {
+                let key1_value = match &key1[pos] {
+                    EncodeType::SortI16(value) => value,
+                    _ => panic!("wrong type"),
+                };
+                let key2_value = match &key2[pos] {
+                    EncodeType::SortI16(value) => value,
+                    _ => panic!("wrong type"),
+                };
+                if is_reverse {
+                    key2_value.cmp(key1_value)
+                } else {
+                    key1_value.cmp(key2_value)
+                }
+            },
+            DecodeType::DecodeI32 => {
+                let key1_value = match &key1[pos] {
+                    EncodeType::SortI32(value) => value,
+                    _ => panic!("wrong type"),
+                };
+                let key2_value = match &key2[pos] {
+                    EncodeType::SortI32(value) => value,
+                    _ => panic!("wrong type"),
+                };
+                if is_reverse {
+                    key2_value.cmp(key1_value)
+                } else {
+                    key1_value.cmp(key2_value)
+                }
+            },
+            DecodeType::DecodeI64 => {
+                let key1_value = match &key1[pos] {
+                    EncodeType::SortI64(value) => value,
+                    _ => panic!("wrong type"),
+                };
+                let key2_value = match &key2[pos] {
+                    EncodeType::SortI64(value) => value,
+                    _ => panic!("wrong type"),
+                };
+                if is_reverse {
+                    key2_value.cmp(key1_value)
+                } else {
+                    key1_value.cmp(key2_value)
+                }
+            },
+            DecodeType::DecodeI128 => {
+                let key1_value = match &key1[pos] {
+                    EncodeType::SortI128(value) => value,
+                    _ => panic!("wrong type"),
+                };
+                let key2_value = match &key2[pos] {
+                    EncodeType::SortI128(value) => value,
+                    _ => panic!("wrong type"),
+                };
+                if is_reverse {
+                    key2_value.cmp(key1_value)
+                } else {
+                    key1_value.cmp(key2_value)
+                }
+            },
             DecodeType::DecodeString => {
                 let key1_value = match &key1[pos] {
                     EncodeType::SortString(value) => value,
@@ -283,6 +722,21 @@ pub fn compare(the_types: &Vec<DecodeType>, key1: &[EncodeType], key2: &[EncodeT
                     key1_value.cmp(key2_value)
                 }
             },
+            DecodeType::DecodeBytes => {
+                let key1_value = match &key1[pos] {
+                    EncodeType::SortBytes(value) => value,
+                    _ => panic!("wrong type"),
+                };
+                let key2_value = match &key2[pos] {
+                    EncodeType::SortBytes(value) => value,
+                    _ => panic!("wrong type"),
+                };
+                if is_reverse {
+                    key2_value.cmp(key1_value)
+                } else {
+                    key1_value.cmp(key2_value)
+                }
+            },
             DecodeType::DecodeBool => {
                 let key1_value = match &key1[pos] {
                     EncodeType::SortBool(value) => value,
@@ -328,140 +782,811 @@ pub fn compare(the_types: &Vec<DecodeType>, key1: &[EncodeType], key2: &[EncodeT
                     key1_value.cmp(key2_value)
                 }
             },
-            _ => panic!("wrong type"),
-        };
-        if result != Ordering::Equal {
-            return result;
-        }
-        pos += 1;
-        is_reverse = false;
-    }
-    Ordering::Equal
-}
-#[inline]
-pub fn compare_bytes(the_types: &Vec<DecodeType>, key1: &[u8], key2: &[u8]) -> Ordering {
-    let decoded_key1 = decode_byte_array(key1, the_types);
-    let decoded_key2 = decode_byte_array(key2, the_types);
-    compare(the_types, &decoded_key1, &decoded_key2)
-}
-
-
-pub fn serialize_decode_types(the_types:&Vec<DecodeType>) -> Vec<u8> {
-    let mut result = Vec::new();
-    for the_type in the_types {
-        match the_type {
-            DecodeType::DecodeU8 => result.push(0),
-            DecodeType::DecodeU16 => result.push(1),
-            DecodeType::DecodeU32 => result.push(2),
-            DecodeType::DecodeU64 => result.push(3),
-            DecodeType::DecodeU128 => result.push(4),
-            DecodeType::DecodeString => result.push(5),
-            DecodeType::DecodeBool => result.push(6),
-            DecodeType::DecodeF32 => result.push(7),
-            DecodeType::DecodeF64 => result.push(8),
-            DecodeType::Reverse => result.push(9),
-            DecodeType::DecodeBytes => result.push(10),
-            DecodeType::DecodeI32 => result.push(11),
-            DecodeType::DecodeI64 => result.push(12),
-        }
-    }
-    result
-}
-
-pub fn deserialize_decode_types(the_types:&Vec<u8>) -> Vec<DecodeType> {
-    let mut result = Vec::new();
-    for the_type in the_types {
-        match the_type {
-            0 => result.push(DecodeType::DecodeU8),
-            1 => result.push(DecodeType::DecodeU16),
-            2 => result.push(DecodeType::DecodeU32),
-            3 => result.push(DecodeType::DecodeU64),
-            4 => result.push(DecodeType::DecodeU128),
-            5 => result.push(DecodeType::DecodeString),
-            6 => result.push(DecodeType::DecodeBool),
-            7 => result.push(DecodeType::DecodeF32),
-            8 => result.push(DecodeType::DecodeF64),
-            9 => result.push(DecodeType::Reverse),
-            10 => result.push(DecodeType::DecodeBytes),
-            11 => result.push(DecodeType::DecodeI32),
-            12 => result.push(DecodeType::DecodeI64),
-            _ => panic!("wrong type"),
-        }
-    }
-    result
-}
-
-
-fn validate_types(decode_types_all: &[DecodeType], encode_types: &[EncodeType]) -> bool {
-    let decode_types: Vec<DecodeType> = decode_types_all.iter().filter(|x| **x != DecodeType::Reverse).cloned().collect();
-    if encode_types.len() != decode_types.len() {
-        return false;
-    }
-    for (encode_type, decode_type) in encode_types.iter().zip(decode_types.iter()) {
-        match encode_type {
-            EncodeType::SortU8(_) => {
-                if *decode_type != DecodeType::DecodeU8 {
-                    return false;
+            DecodeType::DecodeF32Total => {
+                let key1_value = match &key1[pos] {
+                    EncodeType::SortF32Total(value) => value,
+                    _ => panic!("wrong type"),
+                };
+                let key2_value = match &key2[pos] {
+                    EncodeType::SortF32Total(value) => value,
+                    _ => panic!("wrong type"),
+                };
+                if is_reverse {
+                    key2_value.cmp(key1_value)
+                } else {
+                    key1_value.cmp(key2_value)
                 }
             },
-            EncodeType::SortU16(_) => {
-                if *decode_type != DecodeType::DecodeU16 {
-                    return false;
+            DecodeType::DecodeF64Total => {
+                let key1_value = match &key1[pos] {
+                    EncodeType::SortF64Total(value) => value,
+                    _ => panic!("wrong type"),
+                };
+                let key2_value = match &key2[pos] {
+                    EncodeType::SortF64Total(value) => value,
+                    _ => panic!("wrong type"),
+                };
+                if is_reverse {
+                    key2_value.cmp(key1_value)
+                } else {
+                    key1_value.cmp(key2_value)
                 }
             },
-            EncodeType::SortU32(_) => {
-                if *decode_type != DecodeType::DecodeU32 {
-                    return false;
-                }
+            DecodeType::DecodeTimestamp(_) => {
+                let key1_value = match &key1[pos] {
+                    EncodeType::SortTimestamp(value) => value,
+                    _ => panic!("wrong type"),
+                };
+                let key2_value = match &key2[pos] {
+                    EncodeType::SortTimestamp(value) => value,
+                    _ => panic!("wrong type"),
+                };
+                if is_reverse {
+                    key2_value.cmp(key1_value)
+                } else {
+                    key1_value.cmp(key2_value)
+                }
             },
-            EncodeType::SortU64(_) => {
-                if *decode_type != DecodeType::DecodeU64 {
-                    return false;
+            DecodeType::DecodeDate => {
+                let key1_value = match &key1[pos] {
+                    EncodeType::SortDate(value) => value,
+                    _ => panic!("wrong type"),
+                };
+                let key2_value = match &key2[pos] {
+                    EncodeType::SortDate(value) => value,
+                    _ => panic!("wrong type"),
+                };
+                if is_reverse {
+                    key2_value.cmp(key1_value)
+                } else {
+                    key1_value.cmp(key2_value)
                 }
             },
-            EncodeType::SortU128(_) => {
-                if *decode_type != DecodeType::DecodeU128 {
-                    return false;
+            DecodeType::DecodeDuration => {
+                let key1_value = match &key1[pos] {
+                    EncodeType::SortDuration(value) => value,
+                    _ => panic!("wrong type"),
+                };
+                let key2_value = match &key2[pos] {
+                    EncodeType::SortDuration(value) => value,
+                    _ => panic!("wrong type"),
+                };
+                if is_reverse {
+                    key2_value.cmp(key1_value)
+                } else {
+                    key1_value.cmp(key2_value)
+                }
+            },
+            DecodeType::DecodeFixedBytes(_) => {
+                let key1_value = match &key1[pos] {
+                    EncodeType::SortFixedBytes(value) => value,
+                    _ => panic!("wrong type"),
+                };
+                let key2_value = match &key2[pos] {
+                    EncodeType::SortFixedBytes(value) => value,
+                    _ => panic!("wrong type"),
+                };
+                if is_reverse {
+                    key2_value.cmp(key1_value)
+                } else {
+                    key1_value.cmp(key2_value)
                 }
             },
-            EncodeType::SortString(_) => {
-                if *decode_type != DecodeType::DecodeString {
-                    return false;
+            DecodeType::DecodeUuid => {
+                let key1_value = match &key1[pos] {
+                    EncodeType::SortUuid(value) => value,
+                    _ => panic!("wrong type"),
+                };
+                let key2_value = match &key2[pos] {
+                    EncodeType::SortUuid(value) => value,
+                    _ => panic!("wrong type"),
+                };
+                if is_reverse {
+                    key2_value.cmp(key1_value)
+                } else {
+                    key1_value.cmp(key2_value)
+                }
+            },
+            DecodeType::DecodeVarU64 => {
+                let key1_value = match &key1[pos] {
+                    EncodeType::SortVarU64(value) => value,
+                    _ => panic!("wrong type"),
+                };
+                let key2_value = match &key2[pos] {
+                    EncodeType::SortVarU64(value) => value,
+                    _ => panic!("wrong type"),
+                };
+                if is_reverse {
+                    key2_value.cmp(key1_value)
+                } else {
+                    key1_value.cmp(key2_value)
+                }
+            },
+            DecodeType::DecodeVarI64 => {
+                let key1_value = match &key1[pos] {
+                    EncodeType::SortVarI64(value) => value,
+                    _ => panic!("wrong type"),
+                };
+                let key2_value = match &key2[pos] {
+                    EncodeType::SortVarI64(value) => value,
+                    _ => panic!("wrong type"),
+                };
+                if is_reverse {
+                    key2_value.cmp(key1_value)
+                } else {
+                    key1_value.cmp(key2_value)
+                }
+            },
+            DecodeType::DecodeDecimal => {
+                let key1_value = match &key1[pos] {
+                    EncodeType::SortDecimal(value) => value,
+                    _ => panic!("wrong type"),
+                };
+                let key2_value = match &key2[pos] {
+                    EncodeType::SortDecimal(value) => value,
+                    _ => panic!("wrong type"),
+                };
+                if is_reverse {
+                    key2_value.cmp(key1_value)
+                } else {
+                    key1_value.cmp(key2_value)
                 }
             },
-            EncodeType::SortBool(_) => {
-                if *decode_type != DecodeType::DecodeBool {
-                    return false;
+            DecodeType::DecodeCollatedString(..) => {
+                let key1_value = match &key1[pos] {
+                    EncodeType::SortCollatedString(value) => value,
+                    _ => panic!("wrong type"),
+                };
+                let key2_value = match &key2[pos] {
+                    EncodeType::SortCollatedString(value) => value,
+                    _ => panic!("wrong type"),
+                };
+                if is_reverse {
+                    key2_value.cmp(key1_value)
+                } else {
+                    key1_value.cmp(key2_value)
                 }
             },
-            EncodeType::SortF32(_) => {
-                if *decode_type != DecodeType::DecodeF32 {
-                    return false;
+            DecodeType::DecodeTuple(inner) => {
+                let key1_value = match &key1[pos] {
+                    EncodeType::SortTuple(value) => value,
+                    _ => panic!("wrong type"),
+                };
+                let key2_value = match &key2[pos] {
+                    EncodeType::SortTuple(value) => value,
+                    _ => panic!("wrong type"),
+                };
+                if is_reverse {
+                    compare(&flip_directions(inner), key1_value, key2_value)
+                } else {
+                    compare(inner, key1_value, key2_value)
                 }
             },
-            EncodeType::SortF64(_) => {
-                if *decode_type != DecodeType::DecodeF64 {
-                    return false;
+            DecodeType::DecodeUnion(variants) => {
+                let (key1_variant, key1_value) = match &key1[pos] {
+                    EncodeType::SortUnion(variant, value) => (variant, value),
+                    _ => panic!("wrong type"),
+                };
+                let (key2_variant, key2_value) = match &key2[pos] {
+                    EncodeType::SortUnion(variant, value) => (variant, value),
+                    _ => panic!("wrong type"),
+                };
+                if key1_variant != key2_variant {
+                    if is_reverse {
+                        key2_variant.cmp(key1_variant)
+                    } else {
+                        key1_variant.cmp(key2_variant)
+                    }
+                } else {
+                    let the_type = variants[*key1_variant as usize].clone();
+                    let inner_types = if is_reverse { vec![DecodeType::Reverse, the_type] } else { vec![the_type] };
+                    compare(&inner_types, std::slice::from_ref(key1_value), std::slice::from_ref(key2_value))
                 }
             },
-            EncodeType::SortBytes(_) => {
-                if *decode_type != DecodeType::DecodeBytes {
-                    return false;
+            DecodeType::DecodeList(element) => {
+                let key1_value = match &key1[pos] {
+                    EncodeType::SortList(value) => value,
+                    _ => panic!("wrong type"),
+                };
+                let key2_value = match &key2[pos] {
+                    EncodeType::SortList(value) => value,
+                    _ => panic!("wrong type"),
+                };
+                let element_types = if is_reverse { vec![DecodeType::Reverse, (**element).clone()] } else { vec![(**element).clone()] };
+                let mut result = Ordering::Equal;
+                for (key1_element, key2_element) in key1_value.iter().zip(key2_value) {
+                    result = compare(&element_types, std::slice::from_ref(key1_element), std::slice::from_ref(key2_element));
+                    if result != Ordering::Equal {
+                        break;
+                    }
+                }
+                if result != Ordering::Equal {
+                    result
+                } else if is_reverse {
+                    key2_value.len().cmp(&key1_value.len())
+                } else {
+                    key1_value.len().cmp(&key2_value.len())
                 }
             },
-            EncodeType::SortI32(_) => {
-                if *decode_type != DecodeType::DecodeI32 {
-                    return false;
+            DecodeType::DecodeOption(inner, null_order) => {
+                let (key1_value, key2_value) = match (&key1[pos], &key2[pos]) {
+                    (EncodeType::SortOption(key1_value), EncodeType::SortOption(key2_value)) => (key1_value, key2_value),
+                    _ => panic!("wrong type"),
+                };
+                match (key1_value, key2_value) {
+                    (Some(key1_value), Some(key2_value)) => {
+                        let inner_types = if is_reverse { vec![DecodeType::Reverse, (**inner).clone()] } else { vec![(**inner).clone()] };
+                        compare(&inner_types, std::slice::from_ref(key1_value), std::slice::from_ref(key2_value))
+                    },
+                    _ => null_order.place(key1_value.is_some(), key2_value.is_some()),
                 }
             },
-            EncodeType::SortI64(_) => {
-                if *decode_type != DecodeType::DecodeI64 {
-                    return false;
+            // skipped above
+            DecodeType::Reverse => Ordering::Equal,
+        };
+        if result != Ordering::Equal {
+            return result;
+        }
+        pos += 1;
+        is_reverse = false;
+    }
+    Ordering::Equal
+}
+// the same fields with the direction of each one flipped, unlike reversing the result this keeps the nulls in place
+fn flip_directions(the_types: &[DecodeType]) -> Vec<DecodeType> {
+    let mut result = Vec::new();
+    let mut is_reverse = false;
+    for the_type in the_types {
+        if the_type == &DecodeType::Reverse {
+            is_reverse = true;
+            continue;
+        }
+        if !is_reverse {
+            result.push(DecodeType::Reverse);
+        }
+        result.push(the_type.clone());
+        is_reverse = false;
+    }
+    result
+}
+
+#[inline]
+pub fn compare_bytes(the_types: &[DecodeType], key1: &[u8], key2: &[u8]) -> Ordering {
+    compare_encoded(the_types, key1, key2)
+}
+
+#[inline]
+fn compare_field(the_type: &DecodeType, key1: &[u8], pos1: &mut usize, key2: &[u8], pos2: &mut usize, is_reverse: bool) -> Result<Ordering, KeyError> {
+    let result = match the_type {
+        // big endian unsigned integers sort the same as their bytes
+        DecodeType::DecodeU8 => fixed::<1>(key1, pos1)?.cmp(&fixed::<1>(key2, pos2)?),
+        DecodeType::DecodeU16 => fixed::<2>(key1, pos1)?.cmp(&fixed::<2>(key2, pos2)?),
+        DecodeType::DecodeU32 => fixed::<4>(key1, pos1)?.cmp(&fixed::<4>(key2, pos2)?),
+        DecodeType::DecodeU64 => fixed::<8>(key1, pos1)?.cmp(&fixed::<8>(key2, pos2)?),
+        DecodeType::DecodeU128 => fixed::<16>(key1, pos1)?.cmp(&fixed::<16>(key2, pos2)?),
+        DecodeType::DecodeI8 => i8::from_be_bytes(fixed(key1, pos1)?).cmp(&i8::from_be_bytes(fixed(key2, pos2)?)),
+        DecodeType::DecodeI16 => i16::from_be_bytes(fixed(key1, pos1)?).cmp(&i16::from_be_bytes(fixed(key2, pos2)?)),
+        DecodeType::DecodeI32 => i32::from_be_bytes(fixed(key1, pos1)?).cmp(&i32::from_be_bytes(fixed(key2, pos2)?)),
+        DecodeType::DecodeI64 => i64::from_be_bytes(fixed(key1, pos1)?).cmp(&i64::from_be_bytes(fixed(key2, pos2)?)),
+        DecodeType::DecodeI128 => i128::from_be_bytes(fixed(key1, pos1)?).cmp(&i128::from_be_bytes(fixed(key2, pos2)?)),
+        // String::cmp is a byte wise comparison of the utf-8 data
+        DecodeType::DecodeString | DecodeType::DecodeBytes => length_prefixed(key1, pos1)?.cmp(length_prefixed(key2, pos2)?),
+        DecodeType::DecodeBool => (fixed::<1>(key1, pos1)?[0] == 1).cmp(&(fixed::<1>(key2, pos2)?[0] == 1)),
+        // decoded, so bytes that no value is written as, e.g. another NaN, still compare like their value
+        DecodeType::DecodeF32 => F32struct::from_sortable_bits(u32::from_be_bytes(fixed(key1, pos1)?)).cmp(&F32struct::from_sortable_bits(u32::from_be_bytes(fixed(key2, pos2)?))),
+        DecodeType::DecodeF64 => F64struct::from_sortable_bits(u64::from_be_bytes(fixed(key1, pos1)?)).cmp(&F64struct::from_sortable_bits(u64::from_be_bytes(fixed(key2, pos2)?))),
+        // written in an order preserving form
+        DecodeType::DecodeF32Total => fixed::<4>(key1, pos1)?.cmp(&fixed::<4>(key2, pos2)?),
+        DecodeType::DecodeF64Total => fixed::<8>(key1, pos1)?.cmp(&fixed::<8>(key2, pos2)?),
+        DecodeType::DecodeTimestamp(_) => i64::from_be_bytes(fixed(key1, pos1)?).cmp(&i64::from_be_bytes(fixed(key2, pos2)?)),
+        DecodeType::DecodeDate => i32::from_be_bytes(fixed(key1, pos1)?).cmp(&i32::from_be_bytes(fixed(key2, pos2)?)),
+        // big endian seconds followed by big endian nanoseconds
+        DecodeType::DecodeDuration => fixed::<12>(key1, pos1)?.cmp(&fixed::<12>(key2, pos2)?),
+        DecodeType::DecodeFixedBytes(len) => take(key1, pos1, *len)?.cmp(take(key2, pos2, *len)?),
+        DecodeType::DecodeUuid => fixed::<16>(key1, pos1)?.cmp(&fixed::<16>(key2, pos2)?),
+        // the header and bytes of a variable length integer sort like its value
+        DecodeType::DecodeVarU64 => take_var_int(key1, pos1, false)?.cmp(take_var_int(key2, pos2, false)?),
+        DecodeType::DecodeVarI64 => take_var_int(key1, pos1, true)?.cmp(take_var_int(key2, pos2, true)?),
+        DecodeType::DecodeDecimal => take_decimal(key1, pos1)?.cmp(take_decimal(key2, pos2)?),
+        // the stored sort keys, then the strings
+        DecodeType::DecodeCollatedString(_, true) => {
+            let sort_key1 = length_prefixed(key1, pos1)?;
+            let sort_key2 = length_prefixed(key2, pos2)?;
+            sort_key1.cmp(sort_key2).then(length_prefixed(key1, pos1)?.cmp(length_prefixed(key2, pos2)?))
+        },
+        DecodeType::DecodeCollatedString(collation, false) => collation.compare(utf8_string(key1, pos1)?, utf8_string(key2, pos2)?),
+        DecodeType::DecodeTuple(inner) => {
+            let mut inner_reverse = false;
+            for the_type in inner {
+                if the_type == &DecodeType::Reverse {
+                    inner_reverse = true;
+                    continue;
+                }
+                let result = compare_field(the_type, key1, pos1, key2, pos2, is_reverse != inner_reverse)?;
+                if result != Ordering::Equal {
+                    return Ok(result);
+                }
+                inner_reverse = false;
+            }
+            return Ok(Ordering::Equal);
+        },
+        DecodeType::DecodeUnion(variants) => {
+            let start = *pos1;
+            let key1_variant = fixed::<1>(key1, pos1)?[0];
+            let key2_variant = fixed::<1>(key2, pos2)?[0];
+            if key1_variant != key2_variant {
+                key1_variant.cmp(&key2_variant)
+            } else {
+                let the_type = variants.get(key1_variant as usize).ok_or(KeyError::InvalidEncoding { field: 0, offset: start })?;
+                return compare_field(the_type, key1, pos1, key2, pos2, is_reverse);
+            }
+        },
+        DecodeType::DecodeList(element) => {
+            loop {
+                let key1_more = list_marker(key1, pos1)?;
+                let key2_more = list_marker(key2, pos2)?;
+                if !key1_more || !key2_more {
+                    break key1_more.cmp(&key2_more);
+                }
+                let result = compare_field(element, key1, pos1, key2, pos2, is_reverse)?;
+                if result != Ordering::Equal {
+                    return Ok(result);
+                }
+            }
+        },
+        DecodeType::DecodeOption(inner, null_order) => {
+            let key1_present = presence_marker(key1, pos1)?;
+            let key2_present = presence_marker(key2, pos2)?;
+            if key1_present && key2_present {
+                return compare_field(inner, key1, pos1, key2, pos2, is_reverse);
+            }
+            // the place of the nulls is not reversed
+            return Ok(null_order.place(key1_present, key2_present));
+        },
+        // skipped by the caller
+        DecodeType::Reverse => Ordering::Equal,
+    };
+    Ok(if is_reverse { result.reverse() } else { result })
+}
+
+// true when another element follows
+#[inline]
+fn list_marker(data: &[u8], pos: &mut usize) -> Result<bool, KeyError> {
+    let start = *pos;
+    match fixed::<1>(data, pos)?[0] {
+        LIST_ELEMENT => Ok(true),
+        LIST_END => Ok(false),
+        _ => Err(KeyError::InvalidEncoding { field: 0, offset: start }),
+    }
+}
+
+#[inline]
+fn presence_marker(data: &[u8], pos: &mut usize) -> Result<bool, KeyError> {
+    let start = *pos;
+    match fixed::<1>(data, pos)?[0] {
+        NULL_MARKER => Ok(false),
+        PRESENT_MARKER => Ok(true),
+        _ => Err(KeyError::InvalidEncoding { field: 0, offset: start }),
+    }
+}
+
+/// Moves `pos` past one field written by `encode_keys` without decoding it, checking only what `compare_field` needs to read it.
+#[inline]
+pub(crate) fn skip_field(the_type: &DecodeType, data: &[u8], pos: &mut usize) -> Result<(), KeyError> {
+    let len = match the_type {
+        DecodeType::DecodeU8 | DecodeType::DecodeI8 | DecodeType::DecodeBool => 1,
+        DecodeType::DecodeU16 | DecodeType::DecodeI16 => 2,
+        DecodeType::DecodeU32 | DecodeType::DecodeI32 | DecodeType::DecodeF32 | DecodeType::DecodeF32Total | DecodeType::DecodeDate => 4,
+        DecodeType::DecodeU64 | DecodeType::DecodeI64 | DecodeType::DecodeF64 | DecodeType::DecodeF64Total | DecodeType::DecodeTimestamp(_) => 8,
+        DecodeType::DecodeDuration => 12,
+        DecodeType::DecodeU128 | DecodeType::DecodeI128 | DecodeType::DecodeUuid => 16,
+        DecodeType::DecodeFixedBytes(len) => *len,
+        DecodeType::DecodeString | DecodeType::DecodeBytes => return length_prefixed(data, pos).map(|_| ()),
+        // compared by collation, which needs the utf-8 string
+        DecodeType::DecodeCollatedString(_, false) => return utf8_string(data, pos).map(|_| ()),
+        // the sort key, then the string
+        DecodeType::DecodeCollatedString(_, true) => {
+            length_prefixed(data, pos)?;
+            return length_prefixed(data, pos).map(|_| ());
+        },
+        DecodeType::DecodeVarU64 => return take_var_int(data, pos, false).map(|_| ()),
+        DecodeType::DecodeVarI64 => return take_var_int(data, pos, true).map(|_| ()),
+        DecodeType::DecodeDecimal => return take_decimal(data, pos).map(|_| ()),
+        DecodeType::DecodeTuple(inner) => {
+            for the_type in inner {
+                if the_type != &DecodeType::Reverse {
+                    skip_field(the_type, data, pos)?;
                 }
+            }
+            return Ok(());
+        },
+        DecodeType::DecodeUnion(variants) => {
+            let start = *pos;
+            let variant = fixed::<1>(data, pos)?[0];
+            let the_type = variants.get(variant as usize).ok_or(KeyError::InvalidEncoding { field: 0, offset: start })?;
+            return skip_field(the_type, data, pos);
+        },
+        DecodeType::DecodeList(element) => {
+            while list_marker(data, pos)? {
+                skip_field(element, data, pos)?;
+            }
+            return Ok(());
+        },
+        DecodeType::DecodeOption(inner, _) => {
+            if presence_marker(data, pos)? {
+                skip_field(inner, data, pos)?;
+            }
+            return Ok(());
+        },
+        DecodeType::Reverse => 0,
+    };
+    take(data, pos, len).map(|_| ())
+}
+
+/// Compares two keys written by `encode_keys` field by field, without decoding them.
+/// Gives the same result as `compare` on the decoded keys, but does not allocate and stops at the first field that differs.
+/// A key may hold only the leading fields of the schema, it then sorts before every key that starts with the same fields.
+/// A field that can not be read sorts after every value of that field, and two of them by the bytes from the field on,
+/// bytes after the last field sort like such a field. A corrupt key in the database does not abort the comparator and the order stays consistent.
+#[inline]
+pub fn compare_encoded(the_types: &[DecodeType], key1: &[u8], key2: &[u8]) -> Ordering {
+    compare_encoded_with(the_types, PrefixOrder::ShorterFirst, key1, key2)
+}
+
+#[inline]
+pub fn try_compare_encoded(the_types: &[DecodeType], key1: &[u8], key2: &[u8]) -> Result<Ordering, KeyError> {
+    try_compare_encoded_with(the_types, PrefixOrder::ShorterFirst, key1, key2)
+}
+
+/// Like `compare_encoded`, with keys that hold only the leading fields of the schema placed by `prefix_order`.
+#[inline]
+pub fn compare_encoded_with(the_types: &[DecodeType], prefix_order: PrefixOrder, key1: &[u8], key2: &[u8]) -> Ordering {
+    // the keys are compared like sequences of fields, where an unreadable field is one more value after all others,
+    // so the order stays transitive without reading the fields after the first one that differs
+    let mut pos1 = 0;
+    let mut pos2 = 0;
+    let mut is_reverse = false;
+    for the_type in the_types {
+        if the_type == &DecodeType::Reverse {
+            is_reverse = true;
+            continue;
+        }
+        if pos1 == key1.len() || pos2 == key2.len() {
+            return prefix_order.place(pos1 < key1.len(), pos2 < key2.len());
+        }
+        let (start1, start2) = (pos1, pos2);
+        match compare_field(the_type, key1, &mut pos1, key2, &mut pos2, is_reverse) {
+            Ok(Ordering::Equal) => is_reverse = false,
+            Ok(result) => return result,
+            Err(_) => {
+                let key1_readable = skip_field(the_type, key1, &mut start1.clone()).is_ok();
+                let key2_readable = skip_field(the_type, key2, &mut start2.clone()).is_ok();
+                return key2_readable.cmp(&key1_readable).then_with(|| key1[start1..].cmp(&key2[start2..]));
             },
         }
     }
-    true
+    // bytes after the last field, an empty rest sorts first
+    key1[pos1..].cmp(&key2[pos2..])
+}
+
+#[inline]
+pub fn try_compare_encoded_with(the_types: &[DecodeType], prefix_order: PrefixOrder, key1: &[u8], key2: &[u8]) -> Result<Ordering, KeyError> {
+    let (result, key1_has_more, key2_has_more) = try_compare_common_fields(the_types, key1, key2)?;
+    if result != Ordering::Equal {
+        return Ok(result);
+    }
+    Ok(prefix_order.place(key1_has_more, key2_has_more))
+}
+
+/// Compares the fields that both keys have. When they are equal, also tells which of the keys has more fields.
+#[inline]
+pub(crate) fn try_compare_common_fields(the_types: &[DecodeType], key1: &[u8], key2: &[u8]) -> Result<(Ordering, bool, bool), KeyError> {
+    let mut pos1 = 0;
+    let mut pos2 = 0;
+    let mut is_reverse = false;
+    let mut field = 0;
+    for the_type in the_types {
+        if the_type == &DecodeType::Reverse {
+            is_reverse = true;
+            continue;
+        }
+        if pos1 == key1.len() || pos2 == key2.len() {
+            return Ok((Ordering::Equal, pos1 < key1.len(), pos2 < key2.len()));
+        }
+        let result = compare_field(the_type, key1, &mut pos1, key2, &mut pos2, is_reverse).map_err(|e| e.at_field(field))?;
+        if result != Ordering::Equal {
+            return Ok((result, false, false));
+        }
+        is_reverse = false;
+        field += 1;
+    }
+    Ok((Ordering::Equal, false, false))
+}
+
+
+pub fn serialize_decode_types(the_types: &[DecodeType]) -> Vec<u8> {
+    let mut result = Vec::new();
+    for the_type in the_types {
+        serialize_decode_type(the_type, &mut result);
+    }
+    result
+}
+
+// a nullable type is written as its tag followed by the tags of the inner type
+fn serialize_decode_type(the_type: &DecodeType, result: &mut Vec<u8>) {
+    match the_type {
+        DecodeType::DecodeU8 => result.push(0),
+        DecodeType::DecodeU16 => result.push(1),
+        DecodeType::DecodeU32 => result.push(2),
+        DecodeType::DecodeU64 => result.push(3),
+        DecodeType::DecodeU128 => result.push(4),
+        DecodeType::DecodeString => result.push(5),
+        DecodeType::DecodeBool => result.push(6),
+        // 7 and 8 were the floats as raw IEEE bytes, the sortable bits have new tags so old schemas are refused
+        DecodeType::DecodeF32 => result.push(32),
+        DecodeType::DecodeF64 => result.push(33),
+        DecodeType::Reverse => result.push(9),
+        DecodeType::DecodeBytes => result.push(10),
+        DecodeType::DecodeI32 => result.push(11),
+        DecodeType::DecodeI64 => result.push(12),
+        DecodeType::DecodeOption(inner, NullOrder::NullsFirst) => {
+            result.push(13);
+            serialize_decode_type(inner, result);
+        },
+        DecodeType::DecodeOption(inner, NullOrder::NullsLast) => {
+            result.push(14);
+            serialize_decode_type(inner, result);
+        },
+        DecodeType::DecodeI8 => result.push(15),
+        DecodeType::DecodeI16 => result.push(16),
+        DecodeType::DecodeI128 => result.push(17),
+        DecodeType::DecodeF32Total => result.push(18),
+        DecodeType::DecodeF64Total => result.push(19),
+        DecodeType::DecodeTimestamp(unit) => {
+            result.push(20);
+            result.push(match unit {
+                TimeUnit::Seconds => 0,
+                TimeUnit::Millis => 1,
+                TimeUnit::Micros => 2,
+                TimeUnit::Nanos => 3,
+            });
+        },
+        DecodeType::DecodeDate => result.push(21),
+        DecodeType::DecodeDuration => result.push(22),
+        DecodeType::DecodeFixedBytes(len) => {
+            result.push(23);
+            result.extend((*len as u32).to_be_bytes());
+        },
+        DecodeType::DecodeUuid => result.push(24),
+        DecodeType::DecodeVarU64 => result.push(25),
+        DecodeType::DecodeVarI64 => result.push(26),
+        DecodeType::DecodeDecimal => result.push(27),
+        DecodeType::DecodeCollatedString(collation, stored_sort_key) => {
+            result.push(28);
+            result.push(match collation {
+                Collation::Binary => 0,
+                Collation::AsciiCaseFold => 1,
+                Collation::UnicodeCaseFold => 2,
+                Collation::Nfc => 3,
+                Collation::Nfkc => 4,
+                Collation::NfkcCaseFold => 5,
+            });
+            result.push(*stored_sort_key as u8);
+        },
+        DecodeType::DecodeTuple(inner) => {
+            result.push(29);
+            result.extend((inner.len() as u32).to_be_bytes());
+            for the_type in inner {
+                serialize_decode_type(the_type, result);
+            }
+        },
+        DecodeType::DecodeList(element) => {
+            result.push(31);
+            serialize_decode_type(element, result);
+        },
+        DecodeType::DecodeUnion(variants) => {
+            result.push(30);
+            result.extend((variants.len() as u32).to_be_bytes());
+            for the_type in variants {
+                serialize_decode_type(the_type, result);
+            }
+        },
+    }
+}
+
+pub fn deserialize_decode_types(the_types: &[u8]) -> Vec<DecodeType> {
+    match try_deserialize_decode_types(the_types) {
+        Ok(result) => result,
+        Err(error) => panic!("{}", error),
+    }
+}
+
+pub fn try_deserialize_decode_types(the_types: &[u8]) -> Result<Vec<DecodeType>, KeyError> {
+    let mut result = Vec::new();
+    let mut pos = 0;
+    while pos < the_types.len() {
+        result.push(deserialize_decode_type(the_types, &mut pos)?);
+    }
+    Ok(result)
+}
+
+fn deserialize_decode_type(the_types: &[u8], pos: &mut usize) -> Result<DecodeType, KeyError> {
+    let index = *pos;
+    let tag = match the_types.get(index) {
+        Some(tag) => *tag,
+        None => return Err(KeyError::Truncated { field: 0, offset: index, needed: 1, available: 0 }),
+    };
+    *pos += 1;
+    let the_type = match tag {
+        0 => DecodeType::DecodeU8,
+        1 => DecodeType::DecodeU16,
+        2 => DecodeType::DecodeU32,
+        3 => DecodeType::DecodeU64,
+        4 => DecodeType::DecodeU128,
+        5 => DecodeType::DecodeString,
+        6 => DecodeType::DecodeBool,
+        7 | 8 => return Err(KeyError::InvalidDescriptor { reason: "f32 or f64 field written as raw IEEE bytes by an earlier version" }),
+        9 => DecodeType::Reverse,
+        10 => DecodeType::DecodeBytes,
+        11 => DecodeType::DecodeI32,
+        12 => DecodeType::DecodeI64,
+        13 | 14 => {
+            let inner = deserialize_decode_type(the_types, pos)?;
+            if inner == DecodeType::Reverse {
+                return Err(KeyError::InvalidEncoding { field: 0, offset: *pos - 1 });
+            }
+            let null_order = if tag == 13 { NullOrder::NullsFirst } else { NullOrder::NullsLast };
+            DecodeType::DecodeOption(Box::new(inner), null_order)
+        },
+        15 => DecodeType::DecodeI8,
+        16 => DecodeType::DecodeI16,
+        17 => DecodeType::DecodeI128,
+        18 => DecodeType::DecodeF32Total,
+        19 => DecodeType::DecodeF64Total,
+        20 => {
+            let unit = match the_types.get(*pos) {
+                Some(0) => TimeUnit::Seconds,
+                Some(1) => TimeUnit::Millis,
+                Some(2) => TimeUnit::Micros,
+                Some(3) => TimeUnit::Nanos,
+                Some(unit) => return Err(KeyError::UnknownTypeTag { index: *pos, tag: *unit }),
+                None => return Err(KeyError::Truncated { field: 0, offset: *pos, needed: 1, available: 0 }),
+            };
+            *pos += 1;
+            DecodeType::DecodeTimestamp(unit)
+        },
+        21 => DecodeType::DecodeDate,
+        22 => DecodeType::DecodeDuration,
+        23 => DecodeType::DecodeFixedBytes(u32::from_be_bytes(fixed(the_types, pos)?) as usize),
+        24 => DecodeType::DecodeUuid,
+        25 => DecodeType::DecodeVarU64,
+        26 => DecodeType::DecodeVarI64,
+        27 => DecodeType::DecodeDecimal,
+        28 => {
+            let collation = match the_types.get(*pos) {
+                Some(0) => Collation::Binary,
+                Some(1) => Collation::AsciiCaseFold,
+                Some(2) => Collation::UnicodeCaseFold,
+                Some(3) => Collation::Nfc,
+                Some(4) => Collation::Nfkc,
+                Some(5) => Collation::NfkcCaseFold,
+                Some(collation) => return Err(KeyError::UnknownTypeTag { index: *pos, tag: *collation }),
+                None => return Err(KeyError::Truncated { field: 0, offset: *pos, needed: 1, available: 0 }),
+            };
+            *pos += 1;
+            let stored_sort_key = match the_types.get(*pos) {
+                Some(0) => false,
+                Some(1) => true,
+                Some(flag) => return Err(KeyError::UnknownTypeTag { index: *pos, tag: *flag }),
+                None => return Err(KeyError::Truncated { field: 0, offset: *pos, needed: 1, available: 0 }),
+            };
+            *pos += 1;
+            DecodeType::DecodeCollatedString(collation, stored_sort_key)
+        },
+        29 => {
+            let len = u32::from_be_bytes(fixed(the_types, pos)?);
+            let mut inner = Vec::new();
+            for _ in 0..len {
+                inner.push(deserialize_decode_type(the_types, pos)?);
+            }
+            DecodeType::DecodeTuple(inner)
+        },
+        30 => {
+            let len = u32::from_be_bytes(fixed(the_types, pos)?);
+            if len > 256 {
+                return Err(KeyError::InvalidEncoding { field: 0, offset: index + 1 });
+            }
+            let mut variants = Vec::new();
+            for _ in 0..len {
+                let the_type = deserialize_decode_type(the_types, pos)?;
+                if the_type == DecodeType::Reverse {
+                    return Err(KeyError::InvalidEncoding { field: 0, offset: *pos - 1 });
+                }
+                variants.push(the_type);
+            }
+            DecodeType::DecodeUnion(variants)
+        },
+        31 => {
+            let element = deserialize_decode_type(the_types, pos)?;
+            if element == DecodeType::Reverse {
+                return Err(KeyError::InvalidEncoding { field: 0, offset: *pos - 1 });
+            }
+            DecodeType::DecodeList(Box::new(element))
+        },
+        32 => DecodeType::DecodeF32,
+        33 => DecodeType::DecodeF64,
+        tag => return Err(KeyError::UnknownTypeTag { index, tag }),
+    };
+    Ok(the_type)
+}
+
+
+#[inline]
+pub(crate) fn matches_type(encode_type: &EncodeType, decode_type: &DecodeType) -> bool {
+    match encode_type {
+        EncodeType::SortU8(_) => *decode_type == DecodeType::DecodeU8,
+        EncodeType::SortU16(_) => *decode_type == DecodeType::DecodeU16,
+        EncodeType::SortU32(_) => *decode_type == DecodeType::DecodeU32,
+        EncodeType::SortU64(_) => *decode_type == DecodeType::DecodeU64,
+        EncodeType::SortU128(_) => *decode_type == DecodeType::DecodeU128,
+        EncodeType::SortString(_) => *decode_type == DecodeType::DecodeString,
+        EncodeType::SortBool(_) => *decode_type == DecodeType::DecodeBool,
+        EncodeType::SortF32(_) => *decode_type == DecodeType::DecodeF32,
+        EncodeType::SortF64(_) => *decode_type == DecodeType::DecodeF64,
+        EncodeType::SortBytes(_) => *decode_type == DecodeType::DecodeBytes,
+        EncodeType::SortI32(_) => *decode_type == DecodeType::DecodeI32,
+        EncodeType::SortI64(_) => *decode_type == DecodeType::DecodeI64,
+        EncodeType::SortI8(_) => *decode_type == DecodeType::DecodeI8,
+        EncodeType::SortI16(_) => *decode_type == DecodeType::DecodeI16,
+        EncodeType::SortI128(_) => *decode_type == DecodeType::DecodeI128,
+        EncodeType::SortF32Total(_) => *decode_type == DecodeType::DecodeF32Total,
+        EncodeType::SortF64Total(_) => *decode_type == DecodeType::DecodeF64Total,
+        EncodeType::SortTimestamp(value) => *decode_type == DecodeType::DecodeTimestamp(value.unit),
+        EncodeType::SortDate(_) => *decode_type == DecodeType::DecodeDate,
+        EncodeType::SortDuration(_) => *decode_type == DecodeType::DecodeDuration,
+        EncodeType::SortFixedBytes(value) => *decode_type == DecodeType::DecodeFixedBytes(value.len()),
+        EncodeType::SortUuid(_) => *decode_type == DecodeType::DecodeUuid,
+        EncodeType::SortVarU64(_) => *decode_type == DecodeType::DecodeVarU64,
+        EncodeType::SortVarI64(_) => *decode_type == DecodeType::DecodeVarI64,
+        EncodeType::SortDecimal(_) => *decode_type == DecodeType::DecodeDecimal,
+        EncodeType::SortCollatedString(value) => *decode_type == DecodeType::DecodeCollatedString(value.collation, value.stored_sort_key),
+        EncodeType::SortTuple(values) => match decode_type {
+            DecodeType::DecodeTuple(inner) => try_validate_types(inner, values).is_ok(),
+            _ => false,
+        },
+        EncodeType::SortUnion(variant, value) => match decode_type {
+            DecodeType::DecodeUnion(variants) => variants.get(*variant as usize).is_some_and(|the_type| matches_type(value, the_type)),
+            _ => false,
+        },
+        EncodeType::SortList(values) => match decode_type {
+            DecodeType::DecodeList(element) => values.iter().all(|value| matches_type(value, element)),
+            _ => false,
+        },
+        EncodeType::SortOption(value) => match (value, decode_type) {
+            (None, DecodeType::DecodeOption(..)) => true,
+            (Some(value), DecodeType::DecodeOption(inner, _)) => matches_type(value, inner),
+            _ => false,
+        },
+    }
+}
+
+/// Checks that `encode_types` has one value of the right type for every field in `decode_types_all`,
+/// and that every `DecodeType::Reverse` is followed by a field.
+pub fn validate_types(decode_types_all: &[DecodeType], encode_types: &[EncodeType]) -> bool {
+    try_validate_types(decode_types_all, encode_types).is_ok()
+}
+
+/// Like `validate_types`, but reports the first field that does not match.
+pub fn try_validate_types(decode_types_all: &[DecodeType], encode_types: &[EncodeType]) -> Result<(), KeyError> {
+    check_reverse(decode_types_all)?;
+    let decode_types: Vec<&DecodeType> = decode_types_all.iter().filter(|x| **x != DecodeType::Reverse).collect();
+    for field in 0..encode_types.len().max(decode_types.len()) {
+        match (encode_types.get(field), decode_types.get(field)) {
+            (Some(encode_type), Some(decode_type)) if matches_type(encode_type, decode_type) => continue,
+            (found, expected) => return Err(KeyError::SchemaMismatch { field, expected: expected.map(|x| (*x).clone()), found: found.cloned() }),
+        }
+    }
+    Ok(())
 }
 
 #[cfg(test)]
@@ -530,6 +1655,346 @@ mod tests {
         assert_eq!(result, Ordering::Greater);
     }
 
+    #[test]
+    fn test_compare_signed_and_bytes() {
+        let key1 = vec![EncodeType::SortBytes(vec![1, 2]), EncodeType::SortI64(-5), EncodeType::SortI32(-1)];
+        let key2 = vec![EncodeType::SortBytes(vec![1, 2]), EncodeType::SortI64(3), EncodeType::SortI32(-1)];
+        let key3 = vec![EncodeType::SortBytes(vec![1, 3]), EncodeType::SortI64(-9), EncodeType::SortI32(-1)];
+        let the_types = vec![DecodeType::DecodeBytes, DecodeType::DecodeI64, DecodeType::Reverse, DecodeType::DecodeI32];
+        assert_eq!(compare(&the_types, &key1, &key2), Ordering::Less);
+        assert_eq!(compare(&the_types, &key2, &key3), Ordering::Less);
+        assert_eq!(compare(&the_types, &key3, &key3), Ordering::Equal);
+    }
+
+    #[test]
+    fn test_small_and_wide_signed_integers() {
+        let the_types = vec![DecodeType::DecodeI8, DecodeType::Reverse, DecodeType::DecodeI16, DecodeType::DecodeI128];
+        let mut keys = Vec::new();
+        for a in [i8::MIN, -1, 0, 1, i8::MAX] {
+            for b in [i16::MIN, -256, 0, 255, i16::MAX] {
+                for c in [i128::MIN, -1, 0, 1 << 100, i128::MAX] {
+                    keys.push(vec![EncodeType::SortI8(a), EncodeType::SortI16(b), EncodeType::SortI128(c)]);
+                }
+            }
+        }
+        for key1 in &keys {
+            let encoded_key1 = encode_keys(key1);
+            assert_eq!(&try_decode_byte_array(&encoded_key1, &the_types).unwrap(), key1);
+            for key2 in &keys {
+                let expected = key1[0].cmp(&key2[0]).then(key2[1].cmp(&key1[1])).then(key1[2].cmp(&key2[2]));
+                assert_eq!(compare(&the_types, key1, key2), expected);
+                assert_eq!(compare_encoded(&the_types, &encoded_key1, &encode_keys(key2)), expected);
+            }
+        }
+        assert_eq!(deserialize_decode_types(&serialize_decode_types(&the_types)), the_types);
+        assert!(validate_types(&the_types, &keys[0]));
+    }
+
+    #[test]
+    fn test_total_order_floats() {
+        let the_types = vec![DecodeType::DecodeF32Total, DecodeType::Reverse, DecodeType::DecodeF64Total];
+        let floats = [-f64::NAN, f64::NEG_INFINITY, -1.5, -0.0, 0.0, 1e-300, 2.0, f64::INFINITY, f64::NAN];
+        let mut keys = Vec::new();
+        for a in floats {
+            for b in floats {
+                keys.push(vec![EncodeType::SortF32Total(F32total::new(a as f32)), EncodeType::SortF64Total(F64total::new(b))]);
+            }
+        }
+        for key1 in &keys {
+            let encoded_key1 = encode_keys(key1);
+            assert_eq!(&try_decode_byte_array(&encoded_key1, &the_types).unwrap(), key1);
+            for key2 in &keys {
+                let expected = key1[0].cmp(&key2[0]).then(key2[1].cmp(&key1[1]));
+                assert_eq!(compare(&the_types, key1, key2), expected);
+                assert_eq!(compare_encoded(&the_types, &encoded_key1, &encode_keys(key2)), expected);
+            }
+        }
+        // without a reverse the bytes sort in the total order
+        let ascending = vec![DecodeType::DecodeF64Total];
+        for pair in floats.windows(2) {
+            let one = encode_keys(&[EncodeType::SortF64Total(F64total::new(pair[0]))]);
+            let two = encode_keys(&[EncodeType::SortF64Total(F64total::new(pair[1]))]);
+            assert_eq!(one.cmp(&two), Ordering::Less);
+            assert_eq!(compare_encoded(&ascending, &one, &two), Ordering::Less);
+        }
+        assert_eq!(deserialize_decode_types(&serialize_decode_types(&the_types)), the_types);
+    }
+
+    #[test]
+    fn test_time_fields() {
+        let the_types = vec![DecodeType::DecodeDate, DecodeType::Reverse, DecodeType::DecodeTimestamp(TimeUnit::Millis), DecodeType::DecodeDuration];
+        let mut keys = Vec::new();
+        for days in [-1, 0, 19_000] {
+            for millis in [i64::MIN, -1, 0, 1_700_000_000_000] {
+                for duration in [Duration::ZERO, Duration::new(0, 999_999_999), Duration::new(1, 0), Duration::MAX] {
+                    keys.push(vec![EncodeType::SortDate(Date::new(days)), EncodeType::SortTimestamp(Timestamp::new(millis, TimeUnit::Millis)), EncodeType::SortDuration(duration)]);
+                }
+            }
+        }
+        for key1 in &keys {
+            let encoded_key1 = encode_keys(key1);
+            assert_eq!(&try_decode_byte_array(&encoded_key1, &the_types).unwrap(), key1);
+            for key2 in &keys {
+                let expected = key1[0].cmp(&key2[0]).then(key2[1].cmp(&key1[1])).then(key1[2].cmp(&key2[2]));
+                assert_eq!(compare(&the_types, key1, key2), expected);
+                assert_eq!(compare_encoded(&the_types, &encoded_key1, &encode_keys(key2)), expected);
+            }
+        }
+        assert_eq!(deserialize_decode_types(&serialize_decode_types(&the_types)), the_types);
+        assert_eq!(try_deserialize_decode_types(&[20, 9]), Err(KeyError::UnknownTypeTag { index: 1, tag: 9 }));
+        assert!(!validate_types(&the_types, &[EncodeType::SortDate(Date::new(0)), EncodeType::SortTimestamp(Timestamp::new(0, TimeUnit::Micros)), EncodeType::SortDuration(Duration::ZERO)]));
+        let invalid_nanos = [encode_keys(&keys[0])[..20].to_vec(), 1_000_000_000u32.to_be_bytes().to_vec()].concat();
+        assert_eq!(try_decode_byte_array(&invalid_nanos, &the_types), Err(KeyError::InvalidEncoding { field: 2, offset: 20 }));
+    }
+
+    #[test]
+    fn test_fixed_bytes_and_uuid() {
+        let the_types = vec![DecodeType::DecodeFixedBytes(3), DecodeType::Reverse, DecodeType::DecodeUuid, DecodeType::DecodeFixedBytes(0)];
+        let mut keys = Vec::new();
+        for bytes in [[0, 0, 0], [0, 0, 1], [0, 255, 0], [1, 0, 0]] {
+            for uuid in [[0; 16], [1; 16], [255; 16]] {
+                keys.push(vec![EncodeType::SortFixedBytes(bytes.to_vec()), EncodeType::SortUuid(uuid), EncodeType::SortFixedBytes(vec![])]);
+            }
+        }
+        for key1 in &keys {
+            let encoded_key1 = encode_keys(key1);
+            assert_eq!(encoded_key1.len(), 19);
+            assert_eq!(&try_decode_byte_array(&encoded_key1, &the_types).unwrap(), key1);
+            for key2 in &keys {
+                let expected = key1[0].cmp(&key2[0]).then(key2[1].cmp(&key1[1]));
+                assert_eq!(compare(&the_types, key1, key2), expected);
+                assert_eq!(compare_encoded(&the_types, &encoded_key1, &encode_keys(key2)), expected);
+            }
+        }
+        assert_eq!(serialize_decode_types(&the_types), vec![23, 0, 0, 0, 3, 9, 24, 23, 0, 0, 0, 0]);
+        assert_eq!(deserialize_decode_types(&serialize_decode_types(&the_types)), the_types);
+        assert!(try_deserialize_decode_types(&[23, 0, 0]).is_err());
+        assert!(!validate_types(&the_types, &[EncodeType::SortFixedBytes(vec![1, 2]), EncodeType::SortUuid([0; 16]), EncodeType::SortFixedBytes(vec![])]));
+    }
+
+    #[test]
+    fn test_var_ints() {
+        let unsigned = [0, 1, 255, 256, 65_535, 1 << 40, u64::MAX - 1, u64::MAX];
+        let signed = [i64::MIN, i64::MIN + 1, -(1 << 40), -256, -255, -1, 0, 1, 255, 256, i64::MAX];
+        for values in unsigned.windows(2) {
+            let one = encode_keys(&[EncodeType::SortVarU64(values[0])]);
+            let two = encode_keys(&[EncodeType::SortVarU64(values[1])]);
+            assert_eq!(one.cmp(&two), Ordering::Less, "{:?}", values);
+        }
+        for values in signed.windows(2) {
+            let one = encode_keys(&[EncodeType::SortVarI64(values[0])]);
+            let two = encode_keys(&[EncodeType::SortVarI64(values[1])]);
+            assert_eq!(one.cmp(&two), Ordering::Less, "{:?}", values);
+        }
+        assert_eq!(encode_keys(&[EncodeType::SortVarU64(0)]), vec![0]);
+        assert_eq!(encode_keys(&[EncodeType::SortVarU64(300)]), vec![2, 1, 44]);
+        assert_eq!(encode_keys(&[EncodeType::SortVarI64(-1)]), vec![0x7f, 0xfe]);
+        assert_eq!(encode_keys(&[EncodeType::SortVarI64(i64::MIN)]).len(), 9);
+
+        let the_types = vec![DecodeType::DecodeVarU64, DecodeType::Reverse, DecodeType::DecodeVarI64];
+        let mut keys = Vec::new();
+        for a in unsigned {
+            for b in signed {
+                keys.push(vec![EncodeType::SortVarU64(a), EncodeType::SortVarI64(b)]);
+            }
+        }
+        for key1 in &keys {
+            let encoded_key1 = encode_keys(key1);
+            assert_eq!(&try_decode_byte_array(&encoded_key1, &the_types).unwrap(), key1);
+            for key2 in &keys {
+                let expected = key1[0].cmp(&key2[0]).then(key2[1].cmp(&key1[1]));
+                assert_eq!(compare(&the_types, key1, key2), expected);
+                assert_eq!(compare_encoded(&the_types, &encoded_key1, &encode_keys(key2)), expected);
+            }
+        }
+        assert_eq!(deserialize_decode_types(&serialize_decode_types(&the_types)), the_types);
+        // only the shortest form is accepted
+        assert_eq!(try_decode_byte_array(&[2, 0, 1, 0x80], &the_types), Err(KeyError::InvalidEncoding { field: 0, offset: 0 }));
+        assert_eq!(try_decode_byte_array(&[9, 0x80], &the_types), Err(KeyError::InvalidEncoding { field: 0, offset: 0 }));
+        assert_eq!(try_decode_byte_array(&[0, 0x7e, 0xff, 0xfe], &the_types), Err(KeyError::InvalidEncoding { field: 1, offset: 1 }));
+        assert_eq!(try_decode_byte_array(&[0, 0x78, 0x7f, 0, 0, 0, 0, 0, 0, 0xfe], &the_types), Err(KeyError::InvalidEncoding { field: 1, offset: 1 }));
+    }
+
+    #[test]
+    fn test_decimals() {
+        let values: Vec<Decimal> = ["-1e20", "-10.5", "-10.25", "-0.001", "0", "0.001", "0.0011", "10.25", "10.5", "1e20"].iter().map(|value| value.parse().unwrap()).collect();
+        let the_types = vec![DecodeType::DecodeDecimal, DecodeType::Reverse, DecodeType::DecodeDecimal];
+        let mut keys = Vec::new();
+        for a in &values {
+            for b in &values {
+                keys.push(vec![EncodeType::SortDecimal(a.clone()), EncodeType::SortDecimal(b.clone())]);
+            }
+        }
+        for key1 in &keys {
+            let encoded_key1 = encode_keys(key1);
+            assert_eq!(&try_decode_byte_array(&encoded_key1, &the_types).unwrap(), key1);
+            for key2 in &keys {
+                let expected = key1[0].cmp(&key2[0]).then(key2[1].cmp(&key1[1]));
+                assert_eq!(compare(&the_types, key1, key2), expected);
+                assert_eq!(compare_encoded(&the_types, &encoded_key1, &encode_keys(key2)), expected);
+            }
+        }
+        assert_eq!(deserialize_decode_types(&serialize_decode_types(&the_types)), the_types);
+        let encoded = encode_keys(&[EncodeType::SortDecimal("12.5".parse().unwrap())]);
+        assert_eq!(try_decode_byte_array(&encoded[..encoded.len() - 1], &[DecodeType::DecodeDecimal]), Err(KeyError::Truncated { field: 0, offset: 0, needed: encoded.len(), available: encoded.len() - 1 }));
+        assert_eq!(try_decode_byte_array(&[9], &[DecodeType::DecodeDecimal]), Err(KeyError::InvalidEncoding { field: 0, offset: 0 }));
+    }
+
+    #[test]
+    fn test_collated_strings() {
+        let names = ["apple", "Apple", "APPLE", "Zebra", "zebra", "\u{e9}clair", "e\u{301}clair", "\u{c9}CLAIR", "Stra\u{df}e", "STRASSE"];
+        for collation in [Collation::Binary, Collation::AsciiCaseFold, Collation::UnicodeCaseFold, Collation::Nfc, Collation::Nfkc, Collation::NfkcCaseFold] {
+            for stored_sort_key in [true, false] {
+                let the_types = vec![DecodeType::Reverse, DecodeType::DecodeCollatedString(collation, stored_sort_key), DecodeType::DecodeU8];
+                let keys: Vec<Vec<EncodeType>> = names.iter().map(|name| vec![EncodeType::SortCollatedString(CollatedString::new(*name, collation, stored_sort_key)), EncodeType::SortU8(1)]).collect();
+                for key1 in &keys {
+                    let encoded_key1 = encode_keys(key1);
+                    assert_eq!(&try_decode_byte_array(&encoded_key1, &the_types).unwrap(), key1);
+                    for key2 in &keys {
+                        let expected = key2[0].cmp(&key1[0]);
+                        assert_eq!(compare(&the_types, key1, key2), expected);
+                        assert_eq!(compare_encoded(&the_types, &encoded_key1, &encode_keys(key2)), expected);
+                    }
+                }
+                assert_eq!(deserialize_decode_types(&serialize_decode_types(&the_types)), the_types);
+            }
+        }
+        let the_types = [DecodeType::DecodeCollatedString(Collation::AsciiCaseFold, true)];
+        let key = |name: &str| encode_keys(&[EncodeType::SortCollatedString(CollatedString::new(name, Collation::AsciiCaseFold, true))]);
+        assert_eq!(compare_encoded(&the_types, &key("apple"), &key("Zebra")), Ordering::Less);
+        assert_eq!(compare_encoded(&the_types, &key("Apple"), &key("apple")), Ordering::Less);
+        assert_eq!(key("Ab"), vec![0, 0, 0, 2, b'a', b'b', 0, 0, 0, 2, b'A', b'b']);
+        // the stored sort key has to match the string
+        assert_eq!(try_decode_byte_array(&[0, 0, 0, 2, b'a', b'c', 0, 0, 0, 2, b'A', b'b'], &the_types), Err(KeyError::InvalidEncoding { field: 0, offset: 0 }));
+        assert_eq!(try_deserialize_decode_types(&[28, 6, 0]), Err(KeyError::UnknownTypeTag { index: 1, tag: 6 }));
+    }
+
+    #[test]
+    fn test_tuples() {
+        let inner = vec![DecodeType::DecodeString, DecodeType::Reverse, DecodeType::DecodeOption(Box::new(DecodeType::DecodeU8), NullOrder::NullsLast)];
+        let the_types = vec![DecodeType::DecodeU8, DecodeType::Reverse, DecodeType::DecodeTuple(inner.clone()), DecodeType::DecodeTuple(inner.clone())];
+        let tuple = |name: &str, value: Option<u8>| EncodeType::SortTuple(vec![EncodeType::SortString(name.to_string()), EncodeType::SortOption(value.map(|value| Box::new(EncodeType::SortU8(value))))]);
+        // in ascending order, reversing the tuple as a whole flips the direction of both fields but the nulls stay last
+        let tuples = [tuple("a", Some(2)), tuple("a", Some(1)), tuple("a", None), tuple("b", Some(9)), tuple("b", None)];
+        let reversed_rank = [3, 2, 4, 0, 1];
+        let mut keys = Vec::new();
+        for a in &tuples {
+            for b in &tuples {
+                keys.push(vec![EncodeType::SortU8(1), a.clone(), b.clone()]);
+            }
+        }
+        for (i, key1) in keys.iter().enumerate() {
+            let encoded_key1 = encode_keys(key1);
+            assert_eq!(&try_decode_byte_array(&encoded_key1, &the_types).unwrap(), key1);
+            for (j, key2) in keys.iter().enumerate() {
+                let expected = reversed_rank[i / tuples.len()].cmp(&reversed_rank[j / tuples.len()]).then((i % tuples.len()).cmp(&(j % tuples.len())));
+                assert_eq!(compare(&the_types, key1, key2), expected, "{:?} {:?}", key1, key2);
+                assert_eq!(compare_encoded(&the_types, &encoded_key1, &encode_keys(key2)), expected, "{:?} {:?}", key1, key2);
+            }
+        }
+        assert_eq!(deserialize_decode_types(&serialize_decode_types(&the_types)), the_types);
+        assert!(validate_types(&the_types, &keys[0]));
+        assert!(!validate_types(&the_types, &[EncodeType::SortU8(1), EncodeType::SortTuple(vec![EncodeType::SortString("a".to_string())]), tuple("a", None)]));
+    }
+
+    #[test]
+    fn test_unions() {
+        let variants = vec![DecodeType::DecodeU64, DecodeType::DecodeOption(Box::new(DecodeType::DecodeString), NullOrder::NullsLast)];
+        let the_types = vec![DecodeType::DecodeUnion(variants.clone()), DecodeType::Reverse, DecodeType::DecodeUnion(variants.clone())];
+        let handle = |name: Option<&str>| EncodeType::SortUnion(1, Box::new(EncodeType::SortOption(name.map(|name| Box::new(EncodeType::SortString(name.to_string()))))));
+        // in ascending order, descending the variants and the values inside them flip but the nulls stay last
+        let values = [EncodeType::SortUnion(0, Box::new(EncodeType::SortU64(1))), EncodeType::SortUnion(0, Box::new(EncodeType::SortU64(300))), handle(Some("a")), handle(Some("b")), handle(None)];
+        let descending_rank = [4, 3, 1, 0, 2];
+        let mut keys = Vec::new();
+        for a in &values {
+            for b in &values {
+                keys.push(vec![a.clone(), b.clone()]);
+            }
+        }
+        for (i, key1) in keys.iter().enumerate() {
+            let encoded_key1 = encode_keys(key1);
+            assert_eq!(&try_decode_byte_array(&encoded_key1, &the_types).unwrap(), key1);
+            for (j, key2) in keys.iter().enumerate() {
+                let expected = (i / values.len()).cmp(&(j / values.len())).then(descending_rank[i % values.len()].cmp(&descending_rank[j % values.len()]));
+                assert_eq!(compare(&the_types, key1, key2), expected, "{:?} {:?}", key1, key2);
+                assert_eq!(compare_encoded(&the_types, &encoded_key1, &encode_keys(key2)), expected, "{:?} {:?}", key1, key2);
+            }
+        }
+        assert_eq!(encode_keys(&values[..1]), vec![0, 0, 0, 0, 0, 0, 0, 0, 1]);
+        assert_eq!(deserialize_decode_types(&serialize_decode_types(&the_types)), the_types);
+        assert!(!validate_types(&the_types[..1], &[EncodeType::SortUnion(2, Box::new(EncodeType::SortU64(1)))]));
+        assert!(!validate_types(&the_types[..1], &[EncodeType::SortUnion(1, Box::new(EncodeType::SortU64(1)))]));
+        assert_eq!(try_decode_byte_array(&[2, 0], &the_types[..1]), Err(KeyError::InvalidEncoding { field: 0, offset: 0 }));
+    }
+
+    #[test]
+    fn test_lists() {
+        let list = |names: &[&str]| EncodeType::SortList(names.iter().map(|name| EncodeType::SortString(name.to_string())).collect());
+        // in ascending order, shorter lists before the longer lists they start
+        let lists = [list(&[]), list(&["a"]), list(&["a", ""]), list(&["a", "b"]), list(&["ab"]), list(&["b"])];
+        let the_types = vec![DecodeType::DecodeList(Box::new(DecodeType::DecodeString)), DecodeType::Reverse, DecodeType::DecodeList(Box::new(DecodeType::DecodeString)), DecodeType::DecodeU32];
+        let mut keys = Vec::new();
+        for a in &lists {
+            for b in &lists {
+                keys.push(vec![a.clone(), b.clone(), EncodeType::SortU32(1)]);
+            }
+        }
+        for (i, key1) in keys.iter().enumerate() {
+            let encoded_key1 = encode_keys(key1);
+            assert_eq!(&try_decode_byte_array(&encoded_key1, &the_types).unwrap(), key1);
+            for (j, key2) in keys.iter().enumerate() {
+                let expected = (i / lists.len()).cmp(&(j / lists.len())).then((j % lists.len()).cmp(&(i % lists.len())));
+                assert_eq!(compare(&the_types, key1, key2), expected, "{:?} {:?}", key1, key2);
+                assert_eq!(compare_encoded(&the_types, &encoded_key1, &encode_keys(key2)), expected, "{:?} {:?}", key1, key2);
+            }
+        }
+        assert_eq!(encode_keys(&[list(&["a"])]), vec![1, 0, 0, 0, 1, b'a', 0]);
+        assert_eq!(deserialize_decode_types(&serialize_decode_types(&the_types)), the_types);
+        assert!(!validate_types(&the_types[..1], &[EncodeType::SortList(vec![EncodeType::SortU32(1)])]));
+        assert_eq!(try_decode_byte_array(&[1, 0, 0, 0, 0, 2], &the_types[..1]), Err(KeyError::InvalidEncoding { field: 0, offset: 5 }));
+        assert_eq!(try_decode_byte_array(&[1, 0, 0, 0, 0], &the_types[..1]), Err(KeyError::Truncated { field: 0, offset: 5, needed: 1, available: 0 }));
+    }
+
+    #[test]
+    fn test_lists_keep_nulls_in_place() {
+        let element = DecodeType::DecodeOption(Box::new(DecodeType::DecodeU8), NullOrder::NullsLast);
+        let the_types = vec![DecodeType::Reverse, DecodeType::DecodeList(Box::new(element))];
+        let list = |values: &[Option<u8>]| vec![EncodeType::SortList(values.iter().map(|value| EncodeType::SortOption(value.map(|value| Box::new(EncodeType::SortU8(value))))).collect())];
+        let keys = [list(&[Some(2), Some(1)]), list(&[Some(2), None]), list(&[Some(2)]), list(&[Some(1)]), list(&[None, Some(9)])];
+        for pair in keys.windows(2) {
+            assert_eq!(compare(&the_types, &pair[0], &pair[1]), Ordering::Less, "{:?}", pair);
+            assert_eq!(compare_encoded(&the_types, &encode_keys(&pair[0]), &encode_keys(&pair[1])), Ordering::Less, "{:?}", pair);
+        }
+    }
+
+    // every 65521st bit pattern of an f32 and a spread of f64 patterns, the bytes must sort like the values
+    #[test]
+    fn test_nan_first_float_bytes_sort_like_values() {
+        let the_types = vec![DecodeType::DecodeF32, DecodeType::DecodeF64];
+        let mut keys: Vec<Vec<EncodeType>> = (0..=u32::MAX).step_by(65_521).map(|bits| {
+            let wide = (bits as u64) << 32 | bits.rotate_left(7) as u64;
+            vec![EncodeType::SortF32(F32struct::new(f32::from_bits(bits))), EncodeType::SortF64(F64struct::new(f64::from_bits(wide)))]
+        }).collect();
+        keys.extend([-0.0, 0.0, f64::NAN, -f64::NAN, f64::NEG_INFINITY, f64::INFINITY].map(|value| vec![EncodeType::SortF32(F32struct::new(value as f32)), EncodeType::SortF64(F64struct::new(value))]));
+        keys.sort_by(|one, two| compare(&the_types, one, two));
+        for pair in keys.windows(2) {
+            let encoded_key1 = encode_keys(&pair[0]);
+            let encoded_key2 = encode_keys(&pair[1]);
+            assert_eq!(encoded_key1.cmp(&encoded_key2), compare(&the_types, &pair[0], &pair[1]), "{:?} {:?}", pair[0], pair[1]);
+            assert_eq!(compare_encoded(&the_types, &encoded_key1, &encoded_key2), compare(&the_types, &pair[0], &pair[1]));
+            assert_eq!(try_decode_byte_array(&encoded_key1, &the_types).unwrap(), pair[0]);
+        }
+        // negative values sort before positive ones bytewise
+        assert!(encode_keys(&[EncodeType::SortF64(F64struct::new(-2.0))]) < encode_keys(&[EncodeType::SortF64(F64struct::new(-1.0))]));
+        assert!(encode_keys(&[EncodeType::SortF64(F64struct::new(-1.0))]) < encode_keys(&[EncodeType::SortF64(F64struct::new(1.0))]));
+    }
+
+    #[test]
+    fn test_nan_first_floats_have_one_encoding() {
+        assert_eq!(encode_keys(&[EncodeType::SortF64(F64struct::new(-0.0))]), encode_keys(&[EncodeType::SortF64(F64struct::new(0.0))]));
+        assert_eq!(encode_keys(&[EncodeType::SortF32(F32struct::new(-f32::NAN))]), encode_keys(&[EncodeType::SortF32(F32struct::new(f32::NAN))]));
+    }
+
     #[test]
     fn test_compare_gives_equal() {
         let key1 = vec![EncodeType::SortU16(1), EncodeType::SortU32(2)];
@@ -571,6 +2036,62 @@ mod tests {
         assert_eq!(result, Ordering::Greater);
     }
 
+    #[test]
+    fn test_compare_encoded_matches_compare() {
+        let the_types = vec![DecodeType::DecodeString, DecodeType::Reverse, DecodeType::DecodeU32, DecodeType::DecodeBool, DecodeType::Reverse, DecodeType::DecodeF64, DecodeType::DecodeU128];
+        let mut keys = Vec::new();
+        for s in ["", "a", "ab", "b"] {
+            for i in [0, 1, 256, u32::MAX] {
+                for b in [false, true] {
+                    for f in [f64::NAN, -1.0, 0.0, 2.5] {
+                        keys.push(vec![EncodeType::SortString(s.to_string()), EncodeType::SortU32(i), EncodeType::SortBool(b), EncodeType::SortF64(F64struct::new(f)), EncodeType::SortU128(i as u128)]);
+                    }
+                }
+            }
+        }
+        for key1 in &keys {
+            for key2 in &keys {
+                let result = compare_encoded(&the_types, &encode_keys(key1), &encode_keys(key2));
+                assert_eq!(result, compare(&the_types, key1, key2), "{:?} {:?}", key1, key2);
+            }
+        }
+    }
+
+    #[test]
+    fn test_compare_encoded_signed_and_bytes() {
+        let the_types = vec![DecodeType::DecodeBytes, DecodeType::DecodeI64, DecodeType::Reverse, DecodeType::DecodeI32];
+        let key1 = encode_keys(&[EncodeType::SortBytes(vec![1, 2]), EncodeType::SortI64(-5), EncodeType::SortI32(-1)]);
+        let key2 = encode_keys(&[EncodeType::SortBytes(vec![1, 2]), EncodeType::SortI64(3), EncodeType::SortI32(-1)]);
+        let key3 = encode_keys(&[EncodeType::SortBytes(vec![1, 2]), EncodeType::SortI64(3), EncodeType::SortI32(-2)]);
+        assert_eq!(compare_encoded(&the_types, &key1, &key2), Ordering::Less);
+        assert_eq!(compare_encoded(&the_types, &key2, &key3), Ordering::Less);
+        assert_eq!(compare_encoded(&the_types, &key3, &key3), Ordering::Equal);
+    }
+
+    // compares the decoding comparator with compare_encoded, run with
+    // cargo test --release compare_benchmark -- --ignored --nocapture
+    #[test]
+    #[ignore]
+    fn compare_benchmark() {
+        let the_types = vec![DecodeType::DecodeString, DecodeType::Reverse, DecodeType::DecodeU32, DecodeType::DecodeF64, DecodeType::DecodeU64];
+        let keys: Vec<Vec<u8>> = (0..10_000u32).map(|i| encode_keys(&[EncodeType::SortString(format!("customer-{}", i % 100)), EncodeType::SortU32(i), EncodeType::SortF64(F64struct::new(i as f64 / 3.0)), EncodeType::SortU64(i as u64)])).collect();
+        let decoding = |one: &[u8], two: &[u8]| compare(&the_types, &decode_byte_array(one, &the_types), &decode_byte_array(two, &the_types));
+        let in_place = |one: &[u8], two: &[u8]| compare_encoded(&the_types, one, two);
+        for (name, compare_fn) in [("decode + compare", &decoding as &dyn Fn(&[u8], &[u8]) -> Ordering), ("compare_encoded", &in_place)] {
+            let start = std::time::Instant::now();
+            let mut less = 0;
+            for _ in 0..100 {
+                for pair in keys.windows(2) {
+                    if compare_fn(std::hint::black_box(&pair[0]), std::hint::black_box(&pair[1])) == Ordering::Less {
+                        less += 1;
+                    }
+                }
+            }
+            let nanos = start.elapsed().as_nanos() as f64 / (100 * (keys.len() - 1)) as f64;
+            println!("{:<18} {:>8.1} ns/compare ({} less)", name, nanos, less);
+        }
+    }
+
     #[test]
     // write a function which test deserialize_decode_types
     fn test_deserialize_decode_types() {
@@ -580,6 +2101,14 @@ mod tests {
         assert_eq!(the_types, decoded_types);
     }
 
+    #[test]
+    fn test_raw_float_tags_are_refused() {
+        assert_eq!(serialize_decode_types(&[DecodeType::DecodeF32, DecodeType::DecodeF64]), vec![32, 33]);
+        let error = Err(KeyError::InvalidDescriptor { reason: "f32 or f64 field written as raw IEEE bytes by an earlier version" });
+        assert_eq!(try_deserialize_decode_types(&[7]), error);
+        assert_eq!(try_deserialize_decode_types(&[2, 13, 8]), error);
+    }
+
     // write a test for the validate_types function above
     #[test]
     fn test_validate_types() {
@@ -589,6 +2118,186 @@ mod tests {
         assert!(result);
     }
 
+    #[test]
+    fn test_try_decode_byte_array_errors() {
+        let the_types = vec![DecodeType::DecodeU16, DecodeType::Reverse, DecodeType::DecodeString];
+        let encoded_data = encode_keys(&[EncodeType::SortU16(1), EncodeType::SortString("hello".to_string())]);
+        assert_eq!(try_decode_byte_array(&encoded_data[..4], &the_types), Err(KeyError::Truncated { field: 1, offset: 2, needed: 4, available: 2 }));
+        assert_eq!(try_decode_byte_array(&encoded_data[..8], &the_types), Err(KeyError::Truncated { field: 1, offset: 6, needed: 5, available: 2 }));
+        assert_eq!(try_decode_byte_array(&[encoded_data.clone(), vec![0]].concat(), &the_types), Err(KeyError::TrailingBytes { offset: 11, remaining: 1 }));
+        let mut invalid_utf8 = encoded_data.clone();
+        invalid_utf8[6] = 0xff;
+        assert_eq!(try_decode_byte_array(&invalid_utf8, &the_types), Err(KeyError::InvalidUtf8 { field: 1, offset: 2 }));
+        assert!(try_decode_byte_array(&encoded_data, &the_types).is_ok());
+    }
+
+    #[test]
+    fn test_misplaced_reverse() {
+        let key = [EncodeType::SortU16(1), EncodeType::SortU32(2)];
+        let encoded_data = encode_keys(&key);
+        for (the_types, index) in [
+            (vec![DecodeType::DecodeU16, DecodeType::DecodeU32, DecodeType::Reverse], 2),
+            (vec![DecodeType::DecodeU16, DecodeType::Reverse, DecodeType::Reverse, DecodeType::DecodeU32], 1),
+            (vec![DecodeType::Reverse, DecodeType::Reverse, DecodeType::DecodeU16, DecodeType::DecodeU32], 0),
+        ] {
+            assert_eq!(try_decode_byte_array(&encoded_data, &the_types), Err(KeyError::MisplacedReverse { index }));
+            assert_eq!(try_decode_prefix(&encoded_data, &the_types), Err(KeyError::MisplacedReverse { index }));
+            assert_eq!(try_validate_types(&the_types, &key), Err(KeyError::MisplacedReverse { index }));
+            assert!(!validate_types(&the_types, &key));
+            assert_eq!(encode_prefix(&the_types, &key[..1]), Err(KeyError::MisplacedReverse { index }));
+        }
+        // also inside a tuple
+        let the_types = vec![DecodeType::DecodeTuple(vec![DecodeType::DecodeU16, DecodeType::DecodeU32, DecodeType::Reverse])];
+        let tuple = [EncodeType::SortTuple(key.to_vec())];
+        assert_eq!(try_decode_byte_array(&encoded_data, &the_types), Err(KeyError::MisplacedReverse { index: 2 }));
+        assert!(!validate_types(&the_types, &tuple));
+        assert_eq!(KeyError::MisplacedReverse { index: 2 }.to_string(), "reverse at index 2 is not followed by a field");
+    }
+
+    #[test]
+    fn test_compare_bytes_with_corrupt_key_does_not_panic() {
+        let the_types = vec![DecodeType::DecodeString, DecodeType::DecodeU32];
+        let key1 = encode_keys(&[EncodeType::SortString("a".to_string()), EncodeType::SortU32(1)]);
+        let key2 = vec![0, 0, 0, 200, 1];
+        assert!(try_compare_encoded(&the_types, &key1, &key2).is_err());
+        assert_eq!(compare_bytes(&the_types, &key1, &key2), Ordering::Less);
+    }
+
+    #[test]
+    fn test_compare_encoded_puts_unreadable_keys_last() {
+        let the_types = vec![DecodeType::Reverse, DecodeType::DecodeU32];
+        let one = encode_keys(&[EncodeType::SortU32(1)]);
+        let two = encode_keys(&[EncodeType::SortU32(2)]);
+        // by its bytes the key with a trailing byte is between the valid ones
+        let trailing = [one.clone(), vec![5]].concat();
+        let truncated = vec![0, 0, 1];
+        assert_eq!(compare_encoded(&the_types, &two, &one), Ordering::Less);
+        for corrupt in [&trailing, &truncated] {
+            assert_eq!(compare_encoded(&the_types, &one, corrupt), Ordering::Less);
+            assert_eq!(compare_encoded(&the_types, &two, corrupt), Ordering::Less);
+            assert_eq!(compare_encoded(&the_types, corrupt, &one), Ordering::Greater);
+            assert_eq!(compare_encoded(&the_types, corrupt, corrupt), Ordering::Equal);
+        }
+        assert_eq!(compare_encoded(&the_types, &trailing, &truncated), Ordering::Less);
+        // a prefix key is not corrupt
+        assert_eq!(compare_encoded(&the_types, &[], &truncated), Ordering::Less);
+        // a collated string is compared as utf-8
+        let the_types = vec![DecodeType::DecodeCollatedString(Collation::Nfc, false)];
+        let valid = encode_keys(&[EncodeType::SortCollatedString(CollatedString::new("z", Collation::Nfc, false))]);
+        assert_eq!(compare_encoded(&the_types, &valid, &[0, 0, 0, 1, 0xff]), Ordering::Less);
+        // only the fields up to the first difference are read, an unreadable field sorts after the values of that field
+        let the_types = vec![DecodeType::DecodeU32, DecodeType::DecodeString];
+        let key = |number: u32, name: &str| encode_keys(&[EncodeType::SortU32(number), EncodeType::SortString(name.to_string())]);
+        let corrupt = [encode_keys(&[EncodeType::SortU32(1)]), vec![0, 0, 0, 9, b'a']].concat();
+        let keys = [key(1, "a"), key(1, "z"), corrupt, key(2, "a")];
+        for (i, key1) in keys.iter().enumerate() {
+            for (j, key2) in keys.iter().enumerate() {
+                assert_eq!(compare_encoded(&the_types, key1, key2), i.cmp(&j));
+            }
+        }
+    }
+
+    #[test]
+    fn test_compare_encoded_prefix_keys() {
+        let the_types = vec![DecodeType::DecodeString, DecodeType::Reverse, DecodeType::DecodeU32];
+        let prefix = encode_keys(&[EncodeType::SortString("b".to_string())]);
+        let smaller = encode_keys(&[EncodeType::SortString("a".to_string()), EncodeType::SortU32(0)]);
+        let extends = encode_keys(&[EncodeType::SortString("b".to_string()), EncodeType::SortU32(u32::MAX)]);
+        assert_eq!(compare_encoded(&the_types, &prefix, &extends), Ordering::Less);
+        assert_eq!(compare_encoded(&the_types, &extends, &prefix), Ordering::Greater);
+        assert_eq!(compare_encoded(&the_types, &smaller, &prefix), Ordering::Less);
+        assert_eq!(compare_encoded(&the_types, &prefix, &prefix), Ordering::Equal);
+        assert_eq!(compare_encoded(&the_types, &[], &prefix), Ordering::Less);
+    }
+
+    #[test]
+    fn test_prefix_order() {
+        let the_types = vec![DecodeType::DecodeString, DecodeType::Reverse, DecodeType::DecodeU32, DecodeType::DecodeBool];
+        let keys = [
+            vec![EncodeType::SortString("a".to_string()), EncodeType::SortU32(0), EncodeType::SortBool(true)],
+            vec![EncodeType::SortString("b".to_string()), EncodeType::SortU32(7), EncodeType::SortBool(false)],
+            vec![EncodeType::SortString("b".to_string()), EncodeType::SortU32(7), EncodeType::SortBool(true)],
+            vec![EncodeType::SortString("b".to_string()), EncodeType::SortU32(3), EncodeType::SortBool(false)],
+            vec![EncodeType::SortString("c".to_string()), EncodeType::SortU32(9), EncodeType::SortBool(false)],
+        ];
+        let prefix = vec![EncodeType::SortString("b".to_string()), EncodeType::SortU32(7)];
+        let encoded_prefix = encode_prefix(&the_types, &prefix).unwrap();
+        assert_eq!(try_decode_prefix(&encoded_prefix, &the_types).unwrap(), prefix);
+        assert_eq!(try_decode_prefix(&[], &the_types).unwrap(), vec![]);
+        for key in &keys {
+            let encoded = encode_keys(key);
+            let shorter_first = compare_encoded_with(&the_types, PrefixOrder::ShorterFirst, &encoded_prefix, &encoded);
+            let shorter_last = compare_encoded_with(&the_types, PrefixOrder::ShorterLast, &encoded_prefix, &encoded);
+            assert_eq!(shorter_first, compare_encoded(&the_types, &encoded_prefix, &encoded));
+            assert_eq!(shorter_first, compare(&the_types, &prefix, key));
+            assert_eq!(shorter_last, compare_with(&the_types, PrefixOrder::ShorterLast, &prefix, key));
+            assert_eq!(compare_with(&the_types, PrefixOrder::ShorterLast, key, &prefix), shorter_last.reverse());
+            // only the keys starting with the prefix move to the other side
+            let starts_with_prefix = key.starts_with(&prefix);
+            assert_eq!(shorter_first == shorter_last, !starts_with_prefix);
+            if starts_with_prefix {
+                assert_eq!((shorter_first, shorter_last), (Ordering::Less, Ordering::Greater));
+            }
+        }
+        assert_eq!(compare_with(&the_types, PrefixOrder::ShorterLast, &prefix, &prefix), Ordering::Equal);
+        assert_eq!(compare_with(&the_types, PrefixOrder::ShorterLast, &[], &prefix), Ordering::Greater);
+        assert_eq!(encode_prefix(&the_types, &[EncodeType::SortU32(7)]), Err(KeyError::SchemaMismatch { field: 0, expected: Some(DecodeType::DecodeString), found: Some(EncodeType::SortU32(7)) }));
+        let too_long = [keys[0].clone(), vec![EncodeType::SortU8(1)]].concat();
+        assert_eq!(encode_prefix(&the_types, &too_long), Err(KeyError::SchemaMismatch { field: 3, expected: None, found: Some(EncodeType::SortU8(1)) }));
+        assert!(try_decode_prefix(&encoded_prefix[..encoded_prefix.len() - 1], &the_types).is_err());
+    }
+
+    fn nullable_u32(value: Option<u32>) -> EncodeType {
+        EncodeType::SortOption(value.map(|value| Box::new(EncodeType::SortU32(value))))
+    }
+
+    #[test]
+    fn test_nullable_round_trip() {
+        let the_types = vec![DecodeType::DecodeOption(Box::new(DecodeType::DecodeU32), NullOrder::NullsFirst), DecodeType::DecodeOption(Box::new(DecodeType::DecodeString), NullOrder::NullsLast)];
+        let keys = vec![nullable_u32(Some(3)), EncodeType::SortOption(None)];
+        assert!(validate_types(&the_types, &keys));
+        assert!(!validate_types(&the_types, &[EncodeType::SortU32(3), EncodeType::SortOption(None)]));
+        assert!(!validate_types(&the_types, &[EncodeType::SortOption(Some(Box::new(EncodeType::SortU8(3)))), EncodeType::SortOption(None)]));
+        let encoded_data = encode_keys(&keys);
+        assert_eq!(encoded_data, vec![1, 0, 0, 0, 3, 0]);
+        assert_eq!(try_decode_byte_array(&encoded_data, &the_types), Ok(keys));
+        assert_eq!(try_decode_byte_array(&[2, 0, 0, 0, 3, 0], &the_types), Err(KeyError::InvalidEncoding { field: 0, offset: 0 }));
+        assert_eq!(deserialize_decode_types(&serialize_decode_types(&the_types)), the_types);
+        assert_eq!(serialize_decode_types(&the_types), vec![13, 2, 14, 5]);
+        assert!(try_deserialize_decode_types(&[13]).is_err());
+        assert!(try_deserialize_decode_types(&[13, 9]).is_err());
+    }
+
+    #[test]
+    fn test_nulls_first_and_last() {
+        let values = [None, Some(0), Some(7)];
+        for null_order in [NullOrder::NullsFirst, NullOrder::NullsLast] {
+            for reverse in [false, true] {
+                let mut the_types = if reverse { vec![DecodeType::Reverse] } else { vec![] };
+                the_types.push(DecodeType::DecodeOption(Box::new(DecodeType::DecodeU32), null_order));
+                for value1 in values {
+                    for value2 in values {
+                        let key1 = vec![nullable_u32(value1)];
+                        let key2 = vec![nullable_u32(value2)];
+                        let expected = match (value1, value2) {
+                            (Some(value1), Some(value2)) if reverse => value2.cmp(&value1),
+                            (Some(value1), Some(value2)) => value1.cmp(&value2),
+                            _ if null_order == NullOrder::NullsFirst => value1.is_some().cmp(&value2.is_some()),
+                            _ => value2.is_some().cmp(&value1.is_some()),
+                        };
+                        assert_eq!(compare(&the_types, &key1, &key2), expected, "{:?} {:?} {:?}", the_types, value1, value2);
+                        assert_eq!(compare_encoded(&the_types, &encode_keys(&key1), &encode_keys(&key2)), expected, "{:?} {:?} {:?}", the_types, value1, value2);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_try_deserialize_decode_types_unknown_tag() {
+        assert_eq!(try_deserialize_decode_types(&[0, 9, 200]), Err(KeyError::UnknownTypeTag { index: 2, tag: 200 }));
+    }
+
     #[test]
     fn test_validate_types_fail() {
         let the_types = vec![DecodeType::DecodeU32, DecodeType::DecodeU32, DecodeType::DecodeU64, DecodeType::Reverse, DecodeType::DecodeU128, DecodeType::DecodeI32, DecodeType::DecodeI64, DecodeType::DecodeString, DecodeType::DecodeBytes, DecodeType::DecodeBool, DecodeType::DecodeF32, DecodeType::DecodeF64];
@@ -596,5 +2305,10 @@ mod tests {
         let result = validate_types(&the_types, &the_keys);
         assert!(!result);
     }
+    #[test]
+    fn test_try_validate_types_reports_field() {
+        let the_types = vec![DecodeType::DecodeU16, DecodeType::Reverse, DecodeType::DecodeU32];
+        assert_eq!(try_validate_types(&the_types, &[EncodeType::SortU16(1), EncodeType::SortU64(2)]), Err(KeyError::SchemaMismatch { field: 1, expected: Some(DecodeType::DecodeU32), found: Some(EncodeType::SortU64(2)) }));
+        assert_eq!(try_validate_types(&the_types, &[EncodeType::SortU16(1)]), Err(KeyError::SchemaMismatch { field: 1, expected: Some(DecodeType::DecodeU32), found: None }));
+    }
 }
-
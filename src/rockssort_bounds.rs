@@ -0,0 +1,548 @@
+// The neighbours of encoded keys in the order of `compare_encoded`, for exclusive bounds and prefix scans.
+//
+// A key may hold only the leading fields of the schema. With `PrefixOrder::ShorterFirst` it sorts before
+// the longer keys it starts, so the key after the complete key (a, MAX) is the one field key (a + 1), and
+// the key before (a, MIN) is (a). With `PrefixOrder::ShorterLast` it sorts after them, so the key after
+// (a, MAX) is (a) and the key after (a) is (a + 1, MIN). The functions without `_with` use `ShorterFirst`.
+// The values are stepped in the order of their field, which is the reverse value order for
+// descending fields, while nulls keep their place.
+//
+// A value has no direct neighbour when infinitely many values lie next to it: no string is the largest
+// one below "b" (only "a", "a\xff", "a\xff\xff", ...), and decimals and collated strings have no next
+// value at all. The functions then return `None`, the same as when there is no key beyond the given one.
+use crate::rocks_sortable_keys::{encode_keys, try_decode_prefix, DecodeType, EncodeType, NullOrder, PrefixOrder};
+use crate::rockssort_collation::CollatedString;
+use crate::rockssort_error::KeyError;
+use crate::rockssort_float::{F32struct, F32total, F64struct, F64total};
+use crate::rockssort_time::{Date, Timestamp};
+use std::time::Duration;
+
+/// The smallest key that sorts after `key`, which may hold only the leading fields of the schema.
+///
+/// The key after a key with all fields is the next value of its last field, or when that is the
+/// largest value, a shorter key with the next value of an earlier field. The key after a shorter key is
+/// the key with one more field, holding the smallest value of that field.
+/// `None` when no key sorts after `key` or when the next key does not exist, see `prefix_upper_bound`.
+pub fn next_key(the_types: &[DecodeType], key: &[u8]) -> Result<Option<Vec<u8>>, KeyError> {
+    next_key_with(the_types, PrefixOrder::ShorterFirst, key)
+}
+
+/// Like `next_key`, with shorter keys placed by `prefix_order`. When shorter keys sort last, every key is
+/// the last one starting with its fields, so the next key is the same as `prefix_upper_bound_with`.
+pub fn next_key_with(the_types: &[DecodeType], prefix_order: PrefixOrder, key: &[u8]) -> Result<Option<Vec<u8>>, KeyError> {
+    let fields = fields(the_types);
+    let mut values = try_decode_prefix(key, the_types)?;
+    if prefix_order == PrefixOrder::ShorterLast {
+        return Ok(upper_bound_shorter_last(&fields, values));
+    }
+    if values.len() == fields.len() {
+        return Ok(upper_bound(&fields, values));
+    }
+    let (the_type, is_reverse) = fields[values.len()];
+    Ok(first_value(the_type, is_reverse).map(|first| {
+        values.push(first);
+        encode_keys(&values)
+    }))
+}
+
+/// The largest key that sorts before `key`, which may hold only the leading fields of the schema.
+///
+/// `None` when `key` is the empty key or when the previous key does not exist, e.g. when the last
+/// field is a string that does not end in a zero byte, or when a later field has no largest value.
+pub fn prev_key(the_types: &[DecodeType], key: &[u8]) -> Result<Option<Vec<u8>>, KeyError> {
+    prev_key_with(the_types, PrefixOrder::ShorterFirst, key)
+}
+
+/// Like `prev_key`, with shorter keys placed by `prefix_order`. When shorter keys sort last, the key before
+/// a shorter key is the one with the largest value in the next field, and `None` when that does not exist.
+pub fn prev_key_with(the_types: &[DecodeType], prefix_order: PrefixOrder, key: &[u8]) -> Result<Option<Vec<u8>>, KeyError> {
+    let fields = fields(the_types);
+    let mut values = try_decode_prefix(key, the_types)?;
+    if prefix_order == PrefixOrder::ShorterLast {
+        return Ok(prev_key_shorter_last(&fields, values));
+    }
+    let value = match values.pop() {
+        Some(value) => value,
+        None => return Ok(None),
+    };
+    let (the_type, is_reverse) = fields[values.len()];
+    // the shorter key sorts right before the keys it starts
+    if first_value(the_type, is_reverse).as_ref() == Some(&value) {
+        return Ok(Some(encode_keys(&values)));
+    }
+    let previous = match step(the_type, is_reverse, &value, false) {
+        Some(previous) => previous,
+        None => return Ok(None),
+    };
+    values.push(previous);
+    // the last key starting with the previous value has the largest value in every following field
+    for (the_type, is_reverse) in &fields[values.len()..] {
+        match edge(the_type, *is_reverse, true) {
+            Some(last) => values.push(last),
+            None => return Ok(None),
+        }
+    }
+    Ok(Some(encode_keys(&values)))
+}
+
+/// The smallest key that sorts after every key starting with the fields of `prefix`,
+/// the exclusive upper bound of a prefix scan.
+///
+/// This is `prefix` with its last field stepped to the next value, dropping trailing fields that hold
+/// their largest value. `None` when no key sorts after the prefix, e.g. a single u8 field holding 255,
+/// or when the next value does not exist, e.g. for a decimal field: the scan then has to stop by
+/// comparing the prefix instead.
+pub fn prefix_upper_bound(the_types: &[DecodeType], prefix: &[u8]) -> Result<Option<Vec<u8>>, KeyError> {
+    prefix_upper_bound_with(the_types, PrefixOrder::ShorterFirst, prefix)
+}
+
+/// Like `prefix_upper_bound`, with shorter keys placed by `prefix_order`. When shorter keys sort last,
+/// the bound is the first key starting with the next value of the last field, or the prefix without its
+/// last field when that holds the largest value. `None` also when the first key does not exist.
+pub fn prefix_upper_bound_with(the_types: &[DecodeType], prefix_order: PrefixOrder, prefix: &[u8]) -> Result<Option<Vec<u8>>, KeyError> {
+    let fields = fields(the_types);
+    let values = try_decode_prefix(prefix, the_types)?;
+    Ok(match prefix_order {
+        PrefixOrder::ShorterFirst => upper_bound(&fields, values),
+        PrefixOrder::ShorterLast => upper_bound_shorter_last(&fields, values),
+    })
+}
+
+// the types with their direction, without the Reverse markers
+fn fields(the_types: &[DecodeType]) -> Vec<(&DecodeType, bool)> {
+    let mut fields = Vec::new();
+    let mut is_reverse = false;
+    for the_type in the_types {
+        if the_type == &DecodeType::Reverse {
+            is_reverse = true;
+            continue;
+        }
+        fields.push((the_type, is_reverse));
+        is_reverse = false;
+    }
+    fields
+}
+
+// the smallest value in the order of the field, also used to seek to the first key of a group
+pub(crate) fn first_value(the_type: &DecodeType, is_reverse: bool) -> Option<EncodeType> {
+    edge(the_type, is_reverse, false)
+}
+
+// the smallest key after all keys starting with the values
+fn upper_bound(fields: &[(&DecodeType, bool)], mut values: Vec<EncodeType>) -> Option<Vec<u8>> {
+    while let Some(value) = values.pop() {
+        let (the_type, is_reverse) = fields[values.len()];
+        if let Some(next) = step(the_type, is_reverse, &value, true) {
+            values.push(next);
+            return Some(encode_keys(&values));
+        }
+        // carry into the field before, unless the value only lacks a direct neighbour
+        if edge(the_type, is_reverse, true).as_ref() != Some(&value) {
+            return None;
+        }
+    }
+    None
+}
+
+// the smallest key after all keys starting with the values, when those sort before the shorter key
+fn upper_bound_shorter_last(fields: &[(&DecodeType, bool)], mut values: Vec<EncodeType>) -> Option<Vec<u8>> {
+    let value = values.pop()?;
+    let (the_type, is_reverse) = fields[values.len()];
+    match step(the_type, is_reverse, &value, true) {
+        Some(next) => {
+            values.push(next);
+            // the first key starting with the next value has the smallest value in every following field
+            for (the_type, is_reverse) in &fields[values.len()..] {
+                values.push(first_value(the_type, *is_reverse)?);
+            }
+            Some(encode_keys(&values))
+        },
+        // the shorter key follows the group of the largest value
+        None if edge(the_type, is_reverse, true).as_ref() == Some(&value) => Some(encode_keys(&values)),
+        None => None,
+    }
+}
+
+// the largest key before the values, when shorter keys sort after the keys they start
+fn prev_key_shorter_last(fields: &[(&DecodeType, bool)], mut values: Vec<EncodeType>) -> Option<Vec<u8>> {
+    if values.len() < fields.len() {
+        // the last key starting with a shorter key is the one with the largest value in the next field
+        let (the_type, is_reverse) = fields[values.len()];
+        values.push(edge(the_type, is_reverse, true)?);
+        return Some(encode_keys(&values));
+    }
+    // a complete key follows the group of the previous value of its last field that is not the smallest,
+    // the shorter key holding that value is the last key of the group
+    while let Some(value) = values.pop() {
+        let (the_type, is_reverse) = fields[values.len()];
+        if let Some(previous) = step(the_type, is_reverse, &value, false) {
+            values.push(previous);
+            return Some(encode_keys(&values));
+        }
+        if first_value(the_type, is_reverse).as_ref() != Some(&value) {
+            return None;
+        }
+    }
+    None
+}
+
+// The value right after (`up`) or right before `value` in the order of the field,
+// `None` at the end of the field and when there is no direct neighbour.
+fn step(the_type: &DecodeType, is_reverse: bool, value: &EncodeType, up: bool) -> Option<EncodeType> {
+    match (the_type, value) {
+        (DecodeType::DecodeOption(inner, null_order), EncodeType::SortOption(value)) => {
+            let nulls_up = *null_order == NullOrder::NullsLast;
+            match value {
+                None if up == nulls_up => None,
+                None => edge(inner, is_reverse, !up).map(present),
+                Some(value) => match step(inner, is_reverse, value, up) {
+                    Some(next) => Some(present(next)),
+                    None if up == nulls_up && edge(inner, is_reverse, up).as_ref() == Some(&**value) => Some(EncodeType::SortOption(None)),
+                    None => None,
+                },
+            }
+        },
+        (DecodeType::DecodeTuple(inner), EncodeType::SortTuple(values)) => {
+            let inner: Vec<(&DecodeType, bool)> = fields(inner).into_iter().map(|(the_type, inner_reverse)| (the_type, is_reverse != inner_reverse)).collect();
+            // like counting: step the last value that is not at its end, reset the ones after it
+            for index in (0..values.len().min(inner.len())).rev() {
+                let (the_type, is_reverse) = inner[index];
+                if let Some(next) = step(the_type, is_reverse, &values[index], up) {
+                    let mut result = values[..index].to_vec();
+                    result.push(next);
+                    for (the_type, is_reverse) in &inner[index + 1..] {
+                        result.push(edge(the_type, *is_reverse, !up)?);
+                    }
+                    return Some(EncodeType::SortTuple(result));
+                }
+                if edge(the_type, is_reverse, up).as_ref() != Some(&values[index]) {
+                    return None;
+                }
+            }
+            None
+        },
+        (DecodeType::DecodeUnion(variants), EncodeType::SortUnion(variant, value)) => {
+            let the_type = variants.get(*variant as usize)?;
+            if let Some(next) = step(the_type, is_reverse, value, up) {
+                return Some(EncodeType::SortUnion(*variant, Box::new(next)));
+            }
+            if edge(the_type, is_reverse, up).as_ref() != Some(&**value) {
+                return None;
+            }
+            // the variants are ordered by their index
+            let neighbour = if up != is_reverse { variant.checked_add(1)? } else { variant.checked_sub(1)? };
+            let value = edge(variants.get(neighbour as usize)?, is_reverse, !up)?;
+            Some(EncodeType::SortUnion(neighbour, Box::new(value)))
+        },
+        (DecodeType::DecodeList(element), EncodeType::SortList(values)) => {
+            // a list sorts right before the lists it starts when ascending, right after them when descending
+            if up != is_reverse {
+                let mut values = values.clone();
+                values.push(edge(element, is_reverse, !up)?);
+                Some(EncodeType::SortList(values))
+            } else {
+                let (last, rest) = values.split_last()?;
+                (edge(element, is_reverse, up).as_ref() == Some(last)).then(|| EncodeType::SortList(rest.to_vec()))
+            }
+        },
+        // like a list of bytes, the smallest byte in the order of the field is always 0
+        (DecodeType::DecodeString, EncodeType::SortString(value)) => {
+            if up != is_reverse {
+                Some(EncodeType::SortString(format!("{}\0", value)))
+            } else {
+                value.strip_suffix('\0').map(|value| EncodeType::SortString(value.to_string()))
+            }
+        },
+        (DecodeType::DecodeBytes, EncodeType::SortBytes(value)) => {
+            if up != is_reverse {
+                let mut value = value.clone();
+                value.push(0);
+                Some(EncodeType::SortBytes(value))
+            } else {
+                value.strip_suffix(&[0]).map(|value| EncodeType::SortBytes(value.to_vec()))
+            }
+        },
+        // the other types have no nulls inside, a descending field steps the other way
+        _ => step_value(value, up != is_reverse),
+    }
+}
+
+// The value at the end of the field's order (`up`) or at its start, `None` when there is no such value.
+fn edge(the_type: &DecodeType, is_reverse: bool, up: bool) -> Option<EncodeType> {
+    match the_type {
+        DecodeType::DecodeOption(inner, null_order) => {
+            if up == (*null_order == NullOrder::NullsLast) {
+                Some(EncodeType::SortOption(None))
+            } else {
+                edge(inner, is_reverse, up).map(present)
+            }
+        },
+        DecodeType::DecodeTuple(inner) => {
+            let values = fields(inner).into_iter().map(|(the_type, inner_reverse)| edge(the_type, is_reverse != inner_reverse, up)).collect::<Option<Vec<_>>>()?;
+            Some(EncodeType::SortTuple(values))
+        },
+        DecodeType::DecodeUnion(variants) => {
+            let variant = if up != is_reverse { variants.len().checked_sub(1)? } else { 0 };
+            let value = edge(variants.get(variant)?, is_reverse, up)?;
+            Some(EncodeType::SortUnion(variant as u8, Box::new(value)))
+        },
+        // there is no longest list or string, the empty one is at the other end
+        DecodeType::DecodeList(_) => (up == is_reverse).then(|| EncodeType::SortList(Vec::new())),
+        DecodeType::DecodeString => (up == is_reverse).then(|| EncodeType::SortString(String::new())),
+        DecodeType::DecodeBytes => (up == is_reverse).then(|| EncodeType::SortBytes(Vec::new())),
+        DecodeType::DecodeCollatedString(collation, stored_sort_key) => (up == is_reverse).then(|| EncodeType::SortCollatedString(CollatedString::new("", *collation, *stored_sort_key))),
+        _ => edge_value(the_type, up != is_reverse),
+    }
+}
+
+fn present(value: EncodeType) -> EncodeType {
+    EncodeType::SortOption(Some(Box::new(value)))
+}
+
+// the next larger (`up`) or smaller value of a type without nulls inside
+fn step_value(value: &EncodeType, up: bool) -> Option<EncodeType> {
+    macro_rules! step_int {
+        ($value:expr, $variant:expr) => {
+            if up { $value.checked_add(1) } else { $value.checked_sub(1) }.map($variant)
+        };
+    }
+    match value {
+        EncodeType::SortU8(value) => step_int!(value, EncodeType::SortU8),
+        EncodeType::SortU16(value) => step_int!(value, EncodeType::SortU16),
+        EncodeType::SortU32(value) => step_int!(value, EncodeType::SortU32),
+        EncodeType::SortU64(value) => step_int!(value, EncodeType::SortU64),
+        EncodeType::SortU128(value) => step_int!(value, EncodeType::SortU128),
+        EncodeType::SortI8(value) => step_int!(value, EncodeType::SortI8),
+        EncodeType::SortI16(value) => step_int!(value, EncodeType::SortI16),
+        EncodeType::SortI32(value) => step_int!(value, EncodeType::SortI32),
+        EncodeType::SortI64(value) => step_int!(value, EncodeType::SortI64),
+        EncodeType::SortI128(value) => step_int!(value, EncodeType::SortI128),
+        EncodeType::SortVarU64(value) => step_int!(value, EncodeType::SortVarU64),
+        EncodeType::SortVarI64(value) => step_int!(value, EncodeType::SortVarI64),
+        EncodeType::SortBool(value) => (*value != up).then_some(EncodeType::SortBool(up)),
+        EncodeType::SortF32(value) => if up { value.next_up() } else { value.next_down() }.map(EncodeType::SortF32),
+        EncodeType::SortF64(value) => if up { value.next_up() } else { value.next_down() }.map(EncodeType::SortF64),
+        EncodeType::SortF32Total(value) => step_int!(value.to_sortable_bits(), F32total::from_sortable_bits).map(EncodeType::SortF32Total),
+        EncodeType::SortF64Total(value) => step_int!(value.to_sortable_bits(), F64total::from_sortable_bits).map(EncodeType::SortF64Total),
+        EncodeType::SortTimestamp(value) => step_int!(value.since_epoch, |since_epoch| EncodeType::SortTimestamp(Timestamp::new(since_epoch, value.unit))),
+        EncodeType::SortDate(value) => step_int!(value.days_since_epoch, |days| EncodeType::SortDate(Date::new(days))),
+        EncodeType::SortDuration(value) => {
+            let nanosecond = Duration::from_nanos(1);
+            if up { value.checked_add(nanosecond) } else { value.checked_sub(nanosecond) }.map(EncodeType::SortDuration)
+        },
+        EncodeType::SortFixedBytes(value) => step_bytes(value, up).map(EncodeType::SortFixedBytes),
+        EncodeType::SortUuid(value) => step_bytes(value, up).map(|bytes| EncodeType::SortUuid(bytes.try_into().unwrap())),
+        _ => None,
+    }
+}
+
+// big endian increment or decrement with carry, `None` on overflow
+fn step_bytes(value: &[u8], up: bool) -> Option<Vec<u8>> {
+    let (end, reset) = if up { (0xff, 0x00) } else { (0x00, 0xff) };
+    let index = value.iter().rposition(|byte| *byte != end)?;
+    let mut result = value.to_vec();
+    result[index] = if up { result[index] + 1 } else { result[index] - 1 };
+    result[index + 1..].fill(reset);
+    Some(result)
+}
+
+// the largest (`up`) or smallest value of a type without nulls inside
+fn edge_value(the_type: &DecodeType, up: bool) -> Option<EncodeType> {
+    macro_rules! edge_int {
+        ($int:ty, $variant:path) => {
+            Some($variant(if up { <$int>::MAX } else { <$int>::MIN }))
+        };
+    }
+    match the_type {
+        DecodeType::DecodeU8 => edge_int!(u8, EncodeType::SortU8),
+        DecodeType::DecodeU16 => edge_int!(u16, EncodeType::SortU16),
+        DecodeType::DecodeU32 => edge_int!(u32, EncodeType::SortU32),
+        DecodeType::DecodeU64 => edge_int!(u64, EncodeType::SortU64),
+        DecodeType::DecodeU128 => edge_int!(u128, EncodeType::SortU128),
+        DecodeType::DecodeI8 => edge_int!(i8, EncodeType::SortI8),
+        DecodeType::DecodeI16 => edge_int!(i16, EncodeType::SortI16),
+        DecodeType::DecodeI32 => edge_int!(i32, EncodeType::SortI32),
+        DecodeType::DecodeI64 => edge_int!(i64, EncodeType::SortI64),
+        DecodeType::DecodeI128 => edge_int!(i128, EncodeType::SortI128),
+        DecodeType::DecodeVarU64 => edge_int!(u64, EncodeType::SortVarU64),
+        DecodeType::DecodeVarI64 => edge_int!(i64, EncodeType::SortVarI64),
+        DecodeType::DecodeBool => Some(EncodeType::SortBool(up)),
+        // every NaN sorts first
+        DecodeType::DecodeF32 => Some(EncodeType::SortF32(F32struct::new(if up { f32::INFINITY } else { f32::NAN }))),
+        DecodeType::DecodeF64 => Some(EncodeType::SortF64(F64struct::new(if up { f64::INFINITY } else { f64::NAN }))),
+        DecodeType::DecodeF32Total => Some(EncodeType::SortF32Total(F32total::from_sortable_bits(if up { u32::MAX } else { 0 }))),
+        DecodeType::DecodeF64Total => Some(EncodeType::SortF64Total(F64total::from_sortable_bits(if up { u64::MAX } else { 0 }))),
+        DecodeType::DecodeTimestamp(unit) => Some(EncodeType::SortTimestamp(Timestamp::new(if up { i64::MAX } else { i64::MIN }, *unit))),
+        DecodeType::DecodeDate => Some(EncodeType::SortDate(Date::new(if up { i32::MAX } else { i32::MIN }))),
+        DecodeType::DecodeDuration => Some(EncodeType::SortDuration(if up { Duration::MAX } else { Duration::ZERO })),
+        DecodeType::DecodeFixedBytes(len) => Some(EncodeType::SortFixedBytes(vec![if up { 0xff } else { 0x00 }; *len])),
+        DecodeType::DecodeUuid => Some(EncodeType::SortUuid([if up { 0xff } else { 0x00 }; 16])),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rocks_sortable_keys::{compare_encoded_with, try_decode_byte_array};
+    use crate::rockssort_decimal::Decimal;
+    use std::cmp::Ordering;
+
+    // checks the neighbours of every key and of every shorter key they start against the comparator
+    // in both prefix orders, no other key may sort between a key and its neighbour
+    fn check_neighbours(the_types: &[DecodeType], keys: &[Vec<EncodeType>]) {
+        check_neighbours_with(the_types, PrefixOrder::ShorterFirst, keys);
+        check_neighbours_with(the_types, PrefixOrder::ShorterLast, keys);
+    }
+
+    fn check_neighbours_with(the_types: &[DecodeType], prefix_order: PrefixOrder, keys: &[Vec<EncodeType>]) {
+        let compare = |key1: &[u8], key2: &[u8]| compare_encoded_with(the_types, prefix_order, key1, key2);
+        let mut encoded: Vec<Vec<u8>> = keys.iter().flat_map(|key| (0..=key.len()).map(move |len| encode_keys(&key[..len]))).collect();
+        encoded.sort_by(|key1, key2| compare(key1, key2));
+        encoded.dedup();
+        let decoded = |key: &[u8]| try_decode_prefix(key, the_types).unwrap();
+        let between = |low: &[u8], high: &[u8]| encoded.iter().find(|key| compare(low, key) == Ordering::Less && compare(key, high) == Ordering::Less);
+        let mut found = 0;
+        for key in &encoded {
+            if let Some(next) = next_key_with(the_types, prefix_order, key).unwrap() {
+                found += 1;
+                assert_eq!(compare(key, &next), Ordering::Less, "next of {:?}", decoded(key));
+                assert_eq!(between(key, &next), None, "next of {:?} is {:?}", decoded(key), decoded(&next));
+                if let Some(back) = prev_key_with(the_types, prefix_order, &next).unwrap() {
+                    assert_eq!(back, *key, "prev of next of {:?}", decoded(key));
+                }
+            }
+            if let Some(prev) = prev_key_with(the_types, prefix_order, key).unwrap() {
+                found += 1;
+                assert_eq!(compare(&prev, key), Ordering::Less, "prev of {:?}", decoded(key));
+                assert_eq!(between(&prev, key), None, "prev of {:?} is {:?}", decoded(key), decoded(&prev));
+                if let Some(back) = next_key_with(the_types, prefix_order, &prev).unwrap() {
+                    assert_eq!(back, *key, "next of prev of {:?}", decoded(key));
+                }
+            }
+            if let Some(bound) = prefix_upper_bound_with(the_types, prefix_order, key).unwrap() {
+                let prefix = decoded(key);
+                for other in &encoded {
+                    let in_range = compare(other, key) != Ordering::Greater || decoded(other).starts_with(&prefix);
+                    assert_eq!(compare(other, &bound) == Ordering::Less, in_range, "bound of {:?} is {:?}, checking {:?}", prefix, decoded(&bound), decoded(other));
+                }
+            }
+        }
+        assert!(found >= encoded.len(), "{:?}: only {} neighbours of {} keys", prefix_order, found, encoded.len());
+    }
+
+    #[test]
+    fn test_integers_and_strings() {
+        let the_types = vec![DecodeType::Reverse, DecodeType::DecodeU8, DecodeType::DecodeString, DecodeType::DecodeI16];
+        let mut keys = Vec::new();
+        for first in [0, 1, 7, 254, 255] {
+            for second in ["", "\0", "a", "a\0", "a\0\0", "b"] {
+                for third in [i16::MIN, -1, 0, 1, i16::MAX] {
+                    keys.push(vec![EncodeType::SortU8(first), EncodeType::SortString(second.to_string()), EncodeType::SortI16(third)]);
+                }
+            }
+        }
+        check_neighbours(&the_types, &keys);
+
+        let key = |values: &[EncodeType]| encode_keys(values);
+        // descending: the next key holds the smaller value, the shorter key follows the largest value
+        assert_eq!(next_key(&the_types, &key(&[EncodeType::SortU8(7), EncodeType::SortString("a".to_string()), EncodeType::SortI16(i16::MAX)])).unwrap(), Some(key(&[EncodeType::SortU8(7), EncodeType::SortString("a\0".to_string())])));
+        assert_eq!(prefix_upper_bound(&the_types, &key(&[EncodeType::SortU8(7)])).unwrap(), Some(key(&[EncodeType::SortU8(6)])));
+        assert_eq!(prefix_upper_bound(&the_types, &key(&[EncodeType::SortU8(0)])).unwrap(), None);
+        assert_eq!(next_key(&the_types, &key(&[EncodeType::SortU8(7)])).unwrap(), Some(key(&[EncodeType::SortU8(7), EncodeType::SortString(String::new())])));
+        // no string is the largest one below "b"
+        assert_eq!(prev_key(&the_types, &key(&[EncodeType::SortU8(7), EncodeType::SortString("b".to_string())])).unwrap(), None);
+        assert_eq!(prev_key(&the_types, &key(&[EncodeType::SortU8(7), EncodeType::SortString("a\0".to_string())])).unwrap(), Some(key(&[EncodeType::SortU8(7), EncodeType::SortString("a".to_string()), EncodeType::SortI16(i16::MAX)])));
+        assert_eq!(prev_key(&the_types, &key(&[EncodeType::SortU8(7), EncodeType::SortString("a\0".to_string()), EncodeType::SortI16(i16::MIN)])).unwrap(), Some(key(&[EncodeType::SortU8(7), EncodeType::SortString("a\0".to_string())])));
+        assert_eq!(prev_key(&the_types, &[]).unwrap(), None);
+        assert!(next_key(&the_types, &[1, 2]).is_err());
+        // shorter keys sorting last: the shorter key follows the largest value, the next group starts with the smallest values
+        let last = PrefixOrder::ShorterLast;
+        assert_eq!(next_key_with(&the_types, last, &key(&[EncodeType::SortU8(7), EncodeType::SortString("a".to_string()), EncodeType::SortI16(i16::MAX)])).unwrap(), Some(key(&[EncodeType::SortU8(7), EncodeType::SortString("a".to_string())])));
+        assert_eq!(next_key_with(&the_types, last, &key(&[EncodeType::SortU8(7)])).unwrap(), Some(key(&[EncodeType::SortU8(6), EncodeType::SortString(String::new()), EncodeType::SortI16(i16::MIN)])));
+        assert_eq!(prefix_upper_bound_with(&the_types, last, &key(&[EncodeType::SortU8(7)])).unwrap(), Some(key(&[EncodeType::SortU8(6), EncodeType::SortString(String::new()), EncodeType::SortI16(i16::MIN)])));
+        assert_eq!(prefix_upper_bound_with(&the_types, last, &key(&[EncodeType::SortU8(0)])).unwrap(), Some(Vec::new()));
+        assert_eq!(next_key_with(&the_types, last, &[]).unwrap(), None);
+        assert_eq!(prev_key_with(&the_types, last, &key(&[EncodeType::SortU8(7), EncodeType::SortString("a".to_string())])).unwrap(), Some(key(&[EncodeType::SortU8(7), EncodeType::SortString("a".to_string()), EncodeType::SortI16(i16::MAX)])));
+        assert_eq!(prev_key_with(&the_types, last, &key(&[EncodeType::SortU8(7), EncodeType::SortString("a\0".to_string()), EncodeType::SortI16(i16::MIN)])).unwrap(), Some(key(&[EncodeType::SortU8(7), EncodeType::SortString("a".to_string())])));
+        // there is no largest string
+        assert_eq!(prev_key_with(&the_types, last, &key(&[EncodeType::SortU8(7)])).unwrap(), None);
+    }
+
+    #[test]
+    fn test_nullable_and_float_fields() {
+        let nullable = |null_order| DecodeType::DecodeOption(Box::new(DecodeType::DecodeI8), null_order);
+        let the_types = vec![nullable(NullOrder::NullsLast), DecodeType::Reverse, nullable(NullOrder::NullsFirst), DecodeType::DecodeF32, DecodeType::Reverse, DecodeType::DecodeBool];
+        let options = [None, Some(i8::MIN), Some(-1), Some(0), Some(i8::MAX)];
+        let mut keys = Vec::new();
+        for first in options {
+            for second in options {
+                for third in [f32::NAN, f32::NEG_INFINITY, -f32::MAX, -1.0, -f32::from_bits(1), -0.0, 0.0, f32::from_bits(1), 1.0, f32::MAX, f32::INFINITY] {
+                    for fourth in [false, true] {
+                        let option = |value: Option<i8>| EncodeType::SortOption(value.map(|value| Box::new(EncodeType::SortI8(value))));
+                        keys.push(vec![option(first), option(second), EncodeType::SortF32(F32struct::new(third)), EncodeType::SortBool(fourth)]);
+                    }
+                }
+            }
+        }
+        check_neighbours(&the_types, &keys);
+    }
+
+    #[test]
+    fn test_total_floats_and_times() {
+        let the_types = vec![DecodeType::DecodeF64Total, DecodeType::Reverse, DecodeType::DecodeDuration, DecodeType::DecodeFixedBytes(2)];
+        let mut keys = Vec::new();
+        for first in [f64::NAN, -f64::NAN, f64::NEG_INFINITY, -0.0, 0.0, 1.5, f64::INFINITY] {
+            for second in [Duration::ZERO, Duration::from_nanos(999_999_999), Duration::from_secs(1), Duration::MAX] {
+                for third in [[0, 0], [0, 255], [1, 0], [255, 255]] {
+                    keys.push(vec![EncodeType::SortF64Total(F64total::new(first)), EncodeType::SortDuration(second), EncodeType::SortFixedBytes(third.to_vec())]);
+                }
+            }
+        }
+        check_neighbours(&the_types, &keys);
+        assert_eq!(step_bytes(&[0, 255], true), Some(vec![1, 0]));
+        assert_eq!(step_bytes(&[1, 0], false), Some(vec![0, 255]));
+        assert_eq!(step_bytes(&[0, 0], false), None);
+    }
+
+    #[test]
+    fn test_nested_fields() {
+        let tuple = DecodeType::DecodeTuple(vec![DecodeType::DecodeBool, DecodeType::Reverse, DecodeType::DecodeOption(Box::new(DecodeType::DecodeU8), NullOrder::NullsFirst)]);
+        let union = DecodeType::DecodeUnion(vec![DecodeType::DecodeBool, DecodeType::DecodeU8]);
+        let list = DecodeType::DecodeList(Box::new(DecodeType::DecodeOption(Box::new(DecodeType::DecodeBool), NullOrder::NullsLast)));
+        let the_types = vec![DecodeType::Reverse, tuple, union.clone(), DecodeType::Reverse, union, list];
+        let tuples = [(false, None), (false, Some(0)), (false, Some(255)), (true, None), (true, Some(7))];
+        let unions = [(0, EncodeType::SortBool(false)), (0, EncodeType::SortBool(true)), (1, EncodeType::SortU8(0)), (1, EncodeType::SortU8(255))];
+        let lists: [&[Option<bool>]; 5] = [&[], &[None], &[Some(false)], &[Some(false), None], &[Some(true), Some(false)]];
+        let mut keys = Vec::new();
+        for (first, second) in tuples {
+            for (variant, value) in &unions {
+                for (reversed_variant, reversed_value) in &unions {
+                    for list in lists {
+                        keys.push(vec![
+                            EncodeType::SortTuple(vec![EncodeType::SortBool(first), EncodeType::SortOption(second.map(|value| Box::new(EncodeType::SortU8(value))))]),
+                            EncodeType::SortUnion(*variant, Box::new(value.clone())),
+                            EncodeType::SortUnion(*reversed_variant, Box::new(reversed_value.clone())),
+                            EncodeType::SortList(list.iter().map(|value| EncodeType::SortOption(value.map(|value| Box::new(EncodeType::SortBool(value))))).collect()),
+                        ]);
+                    }
+                }
+            }
+        }
+        check_neighbours(&the_types, &keys);
+    }
+
+    #[test]
+    fn test_values_without_neighbours() {
+        let the_types = vec![DecodeType::DecodeU8, DecodeType::DecodeDecimal];
+        let key = encode_keys(&[EncodeType::SortU8(1), EncodeType::SortDecimal(Decimal::new(15, 1))]);
+        assert_eq!(next_key(&the_types, &key).unwrap(), None);
+        assert_eq!(prev_key(&the_types, &key).unwrap(), None);
+        assert_eq!(prefix_upper_bound(&the_types, &key).unwrap(), None);
+        // the prefix before the decimal still has a bound, but no key holds the smallest decimal
+        let prefix = encode_keys(&[EncodeType::SortU8(1)]);
+        assert_eq!(try_decode_byte_array(&prefix_upper_bound(&the_types, &prefix).unwrap().unwrap(), &the_types[..1]).unwrap(), vec![EncodeType::SortU8(2)]);
+        assert_eq!(next_key(&the_types, &prefix).unwrap(), None);
+        assert_eq!(prev_key(&the_types, &encode_keys(&[EncodeType::SortU8(2)])).unwrap(), None);
+    }
+}
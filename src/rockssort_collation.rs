@@ -0,0 +1,124 @@
+// Strings ordered by a collation instead of by their utf-8 bytes.
+//
+// A collation maps a string to its sort key, e.g. "Zebra" to "zebra". Keys compare by the sort key
+// and then by the original string, so strings that only differ in case stay distinct keys and
+// always sort next to each other.
+//
+// The sort key can be stored in front of the string: the comparator then compares bytes and never
+// has to fold a string, at the cost of a larger key. The memcomparable encoding always stores it.
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use unicode_normalization::UnicodeNormalization;
+
+/// How the strings of a collated string field are ordered.
+///
+/// This is not a locale aware collation: accented letters still sort after `z`,
+/// as they do in the code point order of the normalized string.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Collation {
+    /// The utf-8 bytes, like a string field.
+    Binary,
+    /// `A` to `Z` compare like `a` to `z`, other characters are unchanged.
+    AsciiCaseFold,
+    /// Every character compares like its lower case form after upper casing, so `ß` is equal to `ss` and `ς` to `σ`.
+    UnicodeCaseFold,
+    /// Canonically equivalent strings compare equal, e.g. `é` written as one or as two code points.
+    Nfc,
+    /// Compatibility equivalent strings compare equal as well, e.g. `ﬁ` and `fi`.
+    Nfkc,
+    /// `Nfkc` and `UnicodeCaseFold` together.
+    NfkcCaseFold,
+}
+
+impl Collation {
+    /// The string that is compared in place of `value`.
+    pub fn sort_key(self, value: &str) -> Cow<'_, str> {
+        match self {
+            Collation::Binary => Cow::Borrowed(value),
+            Collation::AsciiCaseFold => Cow::Owned(value.to_ascii_lowercase()),
+            Collation::UnicodeCaseFold => Cow::Owned(case_fold(value)),
+            Collation::Nfc => Cow::Owned(value.nfc().collect()),
+            Collation::Nfkc => Cow::Owned(value.nfkc().collect()),
+            Collation::NfkcCaseFold => Cow::Owned(case_fold(&value.nfkc().collect::<String>()).nfkc().collect()),
+        }
+    }
+
+    /// Compares by the sort keys, equal sort keys by the strings.
+    pub fn compare(self, value1: &str, value2: &str) -> Ordering {
+        self.sort_key(value1).cmp(&self.sort_key(value2)).then_with(|| value1.cmp(value2))
+    }
+}
+
+// char wise, so a final sigma folds like any other sigma
+fn case_fold(value: &str) -> String {
+    value.chars().flat_map(char::to_uppercase).flat_map(char::to_lowercase).collect()
+}
+
+/// A string of a collated string field.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CollatedString {
+    pub value: String,
+    pub collation: Collation,
+    /// Whether the sort key is stored in front of the string, it is part of the field type.
+    pub stored_sort_key: bool,
+}
+
+impl CollatedString {
+    pub fn new(value: impl Into<String>, collation: Collation, stored_sort_key: bool) -> Self {
+        Self {
+            value: value.into(),
+            collation,
+            stored_sort_key
+        }
+    }
+
+    pub fn sort_key(&self) -> Cow<'_, str> {
+        self.collation.sort_key(&self.value)
+    }
+}
+
+impl Ord for CollatedString {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.collation.compare(&self.value, &other.value)
+    }
+}
+
+impl PartialOrd for CollatedString {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted(collation: Collation, values: &[&str]) -> Vec<String> {
+        let mut values: Vec<String> = values.iter().map(|value| value.to_string()).collect();
+        values.sort_by(|a, b| collation.compare(a, b));
+        values
+    }
+
+    #[test]
+    fn test_case_folding() {
+        assert_eq!(sorted(Collation::Binary, &["apple", "Zebra", "Apple"]), ["Apple", "Zebra", "apple"]);
+        assert_eq!(sorted(Collation::AsciiCaseFold, &["apple", "Zebra", "Apple"]), ["Apple", "apple", "Zebra"]);
+        assert_eq!(Collation::AsciiCaseFold.sort_key("ÄBC"), "Äbc");
+        assert_eq!(Collation::UnicodeCaseFold.sort_key("ÄBC Straße"), "äbc strasse");
+        assert_eq!(Collation::UnicodeCaseFold.sort_key("ΟΔΟΣ"), Collation::UnicodeCaseFold.sort_key("οδος"));
+        assert_eq!(Collation::UnicodeCaseFold.compare("Straße", "STRASSE"), "Straße".cmp("STRASSE"));
+    }
+
+    #[test]
+    fn test_normalization() {
+        let composed = "caf\u{e9}";
+        let decomposed = "cafe\u{301}";
+        assert_eq!(Collation::Nfc.sort_key(decomposed), composed);
+        assert_ne!(Collation::Binary.sort_key(decomposed), composed);
+        assert_eq!(Collation::Nfc.sort_key("\u{fb01}"), "\u{fb01}");
+        assert_eq!(Collation::Nfkc.sort_key("\u{fb01}x"), "fix");
+        assert_eq!(Collation::NfkcCaseFold.sort_key("\u{fb01}X CAFE\u{301}"), "fix caf\u{e9}");
+        // equal sort keys still give distinct, neighbouring keys, the accent sorts after z
+        assert_eq!(sorted(Collation::Nfc, &[composed, "cafe", decomposed, "cafz"]), ["cafe", "cafz", decomposed, composed]);
+    }
+}
@@ -0,0 +1,280 @@
+use std::cmp::Ordering;
+use std::fs;
+use std::io::{self, Write};
+use std::marker::PhantomData;
+use std::ops::Bound;
+use std::path::Path;
+use rocksdb::{DBRawIterator, Options, DB};
+use crate::rocks_sortable_keys::{try_compare_common_fields, try_decode_prefix, DecodeType, EncodeType, PrefixOrder};
+use crate::rockssort_bounds::{first_value, prefix_upper_bound};
+use crate::rockssort_error::{DbError, KeyError};
+use crate::rockssort_key::{KeyValues, SortableKey};
+use crate::rockssort_schema::KeySchema;
+
+// the descriptor of the schema, next to the files of rocksdb
+const SCHEMA_FILE: &str = "SORTABLE_KEY_SCHEMA";
+
+/// A RocksDB database whose keys are compound keys of type `K`, ordered by a `KeySchema`.
+///
+/// `K` is either a type implementing `SortableKey` or a plain `Vec<EncodeType>`.
+pub struct SortedDb<K> {
+    db: DB,
+    schema: KeySchema,
+    the_types: Vec<DecodeType>,
+    key: PhantomData<fn() -> K>,
+}
+
+impl<K: KeyValues> SortedDb<K> {
+    /// Opens or creates the database at `path` with the comparator of `schema`.
+    pub fn open<P: AsRef<Path>>(path: P, schema: KeySchema) -> Result<Self, DbError> {
+        let mut opts = Options::default();
+        opts.create_if_missing(true);
+        Self::open_with_options(opts, path, schema)
+    }
+
+    /// Like `open`, with caller provided options. The comparator in `opts` is replaced by the one of `schema`.
+    ///
+    /// The schema is stored in the database directory and checked on every later open:
+    /// a schema that orders the keys differently is refused with `DbError::SchemaMismatch`.
+    /// Field names are not part of the order, renaming a field updates the stored schema.
+    pub fn open_with_options<P: AsRef<Path>>(mut opts: Options, path: P, schema: KeySchema) -> Result<Self, DbError> {
+        let path = path.as_ref();
+        let stored = Self::stored_schema(path)?;
+        if let Some(stored) = &stored {
+            if !stored.same_order(&schema) {
+                return Err(DbError::SchemaMismatch { stored: stored.clone(), requested: schema });
+            }
+        }
+        schema.set_comparator(&mut opts);
+        let db = DB::open(&opts, path)?;
+        if stored.as_ref() != Some(&schema) {
+            write_schema_file(path, &schema)?;
+        }
+        let the_types = schema.decode_types();
+        Ok(Self { db, schema, the_types, key: PhantomData })
+    }
+
+    /// The schema stored in the database directory at `path`, `None` when there is none.
+    pub fn stored_schema<P: AsRef<Path>>(path: P) -> Result<Option<KeySchema>, DbError> {
+        match fs::read(path.as_ref().join(SCHEMA_FILE)) {
+            Ok(data) => Ok(Some(KeySchema::from_descriptor(&data)?)),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    /// Destroys the database at `path` together with its stored schema.
+    pub fn destroy<P: AsRef<Path>>(opts: &Options, path: P) -> Result<(), DbError> {
+        let path = path.as_ref();
+        DB::destroy(opts, path)?;
+        match fs::remove_file(path.join(SCHEMA_FILE)) {
+            Err(error) if error.kind() != io::ErrorKind::NotFound => return Err(error.into()),
+            _ => (),
+        }
+        // rocksdb leaves the directory behind when it still contained the schema file
+        let _ = fs::remove_dir(path);
+        Ok(())
+    }
+
+    pub fn schema(&self) -> &KeySchema {
+        &self.schema
+    }
+
+    /// The underlying database, for operations the wrapper does not cover.
+    pub fn db(&self) -> &DB {
+        &self.db
+    }
+
+    fn encode(&self, key: &K) -> Result<Vec<u8>, DbError> {
+        Ok(self.schema.encode_key(&key.to_encode_types())?)
+    }
+
+    pub fn put<V: AsRef<[u8]>>(&self, key: &K, value: V) -> Result<(), DbError> {
+        Ok(self.db.put(self.encode(key)?, value)?)
+    }
+
+    pub fn get(&self, key: &K) -> Result<Option<Vec<u8>>, DbError> {
+        Ok(self.db.get(self.encode(key)?)?)
+    }
+
+    pub fn delete(&self, key: &K) -> Result<(), DbError> {
+        Ok(self.db.delete(self.encode(key)?)?)
+    }
+
+    /// Iterates over all keys and values in key order.
+    pub fn iter(&self) -> SortedIter<'_, K> {
+        let mut iter = self.db.raw_iterator();
+        iter.seek_to_first();
+        SortedIter { iter, schema: &self.schema, the_types: &self.the_types, end: None, done: false, key: PhantomData }
+    }
+
+    /// Iterates over the keys that start with the values in `prefix`.
+    pub fn prefix(&self, prefix: &[EncodeType]) -> Result<SortedIter<'_, K>, DbError> {
+        self.range(prefix, Bound::Unbounded, Bound::Unbounded)
+    }
+
+    /// Iterates over the keys that start with the values in `prefix` and where the next field is within `lower` and `upper`.
+    /// The bounds are values of that field, for a descending field the scan starts at `upper`.
+    ///
+    /// For example all keys where field 0 is "a" and field 1 is between 10 and 20:
+    /// `db.range(&[EncodeType::SortString("a".to_string())], Bound::Included(EncodeType::SortU32(10)), Bound::Included(EncodeType::SortU32(20)))`
+    pub fn range(&self, prefix: &[EncodeType], lower: Bound<EncodeType>, upper: Bound<EncodeType>) -> Result<SortedIter<'_, K>, DbError> {
+        let descending = self.schema.fields().get(prefix.len()).is_some_and(|field| field.descending);
+        // the bounds in key order
+        let (start, end) = if descending { (upper, lower) } else { (lower, upper) };
+        let with_bound = |value: &EncodeType| [prefix, std::slice::from_ref(value)].concat();
+        let bound_key = |value: &EncodeType| self.schema.encode_prefix(&with_bound(value));
+        let prefix_key = self.schema.encode_prefix(prefix)?;
+
+        let (mut start_values, mut skip_equal) = match &start {
+            Bound::Included(value) => (with_bound(value), false),
+            Bound::Excluded(value) => (with_bound(value), true),
+            Bound::Unbounded => (prefix.to_vec(), false),
+        };
+        // an excluded start is passed by seeking to the first key after all keys starting with it,
+        // the keys equal to it are only skipped one by one when its value has no next value.
+        // The bound is taken with shorter keys first and only its values are used: with shorter keys last
+        // the shorter bound sorts after its group, and `seek_key` turns the values into the first key of the group.
+        if skip_equal {
+            if let Some(after) = prefix_upper_bound(&self.the_types, &self.schema.encode_prefix(&start_values)?)? {
+                start_values = try_decode_prefix(&after, &self.the_types)?;
+                skip_equal = false;
+            }
+        }
+        let start_key = self.schema.encode_prefix(&start_values)?;
+        let mut iter = self.db.raw_iterator();
+        match self.seek_key(start_values)? {
+            Some(seek_key) => iter.seek(seek_key),
+            // shorter keys sort last, so the start key is right after the keys starting with it
+            None if skip_equal => iter.seek(&start_key),
+            None => {
+                iter.seek_for_prev(&start_key);
+                while iter.valid() && try_compare_common_fields(&self.the_types, iter.key().unwrap(), &start_key)?.0 == Ordering::Equal {
+                    iter.prev();
+                }
+                if iter.valid() {
+                    iter.next();
+                } else {
+                    iter.seek_to_first();
+                }
+            },
+        }
+        // the keys equal to an excluded start without a next value
+        while iter.valid() {
+            let result = try_compare_common_fields(&self.the_types, iter.key().unwrap(), &start_key)?.0;
+            if result == Ordering::Greater || (result == Ordering::Equal && !skip_equal) {
+                break;
+            }
+            iter.next();
+        }
+        let end = match &end {
+            Bound::Included(value) => RangeEnd { key: bound_key(value)?, inclusive: true },
+            Bound::Excluded(value) => RangeEnd { key: bound_key(value)?, inclusive: false },
+            // a key outside of the prefix compares greater than the prefix key
+            Bound::Unbounded => RangeEnd { key: prefix_key, inclusive: true },
+        };
+        Ok(SortedIter { iter, schema: &self.schema, the_types: &self.the_types, end: Some(end), done: false, key: PhantomData })
+    }
+
+    // A key that sorts before every key starting with `values` and after all keys before them.
+    // When shorter keys sort last that is the key with the first value in every following field,
+    // `None` when a field has no first value, e.g. a descending string. `range` then steps back
+    // from the start key over the keys starting with `values`, which it reads next anyway.
+    fn seek_key(&self, mut values: Vec<EncodeType>) -> Result<Option<Vec<u8>>, KeyError> {
+        if self.schema.prefix_order() == PrefixOrder::ShorterLast {
+            for field in self.schema.fields().iter().skip(values.len()) {
+                match first_value(&field.decode_type(), field.descending) {
+                    Some(value) => values.push(value),
+                    None => return Ok(None),
+                }
+            }
+        }
+        self.schema.encode_prefix(&values).map(Some)
+    }
+}
+
+// written to a temporary file and renamed, so a crash never leaves a partial descriptor behind
+fn write_schema_file(path: &Path, schema: &KeySchema) -> Result<(), DbError> {
+    let temp_path = path.join(format!("{}.tmp", SCHEMA_FILE));
+    let mut file = fs::File::create(&temp_path)?;
+    file.write_all(&schema.to_descriptor())?;
+    file.sync_all()?;
+    fs::rename(&temp_path, path.join(SCHEMA_FILE))?;
+    Ok(())
+}
+
+// the iteration stops at the first key whose leading fields compare greater than (or equal to, when not inclusive) `key`
+struct RangeEnd {
+    key: Vec<u8>,
+    inclusive: bool,
+}
+
+impl<K: SortableKey> SortedDb<K> {
+    /// Opens or creates the database at `path` with the schema of `K`.
+    pub fn open_typed<P: AsRef<Path>>(path: P) -> Result<Self, DbError> {
+        Self::open(path, K::key_schema())
+    }
+}
+
+/// Iterator over the decoded keys and the values of a `SortedDb`.
+pub struct SortedIter<'a, K> {
+    iter: DBRawIterator<'a>,
+    schema: &'a KeySchema,
+    the_types: &'a [DecodeType],
+    end: Option<RangeEnd>,
+    done: bool,
+    key: PhantomData<fn() -> K>,
+}
+
+impl<'a, K: KeyValues> SortedIter<'a, K> {
+    fn past_end(&self, key: &[u8]) -> Result<bool, DbError> {
+        let end = match &self.end {
+            Some(end) => end,
+            None => return Ok(false),
+        };
+        let result = try_compare_common_fields(self.the_types, key, &end.key)?.0;
+        Ok(result == Ordering::Greater || (result == Ordering::Equal && !end.inclusive))
+    }
+}
+
+impl<'a, K: KeyValues> Iterator for SortedIter<'a, K> {
+    type Item = Result<(K, Vec<u8>), DbError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        if !self.iter.valid() {
+            self.done = true;
+            return self.iter.status().err().map(|error| Err(error.into()));
+        }
+        let raw_key = self.iter.key().unwrap();
+        match self.past_end(raw_key) {
+            Ok(false) => (),
+            Ok(true) => {
+                self.done = true;
+                return None;
+            },
+            Err(error) => {
+                self.done = true;
+                return Some(Err(error));
+            },
+        }
+        let item = self.schema.decode_key(raw_key)
+            .and_then(K::from_encode_types)
+            .map(|key| (key, self.iter.value().unwrap().to_vec()))
+            .map_err(DbError::from);
+        self.iter.next();
+        Some(item)
+    }
+}
+
+impl KeyValues for Vec<EncodeType> {
+    fn to_encode_types(&self) -> Vec<EncodeType> {
+        self.clone()
+    }
+
+    fn from_encode_types(values: Vec<EncodeType>) -> Result<Self, KeyError> {
+        Ok(values)
+    }
+}
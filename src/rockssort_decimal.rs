@@ -0,0 +1,353 @@
+// Exact decimal numbers, stored so that the bytes sort in numeric order:
+//
+//   sign          0x01 negative, 0x02 zero (nothing follows), 0x03 positive
+//   exponent      i32 with the sign bit flipped, big endian, the value is 0.d1 d2 d3 ... * 10^exponent
+//   digits        two digits per byte, each stored as digit + 1 in a nibble, an odd count ends in a 0 nibble
+//   terminator    0x00
+//
+// Negative numbers have the exponent, digits and terminator inverted, so larger magnitudes sort first.
+// The digits have no leading or trailing zeros, so equal numbers have equal bytes: 1.50 is stored as 1.5.
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+const NEGATIVE: u8 = 0x01;
+const ZERO: u8 = 0x02;
+const POSITIVE: u8 = 0x03;
+const TERMINATOR: u8 = 0x00;
+
+/// An arbitrary precision decimal number for keys that need exact numeric order, such as amounts of money.
+///
+/// ```
+/// use rocks_sortable_keys::Decimal;
+///
+/// let price: Decimal = "-12.50".parse().unwrap();
+/// assert_eq!(price, Decimal::new(-125, 1));
+/// assert_eq!(price.to_string(), "-12.5");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Decimal {
+    negative: bool,
+    // without leading and trailing zeros, empty for zero
+    digits: Vec<u8>,
+    exponent: i32,
+}
+
+/// The error returned when a string is not a decimal number.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseDecimalError;
+
+impl fmt::Display for ParseDecimalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid decimal number")
+    }
+}
+
+impl std::error::Error for ParseDecimalError {}
+
+impl Decimal {
+    /// `mantissa * 10^-scale`, the way `rust_decimal` describes a number.
+    ///
+    /// # Panics
+    ///
+    /// When `scale` is larger than `i32::MAX`, see `try_new`.
+    pub fn new(mantissa: i128, scale: u32) -> Self {
+        Self::try_new(mantissa, scale).expect("the scale of a decimal is at most i32::MAX")
+    }
+
+    /// `mantissa * 10^-scale`, `None` when `scale` is larger than `i32::MAX`.
+    pub fn try_new(mantissa: i128, scale: u32) -> Option<Self> {
+        let scale = i32::try_from(scale).ok()?;
+        let digits = mantissa.unsigned_abs().to_string().bytes().map(|digit| digit - b'0').collect();
+        Self::from_parts(mantissa < 0, digits, -(scale as i64))
+    }
+
+    pub fn zero() -> Self {
+        Self {
+            negative: false,
+            digits: Vec::new(),
+            exponent: 0
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.digits.is_empty()
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    // the number `digits * 10^exponent`, where `digits` is an integer written as decimal digits
+    fn from_parts(negative: bool, mut digits: Vec<u8>, exponent: i64) -> Option<Self> {
+        let leading = digits.iter().take_while(|digit| **digit == 0).count();
+        digits.drain(..leading);
+        let trailing = digits.iter().rev().take_while(|digit| **digit == 0).count();
+        digits.truncate(digits.len() - trailing);
+        if digits.is_empty() {
+            return Some(Self::zero());
+        }
+        // the point moves from after the last digit to before the first one
+        let exponent = exponent.checked_add(trailing as i64)?.checked_add(digits.len() as i64)?;
+        let exponent = i32::try_from(exponent).ok()?;
+        Some(Self { negative, digits, exponent })
+    }
+
+    fn sign_byte(&self) -> u8 {
+        if self.is_zero() {
+            ZERO
+        } else if self.negative {
+            NEGATIVE
+        } else {
+            POSITIVE
+        }
+    }
+
+    pub(crate) fn encode(&self, out: &mut Vec<u8>) {
+        out.push(self.sign_byte());
+        if self.is_zero() {
+            return;
+        }
+        let start = out.len();
+        out.extend(((self.exponent as u32) ^ 0x8000_0000).to_be_bytes());
+        for pair in self.digits.chunks(2) {
+            let low = pair.get(1).map_or(0, |digit| digit + 1);
+            out.push((pair[0] + 1) << 4 | low);
+        }
+        out.push(TERMINATOR);
+        if self.negative {
+            for byte in &mut out[start..] {
+                *byte = !*byte;
+            }
+        }
+    }
+
+    /// The length of the number at the start of `data`, with every byte xor-ed with `mask`.
+    /// `None` for an invalid sign byte, more than `data.len()` when the number is cut off.
+    pub(crate) fn encoded_len(data: &[u8], mask: u8) -> Option<usize> {
+        let terminator = match data.first().map(|sign| sign ^ mask) {
+            None | Some(ZERO) => return Some(1),
+            Some(NEGATIVE) => !TERMINATOR,
+            Some(POSITIVE) => TERMINATOR,
+            Some(_) => return None,
+        };
+        match data.get(5..).and_then(|rest| rest.iter().position(|byte| byte ^ mask == terminator)) {
+            Some(end) => Some(5 + end + 1),
+            None => Some(data.len() + 1),
+        }
+    }
+
+    /// Reads a number written by `encode`, `encoded` must have the length given by `encoded_len`.
+    pub(crate) fn decode(encoded: &[u8]) -> Option<Self> {
+        if encoded[0] == ZERO {
+            return Some(Self::zero());
+        }
+        let negative = encoded[0] == NEGATIVE;
+        let mask = if negative { 0xff } else { 0x00 };
+        let exponent_bytes: [u8; 4] = encoded[1..5].iter().map(|byte| byte ^ mask).collect::<Vec<u8>>().try_into().ok()?;
+        let exponent = (u32::from_be_bytes(exponent_bytes) ^ 0x8000_0000) as i32;
+        let packed = &encoded[5..encoded.len() - 1];
+        let mut digits = Vec::with_capacity(packed.len() * 2);
+        for (i, byte) in packed.iter().enumerate() {
+            let byte = byte ^ mask;
+            let (high, low) = (byte >> 4, byte & 0x0f);
+            if !(1..=10).contains(&high) || low > 10 || (low == 0 && i + 1 != packed.len()) {
+                return None;
+            }
+            digits.push(high - 1);
+            if low != 0 {
+                digits.push(low - 1);
+            }
+        }
+        // only the normalized form is valid, so equal numbers always have equal bytes
+        if !matches!(digits.first(), Some(1..=9)) || digits.last() == Some(&0) {
+            return None;
+        }
+        Some(Self { negative, digits, exponent })
+    }
+
+    #[cfg(feature = "rust_decimal")]
+    pub fn from_rust_decimal(value: rust_decimal::Decimal) -> Self {
+        Self::new(value.mantissa(), value.scale())
+    }
+
+    /// `None` when the number does not fit into a `rust_decimal::Decimal`.
+    #[cfg(feature = "rust_decimal")]
+    pub fn to_rust_decimal(&self) -> Option<rust_decimal::Decimal> {
+        let mut mantissa: i128 = 0;
+        for digit in &self.digits {
+            mantissa = mantissa.checked_mul(10)?.checked_add(*digit as i128)?;
+        }
+        let mut scale = self.digits.len() as i64 - self.exponent as i64;
+        while scale < 0 {
+            mantissa = mantissa.checked_mul(10)?;
+            scale += 1;
+        }
+        if self.negative {
+            mantissa = -mantissa;
+        }
+        rust_decimal::Decimal::try_from_i128_with_scale(mantissa, u32::try_from(scale).ok()?).ok()
+    }
+}
+
+impl Default for Decimal {
+    fn default() -> Self {
+        Self::zero()
+    }
+}
+
+impl Ord for Decimal {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let result = self.sign_byte().cmp(&other.sign_byte());
+        if result != Ordering::Equal || self.is_zero() {
+            return result;
+        }
+        let magnitude = self.exponent.cmp(&other.exponent).then_with(|| self.digits.cmp(&other.digits));
+        if self.negative {
+            magnitude.reverse()
+        } else {
+            magnitude
+        }
+    }
+}
+
+impl PartialOrd for Decimal {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Accepts numbers like `12`, `-0.5`, `+.25` and `1.5e-3`.
+impl FromStr for Decimal {
+    type Err = ParseDecimalError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (negative, value) = match value.as_bytes().first() {
+            Some(b'-') => (true, &value[1..]),
+            Some(b'+') => (false, &value[1..]),
+            _ => (false, value),
+        };
+        let (number, exponent) = match value.find(['e', 'E']) {
+            Some(index) => (&value[..index], value[index + 1..].parse::<i64>().map_err(|_| ParseDecimalError)?),
+            None => (value, 0),
+        };
+        let (integer, fraction) = number.split_once('.').unwrap_or((number, ""));
+        if integer.is_empty() && fraction.is_empty() {
+            return Err(ParseDecimalError);
+        }
+        let mut digits = Vec::with_capacity(integer.len() + fraction.len());
+        for digit in integer.bytes().chain(fraction.bytes()) {
+            if !digit.is_ascii_digit() {
+                return Err(ParseDecimalError);
+            }
+            digits.push(digit - b'0');
+        }
+        let exponent = exponent.checked_sub(fraction.len() as i64).ok_or(ParseDecimalError)?;
+        Self::from_parts(negative, digits, exponent).ok_or(ParseDecimalError)
+    }
+}
+
+/// Writes the number without exponent, e.g. `-0.005` or `1200`.
+impl fmt::Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_zero() {
+            return write!(f, "0");
+        }
+        if self.negative {
+            write!(f, "-")?;
+        }
+        let digits: String = self.digits.iter().map(|digit| (b'0' + digit) as char).collect();
+        let point = self.exponent as i64;
+        if point <= 0 {
+            write!(f, "0.{}{}", "0".repeat(point.unsigned_abs() as usize), digits)
+        } else if point as usize >= digits.len() {
+            write!(f, "{}{}", digits, "0".repeat(point as usize - digits.len()))
+        } else {
+            write!(f, "{}.{}", &digits[..point as usize], &digits[point as usize..])
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decimal(value: &str) -> Decimal {
+        value.parse().unwrap()
+    }
+
+    fn encoded(value: &Decimal) -> Vec<u8> {
+        let mut out = Vec::new();
+        value.encode(&mut out);
+        out
+    }
+
+    #[test]
+    fn test_parse_and_display() {
+        assert_eq!(decimal("1.50"), Decimal::new(15, 1));
+        assert_eq!(decimal("-0.0050"), Decimal::new(-5, 3));
+        assert_eq!(decimal("+.25"), Decimal::new(25, 2));
+        assert_eq!(decimal("12e3"), Decimal::new(12_000, 0));
+        assert_eq!(decimal("-0.000"), Decimal::zero());
+        assert_eq!(decimal("1.5e-3").to_string(), "0.0015");
+        assert_eq!(decimal("-1200").to_string(), "-1200");
+        assert_eq!(decimal("123.456").to_string(), "123.456");
+        for invalid in ["", "-", ".", "1.2.3", "1e", "e5", "12a", "1e99999999999", "1e9223372036854775807", "100e9223372036854775807", "0.1e-9223372036854775808"] {
+            assert_eq!(invalid.parse::<Decimal>(), Err(ParseDecimalError), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_scale_limit() {
+        assert_eq!(Decimal::try_new(1, u32::MAX), None);
+        assert_eq!(Decimal::try_new(1, i32::MAX as u32 + 1), None);
+        assert_eq!(Decimal::try_new(i128::MIN, i32::MAX as u32).map(|value| value.exponent), Some(-i32::MAX + 39));
+        assert_eq!(Decimal::try_new(0, u32::MAX), None);
+        assert_eq!(Decimal::try_new(-125, 1), Some(decimal("-12.5")));
+    }
+
+    #[test]
+    #[should_panic(expected = "the scale of a decimal is at most i32::MAX")]
+    fn test_new_panics_on_large_scale() {
+        Decimal::new(1, u32::MAX);
+    }
+
+    #[test]
+    fn test_bytes_sort_in_numeric_order() {
+        let values = ["-1e30", "-1000", "-999.99", "-10", "-9.5", "-1", "-0.99", "-0.1", "-0.011", "-0.01", "0", "0.01", "0.011", "0.1", "0.99", "1", "1.000001", "9.5", "10", "99.99", "100", "1e30"];
+        let values: Vec<Decimal> = values.iter().map(|value| decimal(value)).collect();
+        for pair in values.windows(2) {
+            assert_eq!(pair[0].cmp(&pair[1]), Ordering::Less, "{} {}", pair[0], pair[1]);
+            assert_eq!(encoded(&pair[0]).cmp(&encoded(&pair[1])), Ordering::Less, "{} {}", pair[0], pair[1]);
+        }
+        for value in &values {
+            let bytes = encoded(value);
+            assert_eq!(Decimal::encoded_len(&[bytes.clone(), vec![7, 7]].concat(), 0), Some(bytes.len()));
+            let inverted: Vec<u8> = bytes.iter().map(|byte| !byte).collect();
+            assert_eq!(Decimal::encoded_len(&inverted, 0xff), Some(bytes.len()));
+            assert_eq!(Decimal::decode(&bytes).as_ref(), Some(value));
+        }
+        assert_eq!(encoded(&decimal("1.5")), encoded(&decimal("1.50")));
+    }
+
+    #[test]
+    fn test_decode_rejects_invalid_bytes() {
+        let mut bytes = encoded(&decimal("15"));
+        assert_eq!(Decimal::encoded_len(&bytes[..bytes.len() - 1], 0), Some(bytes.len()));
+        assert_eq!(Decimal::encoded_len(&[], 0), Some(1));
+        assert_eq!(Decimal::encoded_len(&[7], 0), None);
+        // a trailing zero digit
+        bytes[5] = 0x21;
+        assert_eq!(Decimal::decode(&bytes), None);
+    }
+
+    #[cfg(feature = "rust_decimal")]
+    #[test]
+    fn test_rust_decimal_conversions() {
+        let value = rust_decimal::Decimal::new(-123_450, 3);
+        assert_eq!(Decimal::from_rust_decimal(value), decimal("-123.45"));
+        assert_eq!(decimal("-123.45").to_rust_decimal(), Some(value.normalize()));
+        assert_eq!(decimal("1e40").to_rust_decimal(), None);
+        assert_eq!(decimal("1e-40").to_rust_decimal(), None);
+    }
+}
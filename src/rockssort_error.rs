@@ -0,0 +1,108 @@
+use std::{fmt, io};
+use crate::rocks_sortable_keys::{DecodeType, EncodeType};
+use crate::rockssort_schema::KeySchema;
+
+/// Errors returned by the fallible (`try_`) decoding functions.
+/// `field` is the index of the field in the key (not counting `DecodeType::Reverse`) and `offset` is the byte position in the input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum KeyError {
+    /// The input ended while reading a field.
+    Truncated { field: usize, offset: usize, needed: usize, available: usize },
+    /// A string field does not contain valid UTF-8.
+    InvalidUtf8 { field: usize, offset: usize },
+    /// The bytes of a field are not a valid encoding of its type.
+    InvalidEncoding { field: usize, offset: usize },
+    /// A serialized type list contains a tag that is not known.
+    UnknownTypeTag { index: usize, tag: u8 },
+    /// All fields were decoded but there are bytes left in the input.
+    TrailingBytes { offset: usize, remaining: usize },
+    /// A key does not match the types it is used with. `None` means the field is missing,
+    /// `found` is also `None` when the value does not fit the Rust type of a `SortableKey` field.
+    SchemaMismatch { field: usize, expected: Option<DecodeType>, found: Option<EncodeType> },
+    /// A serialized `KeySchema` can not be read.
+    InvalidDescriptor { reason: &'static str },
+    /// The `DecodeType::Reverse` at `index` of a type list is not followed by a field, it is the last type or another `Reverse` follows.
+    MisplacedReverse { index: usize },
+}
+
+impl KeyError {
+    // decoders of a single field do not know their position in the key, the caller fills it in
+    pub(crate) fn at_field(self, field: usize) -> KeyError {
+        match self {
+            KeyError::Truncated { offset, needed, available, .. } => KeyError::Truncated { field, offset, needed, available },
+            KeyError::InvalidUtf8 { offset, .. } => KeyError::InvalidUtf8 { field, offset },
+            KeyError::InvalidEncoding { offset, .. } => KeyError::InvalidEncoding { field, offset },
+            other => other,
+        }
+    }
+}
+
+impl fmt::Display for KeyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeyError::Truncated { field, offset, needed, available } => write!(f, "key truncated in field {} at byte {}: needed {} bytes, {} available", field, offset, needed, available),
+            KeyError::InvalidUtf8 { field, offset } => write!(f, "invalid utf-8 in field {} at byte {}", field, offset),
+            KeyError::InvalidEncoding { field, offset } => write!(f, "invalid encoding in field {} at byte {}", field, offset),
+            KeyError::UnknownTypeTag { index, tag } => write!(f, "unknown type tag {} at index {}", tag, index),
+            KeyError::TrailingBytes { offset, remaining } => write!(f, "{} trailing bytes after the last field at byte {}", remaining, offset),
+            KeyError::SchemaMismatch { field, expected, found } => write!(f, "field {} does not match the schema: expected {:?}, found {:?}", field, expected, found),
+            KeyError::InvalidDescriptor { reason } => write!(f, "invalid schema descriptor: {}", reason),
+            KeyError::MisplacedReverse { index } => write!(f, "reverse at index {} is not followed by a field", index),
+        }
+    }
+}
+
+impl std::error::Error for KeyError {}
+
+/// Errors returned by `SortedDb`.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum DbError {
+    Rocks(rocksdb::Error),
+    Key(KeyError),
+    /// Reading or writing the stored schema failed.
+    Io(io::Error),
+    /// The database was created with a schema that orders the keys differently.
+    SchemaMismatch { stored: KeySchema, requested: KeySchema },
+}
+
+impl fmt::Display for DbError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DbError::Rocks(error) => write!(f, "rocksdb: {}", error),
+            DbError::Key(error) => write!(f, "{}", error),
+            DbError::Io(error) => write!(f, "schema file: {}", error),
+            DbError::SchemaMismatch { stored, requested } => write!(f, "the database was created with a schema that orders the keys differently, comparator {} but opened with {}", stored.comparator_name(), requested.comparator_name()),
+        }
+    }
+}
+
+impl std::error::Error for DbError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            DbError::Rocks(error) => Some(error),
+            DbError::Key(error) => Some(error),
+            DbError::Io(error) => Some(error),
+            DbError::SchemaMismatch { .. } => None,
+        }
+    }
+}
+
+impl From<rocksdb::Error> for DbError {
+    fn from(error: rocksdb::Error) -> Self {
+        DbError::Rocks(error)
+    }
+}
+
+impl From<KeyError> for DbError {
+    fn from(error: KeyError) -> Self {
+        DbError::Key(error)
+    }
+}
+
+impl From<io::Error> for DbError {
+    fn from(error: io::Error) -> Self {
+        DbError::Io(error)
+    }
+}
@@ -1,4 +1,14 @@
-
+// Two orderings are offered for floats:
+//
+//   F32struct / F64struct   NaN first: every NaN is equal and smaller than all other values,
+//                           -0.0 is equal to 0.0, everything else compares as usual.
+//                           Both encodings write bytes that sort in this order: NaN as all zero bits,
+//                           -0.0 as 0.0 and the other values like the total order below,
+//                           so equal values have equal bytes.
+//   F32total / F64total     IEEE 754 total order, the order of `f32::total_cmp`:
+//                           -NaN < -inf < ... < -0.0 < 0.0 < ... < inf < NaN.
+//                           Both encodings write bytes that sort in this order: the sign bit is flipped
+//                           for positive values and all bits are flipped for negative values.
 #[derive(Debug, Clone)]
 pub struct F32struct {
     pub value: f32
@@ -7,7 +17,7 @@ pub struct F32struct {
 #[derive(Debug, Clone)]
 pub struct F64struct {
     pub value: f64
-}   
+}
 
 fn compare_f32(a: &f32, b: &f32) -> std::cmp::Ordering {
     if a.is_nan() && b.is_nan() {
@@ -37,11 +47,11 @@ impl Ord for F32struct {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
         compare_f32(&self.value, &other.value)
     }
-}   
+}
 
 impl PartialOrd for F32struct {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        Some(compare_f32(&self.value, &other.value))
+        Some(self.cmp(other))
     }
 }
 
@@ -53,7 +63,7 @@ impl Ord for F64struct {
 
 impl PartialOrd for F64struct {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        Some(compare_f64(&self.value, &other.value))
+        Some(self.cmp(other))
     }
 }
 
@@ -78,6 +88,70 @@ impl F32struct {
             value
         }
     }
+
+    // the value with one representation for -0.0 / 0.0 and for all NaNs
+    pub(crate) fn canonical(&self) -> f32 {
+        if self.value.is_nan() {
+            f32::NAN
+        } else if self.value == 0.0 {
+            0.0
+        } else {
+            self.value
+        }
+    }
+
+    #[inline]
+    pub(crate) fn to_sortable_bits(&self) -> u32 {
+        let value = self.canonical();
+        if value.is_nan() {
+            return 0;
+        }
+        F32total::new(value).to_sortable_bits()
+    }
+
+    #[inline]
+    pub(crate) fn from_sortable_bits(bits: u32) -> Self {
+        Self::new(F32total::from_sortable_bits(bits).value)
+    }
+
+    // the next larger value in the NaN first order, every NaN is the smallest value and -0.0 is skipped
+    pub(crate) fn next_up(&self) -> Option<Self> {
+        let value = self.canonical();
+        if value.is_nan() {
+            return Some(Self::new(f32::NEG_INFINITY));
+        }
+        if value == f32::INFINITY {
+            return None;
+        }
+        let bits = value.to_bits();
+        let next = if value == 0.0 {
+            f32::from_bits(1)
+        } else if value > 0.0 {
+            f32::from_bits(bits + 1)
+        } else {
+            f32::from_bits(bits - 1)
+        };
+        Some(Self::new(Self::new(next).canonical()))
+    }
+
+    pub(crate) fn next_down(&self) -> Option<Self> {
+        let value = self.canonical();
+        if value.is_nan() {
+            return None;
+        }
+        if value == f32::NEG_INFINITY {
+            return Some(Self::new(f32::NAN));
+        }
+        let bits = value.to_bits();
+        let next = if value == 0.0 {
+            -f32::from_bits(1)
+        } else if value > 0.0 {
+            f32::from_bits(bits - 1)
+        } else {
+            f32::from_bits(bits + 1)
+        };
+        Some(Self::new(next))
+    }
 }
 
 impl F64struct {
@@ -86,4 +160,229 @@ impl F64struct {
             value
         }
     }
+
+    pub(crate) fn canonical(&self) -> f64 {
+        if self.value.is_nan() {
+            f64::NAN
+        } else if self.value == 0.0 {
+            0.0
+        } else {
+            self.value
+        }
+    }
+
+    #[inline]
+    pub(crate) fn to_sortable_bits(&self) -> u64 {
+        let value = self.canonical();
+        if value.is_nan() {
+            return 0;
+        }
+        F64total::new(value).to_sortable_bits()
+    }
+
+    #[inline]
+    pub(crate) fn from_sortable_bits(bits: u64) -> Self {
+        Self::new(F64total::from_sortable_bits(bits).value)
+    }
+
+    pub(crate) fn next_up(&self) -> Option<Self> {
+        let value = self.canonical();
+        if value.is_nan() {
+            return Some(Self::new(f64::NEG_INFINITY));
+        }
+        if value == f64::INFINITY {
+            return None;
+        }
+        let bits = value.to_bits();
+        let next = if value == 0.0 {
+            f64::from_bits(1)
+        } else if value > 0.0 {
+            f64::from_bits(bits + 1)
+        } else {
+            f64::from_bits(bits - 1)
+        };
+        Some(Self::new(Self::new(next).canonical()))
+    }
+
+    pub(crate) fn next_down(&self) -> Option<Self> {
+        let value = self.canonical();
+        if value.is_nan() {
+            return None;
+        }
+        if value == f64::NEG_INFINITY {
+            return Some(Self::new(f64::NAN));
+        }
+        let bits = value.to_bits();
+        let next = if value == 0.0 {
+            -f64::from_bits(1)
+        } else if value > 0.0 {
+            f64::from_bits(bits - 1)
+        } else {
+            f64::from_bits(bits + 1)
+        };
+        Some(Self::new(next))
+    }
+}
+
+/// An `f32` ordered by `f32::total_cmp`, encoded so that its bytes sort in the same order.
+#[derive(Debug, Clone)]
+pub struct F32total {
+    pub value: f32
+}
+
+/// An `f64` ordered by `f64::total_cmp`, encoded so that its bytes sort in the same order.
+#[derive(Debug, Clone)]
+pub struct F64total {
+    pub value: f64
+}
+
+impl F32total {
+    pub fn new(value: f32) -> Self {
+        Self {
+            value
+        }
+    }
+
+    #[inline]
+    pub(crate) fn to_sortable_bits(&self) -> u32 {
+        let bits = self.value.to_bits();
+        if bits & 0x8000_0000 != 0 {
+            !bits
+        } else {
+            bits ^ 0x8000_0000
+        }
+    }
+
+    #[inline]
+    pub(crate) fn from_sortable_bits(bits: u32) -> Self {
+        if bits & 0x8000_0000 != 0 {
+            Self::new(f32::from_bits(bits ^ 0x8000_0000))
+        } else {
+            Self::new(f32::from_bits(!bits))
+        }
+    }
+}
+
+impl F64total {
+    pub fn new(value: f64) -> Self {
+        Self {
+            value
+        }
+    }
+
+    #[inline]
+    pub(crate) fn to_sortable_bits(&self) -> u64 {
+        let bits = self.value.to_bits();
+        if bits & 0x8000_0000_0000_0000 != 0 {
+            !bits
+        } else {
+            bits ^ 0x8000_0000_0000_0000
+        }
+    }
+
+    #[inline]
+    pub(crate) fn from_sortable_bits(bits: u64) -> Self {
+        if bits & 0x8000_0000_0000_0000 != 0 {
+            Self::new(f64::from_bits(bits ^ 0x8000_0000_0000_0000))
+        } else {
+            Self::new(f64::from_bits(!bits))
+        }
+    }
+}
+
+impl Ord for F32total {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.value.total_cmp(&other.value)
+    }
+}
+
+impl PartialOrd for F32total {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for F32total {
+    fn eq(&self, other: &Self) -> bool {
+        self.value.to_bits() == other.value.to_bits()
+    }
+}
+
+impl Eq for F32total {}
+
+impl Ord for F64total {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.value.total_cmp(&other.value)
+    }
+}
+
+impl PartialOrd for F64total {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for F64total {
+    fn eq(&self, other: &Self) -> bool {
+        self.value.to_bits() == other.value.to_bits()
+    }
+}
+
+impl Eq for F64total {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cmp::Ordering;
+
+    // bit patterns spread over the whole range, together with the special values
+    fn sample_f32_bits() -> Vec<u32> {
+        let mut bits: Vec<u32> = (0..=u32::MAX).step_by(65_521).collect();
+        bits.extend([0, 1, 0x8000_0000, 0x8000_0001, 0x7f7f_ffff, 0xff7f_ffff, 0x7f80_0000, 0xff80_0000, 0x7f80_0001, 0x7fc0_0000, 0xffc0_0000, 0xffff_ffff]);
+        bits
+    }
+
+    fn sample_f64_bits() -> Vec<u64> {
+        let mut bits: Vec<u64> = (0..70_000u64).map(|i| i.wrapping_mul(0x9e37_79b9_7f4a_7c15)).collect();
+        bits.extend([0, 1, 1 << 63, (1 << 63) + 1, 0x7fef_ffff_ffff_ffff, 0xffef_ffff_ffff_ffff, 0x7ff0_0000_0000_0000, 0xfff0_0000_0000_0000, 0x7ff0_0000_0000_0001, 0x7ff8_0000_0000_0000, 0xfff8_0000_0000_0000, u64::MAX]);
+        bits
+    }
+
+    // after sorting by `Ord`, neighbouring values must compare the same by their encoded bits
+    fn assert_order_preserving<T: Ord + std::fmt::Debug, B: Ord>(mut values: Vec<T>, to_bits: impl Fn(&T) -> B) {
+        values.sort();
+        for pair in values.windows(2) {
+            assert_eq!(to_bits(&pair[0]).cmp(&to_bits(&pair[1])), pair[0].cmp(&pair[1]), "{:?} {:?}", pair[0], pair[1]);
+        }
+    }
+
+    #[test]
+    fn test_total_order_matches_total_cmp() {
+        let values: Vec<F32total> = sample_f32_bits().into_iter().map(|bits| F32total::new(f32::from_bits(bits))).collect();
+        for value in &values {
+            assert_eq!(F32total::from_sortable_bits(value.to_sortable_bits()), *value);
+        }
+        assert_order_preserving(values, F32total::to_sortable_bits);
+        let values: Vec<F64total> = sample_f64_bits().into_iter().map(|bits| F64total::new(f64::from_bits(bits))).collect();
+        for value in &values {
+            assert_eq!(F64total::from_sortable_bits(value.to_sortable_bits()), *value);
+        }
+        assert_order_preserving(values, F64total::to_sortable_bits);
+        assert_eq!(F64total::new(-0.0).cmp(&F64total::new(0.0)), Ordering::Less);
+        assert_eq!(F64total::new(-f64::NAN).cmp(&F64total::new(f64::NEG_INFINITY)), Ordering::Less);
+        assert_eq!(F64total::new(f64::NAN).cmp(&F64total::new(f64::INFINITY)), Ordering::Greater);
+    }
+
+    #[test]
+    fn test_nan_first_canonical_value() {
+        let values: Vec<F32struct> = sample_f32_bits().into_iter().map(|bits| F32struct::new(f32::from_bits(bits))).collect();
+        for one in values.iter().step_by(97) {
+            for two in values.iter().step_by(89) {
+                assert_eq!(one == two, one.canonical().to_bits() == two.canonical().to_bits(), "{:?} {:?}", one, two);
+            }
+        }
+        assert_eq!(F64struct::new(-0.0).canonical().to_bits(), 0);
+        assert_eq!(F64struct::new(-f64::NAN).canonical().to_bits(), f64::NAN.to_bits());
+        assert_eq!(F32struct::new(f32::NAN).cmp(&F32struct::new(f32::NEG_INFINITY)), Ordering::Less);
+    }
 }
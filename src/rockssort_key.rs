@@ -0,0 +1,438 @@
+use crate::rocks_sortable_keys::{encode_keys, matches_type, DecodeType, EncodeType, NullOrder};
+use crate::rockssort_error::KeyError;
+use crate::rockssort_float::{F32struct, F32total, F64struct, F64total};
+use crate::rockssort_schema::KeySchema;
+use crate::rockssort_decimal::Decimal;
+use crate::rockssort_time::{Date, TimeUnit, Timestamp};
+use std::time::{Duration, SystemTime};
+
+/// A Rust type that can be used as one field of a compound key.
+pub trait SortableField: Sized {
+    fn decode_type() -> DecodeType;
+    fn to_encode_type(&self) -> EncodeType;
+    fn from_encode_type(value: EncodeType) -> Option<Self>;
+}
+
+/// Conversion between a key and its list of field values.
+pub trait KeyValues: Sized {
+    fn to_encode_types(&self) -> Vec<EncodeType>;
+    fn from_encode_types(values: Vec<EncodeType>) -> Result<Self, KeyError>;
+}
+
+/// A key with a schema known at compile time, usually implemented with `#[derive(SortableKey)]`.
+///
+/// Fields are sorted in declaration order, `#[key(desc)]` sorts a field descending.
+#[cfg_attr(feature = "derive", doc = r#"
+```
+use rocks_sortable_keys::{SortableKey, DecodeType};
+
+#[derive(SortableKey, Debug, PartialEq)]
+struct Event {
+    customer: String,
+    #[key(desc)]
+    created: u64,
+}
+
+let event = Event { customer: "a".to_string(), created: 10 };
+let encoded = event.encode_key();
+assert_eq!(Event::decode_key(&encoded).unwrap(), event);
+assert_eq!(Event::key_schema().decode_types(), vec![DecodeType::DecodeString, DecodeType::Reverse, DecodeType::DecodeU64]);
+```
+"#)]
+pub trait SortableKey: KeyValues {
+    fn key_schema() -> KeySchema;
+
+    /// Encodes the values without checking them against `key_schema()`, which the derive macro keeps in sync.
+    /// A hand written `to_encode_types` that can disagree with the schema should use `try_encode_key`.
+    fn encode_key(&self) -> Vec<u8> {
+        encode_keys(&self.to_encode_types())
+    }
+
+    fn try_encode_key(&self) -> Result<Vec<u8>, KeyError> {
+        Self::key_schema().encode_key(&self.to_encode_types())
+    }
+
+    fn decode_key(data: &[u8]) -> Result<Self, KeyError> {
+        Self::from_encode_types(Self::key_schema().decode_key(data)?)
+    }
+}
+
+// used by the derive macro to take the fields out of a decoded key one by one
+#[doc(hidden)]
+pub fn next_field<T: SortableField>(values: &mut impl Iterator<Item = EncodeType>, field: usize) -> Result<T, KeyError> {
+    let expected = T::decode_type();
+    match values.next() {
+        // the value is moved into the field, so a value that does not fit the Rust type is not reported
+        Some(value) if matches_type(&value, &expected) => T::from_encode_type(value).ok_or(KeyError::SchemaMismatch { field, expected: Some(expected), found: None }),
+        found => Err(KeyError::SchemaMismatch { field, expected: Some(expected), found }),
+    }
+}
+
+#[doc(hidden)]
+pub fn no_more_fields(values: &mut impl Iterator<Item = EncodeType>, field: usize) -> Result<(), KeyError> {
+    match values.next() {
+        Some(value) => Err(KeyError::SchemaMismatch { field, expected: None, found: Some(value) }),
+        None => Ok(()),
+    }
+}
+
+macro_rules! sortable_field {
+    ($rust_type:ty, $decode_type:ident, $encode_type:ident) => {
+        impl SortableField for $rust_type {
+            fn decode_type() -> DecodeType {
+                DecodeType::$decode_type
+            }
+
+            fn to_encode_type(&self) -> EncodeType {
+                EncodeType::$encode_type(self.clone())
+            }
+
+            fn from_encode_type(value: EncodeType) -> Option<Self> {
+                match value {
+                    EncodeType::$encode_type(value) => Some(value),
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+sortable_field!(u8, DecodeU8, SortU8);
+sortable_field!(u16, DecodeU16, SortU16);
+sortable_field!(u32, DecodeU32, SortU32);
+sortable_field!(u64, DecodeU64, SortU64);
+sortable_field!(u128, DecodeU128, SortU128);
+sortable_field!(i8, DecodeI8, SortI8);
+sortable_field!(i16, DecodeI16, SortI16);
+sortable_field!(i32, DecodeI32, SortI32);
+sortable_field!(i64, DecodeI64, SortI64);
+sortable_field!(i128, DecodeI128, SortI128);
+sortable_field!(String, DecodeString, SortString);
+sortable_field!(Vec<u8>, DecodeBytes, SortBytes);
+sortable_field!(bool, DecodeBool, SortBool);
+sortable_field!(F32struct, DecodeF32, SortF32);
+sortable_field!(F64struct, DecodeF64, SortF64);
+sortable_field!(F32total, DecodeF32Total, SortF32Total);
+sortable_field!(F64total, DecodeF64Total, SortF64Total);
+sortable_field!(Date, DecodeDate, SortDate);
+sortable_field!(Duration, DecodeDuration, SortDuration);
+
+impl SortableField for f32 {
+    fn decode_type() -> DecodeType {
+        DecodeType::DecodeF32
+    }
+
+    fn to_encode_type(&self) -> EncodeType {
+        EncodeType::SortF32(F32struct::new(*self))
+    }
+
+    fn from_encode_type(value: EncodeType) -> Option<Self> {
+        F32struct::from_encode_type(value).map(|value| value.value)
+    }
+}
+
+impl SortableField for f64 {
+    fn decode_type() -> DecodeType {
+        DecodeType::DecodeF64
+    }
+
+    fn to_encode_type(&self) -> EncodeType {
+        EncodeType::SortF64(F64struct::new(*self))
+    }
+
+    fn from_encode_type(value: EncodeType) -> Option<Self> {
+        F64struct::from_encode_type(value).map(|value| value.value)
+    }
+}
+
+impl<const N: usize> SortableField for [u8; N] {
+    fn decode_type() -> DecodeType {
+        DecodeType::DecodeFixedBytes(N)
+    }
+
+    fn to_encode_type(&self) -> EncodeType {
+        EncodeType::SortFixedBytes(self.to_vec())
+    }
+
+    fn from_encode_type(value: EncodeType) -> Option<Self> {
+        match value {
+            EncodeType::SortFixedBytes(value) => value.try_into().ok(),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "uuid")]
+impl SortableField for uuid::Uuid {
+    fn decode_type() -> DecodeType {
+        DecodeType::DecodeUuid
+    }
+
+    fn to_encode_type(&self) -> EncodeType {
+        EncodeType::SortUuid(*self.as_bytes())
+    }
+
+    fn from_encode_type(value: EncodeType) -> Option<Self> {
+        match value {
+            EncodeType::SortUuid(value) => Some(uuid::Uuid::from_bytes(value)),
+            _ => None,
+        }
+    }
+}
+
+impl SortableField for Decimal {
+    fn decode_type() -> DecodeType {
+        DecodeType::DecodeDecimal
+    }
+
+    fn to_encode_type(&self) -> EncodeType {
+        EncodeType::SortDecimal(self.clone())
+    }
+
+    fn from_encode_type(value: EncodeType) -> Option<Self> {
+        match value {
+            EncodeType::SortDecimal(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+// the stored number has no trailing zeros, so 1.50 is read back as 1.5
+#[cfg(feature = "rust_decimal")]
+impl SortableField for rust_decimal::Decimal {
+    fn decode_type() -> DecodeType {
+        DecodeType::DecodeDecimal
+    }
+
+    fn to_encode_type(&self) -> EncodeType {
+        EncodeType::SortDecimal(Decimal::from_rust_decimal(*self))
+    }
+
+    fn from_encode_type(value: EncodeType) -> Option<Self> {
+        match value {
+            EncodeType::SortDecimal(value) => value.to_rust_decimal(),
+            _ => None,
+        }
+    }
+}
+
+// the type of a field can not carry a unit, so points in time are stored in microseconds, which covers the
+// whole range of chrono and time. Finer values are rounded down and read back without their nanoseconds.
+const FIELD_TIME_UNIT: TimeUnit = TimeUnit::Micros;
+
+fn field_timestamp(timestamp: Option<Timestamp>) -> EncodeType {
+    EncodeType::SortTimestamp(timestamp.expect("a point in time of a key field fits into an i64 of microseconds"))
+}
+
+fn from_field_timestamp(value: EncodeType) -> Option<Timestamp> {
+    match value {
+        EncodeType::SortTimestamp(value) if value.unit == FIELD_TIME_UNIT => Some(value),
+        _ => None,
+    }
+}
+
+// a timestamp in another unit is converted, so it is read back in microseconds
+impl SortableField for Timestamp {
+    fn decode_type() -> DecodeType {
+        DecodeType::DecodeTimestamp(FIELD_TIME_UNIT)
+    }
+
+    fn to_encode_type(&self) -> EncodeType {
+        field_timestamp(self.to_unit(FIELD_TIME_UNIT))
+    }
+
+    fn from_encode_type(value: EncodeType) -> Option<Self> {
+        from_field_timestamp(value)
+    }
+}
+
+impl SortableField for SystemTime {
+    fn decode_type() -> DecodeType {
+        DecodeType::DecodeTimestamp(FIELD_TIME_UNIT)
+    }
+
+    fn to_encode_type(&self) -> EncodeType {
+        field_timestamp(Timestamp::from_system_time(*self, FIELD_TIME_UNIT))
+    }
+
+    fn from_encode_type(value: EncodeType) -> Option<Self> {
+        from_field_timestamp(value)?.to_system_time()
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl SortableField for chrono::DateTime<chrono::Utc> {
+    fn decode_type() -> DecodeType {
+        DecodeType::DecodeTimestamp(FIELD_TIME_UNIT)
+    }
+
+    fn to_encode_type(&self) -> EncodeType {
+        field_timestamp(Timestamp::from_chrono(self, FIELD_TIME_UNIT))
+    }
+
+    fn from_encode_type(value: EncodeType) -> Option<Self> {
+        from_field_timestamp(value)?.to_chrono()
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl SortableField for chrono::NaiveDate {
+    fn decode_type() -> DecodeType {
+        DecodeType::DecodeDate
+    }
+
+    fn to_encode_type(&self) -> EncodeType {
+        EncodeType::SortDate(Date::from_chrono(*self))
+    }
+
+    fn from_encode_type(value: EncodeType) -> Option<Self> {
+        match value {
+            EncodeType::SortDate(value) => value.to_chrono(),
+            _ => None,
+        }
+    }
+}
+
+// the offset is not stored, a value is read back in UTC
+#[cfg(feature = "time")]
+impl SortableField for time::OffsetDateTime {
+    fn decode_type() -> DecodeType {
+        DecodeType::DecodeTimestamp(FIELD_TIME_UNIT)
+    }
+
+    fn to_encode_type(&self) -> EncodeType {
+        field_timestamp(Timestamp::from_time(self, FIELD_TIME_UNIT))
+    }
+
+    fn from_encode_type(value: EncodeType) -> Option<Self> {
+        from_field_timestamp(value)?.to_time()
+    }
+}
+
+#[cfg(feature = "time")]
+impl SortableField for time::Date {
+    fn decode_type() -> DecodeType {
+        DecodeType::DecodeDate
+    }
+
+    fn to_encode_type(&self) -> EncodeType {
+        EncodeType::SortDate(Date::from_time(*self))
+    }
+
+    fn from_encode_type(value: EncodeType) -> Option<Self> {
+        match value {
+            EncodeType::SortDate(value) => value.to_time(),
+            _ => None,
+        }
+    }
+}
+
+// tuples are stored as `DecodeType::DecodeTuple`, so a reversed tuple reverses all of its fields
+macro_rules! sortable_tuple {
+    ($($name:ident $index:tt),+) => {
+        impl<$($name: SortableField),+> SortableField for ($($name,)+) {
+            fn decode_type() -> DecodeType {
+                DecodeType::DecodeTuple(vec![$($name::decode_type()),+])
+            }
+
+            fn to_encode_type(&self) -> EncodeType {
+                EncodeType::SortTuple(vec![$(self.$index.to_encode_type()),+])
+            }
+
+            fn from_encode_type(value: EncodeType) -> Option<Self> {
+                match value {
+                    EncodeType::SortTuple(values) => {
+                        let mut values = values.into_iter();
+                        let tuple = ($($name::from_encode_type(values.next()?)?,)+);
+                        values.next().is_none().then_some(tuple)
+                    },
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+sortable_tuple!(A 0, B 1);
+sortable_tuple!(A 0, B 1, C 2);
+sortable_tuple!(A 0, B 1, C 2, D 3);
+
+// `Vec<u8>` is a bytes field, so lists are implemented for each element type instead of for every `Vec<T>`
+macro_rules! sortable_list {
+    ($($element:ty),+) => {
+        $(
+            impl SortableField for Vec<$element> {
+                fn decode_type() -> DecodeType {
+                    DecodeType::DecodeList(Box::new(<$element>::decode_type()))
+                }
+
+                fn to_encode_type(&self) -> EncodeType {
+                    EncodeType::SortList(self.iter().map(SortableField::to_encode_type).collect())
+                }
+
+                fn from_encode_type(value: EncodeType) -> Option<Self> {
+                    match value {
+                        EncodeType::SortList(values) => values.into_iter().map(<$element>::from_encode_type).collect(),
+                        _ => None,
+                    }
+                }
+            }
+        )+
+    };
+}
+
+sortable_list!(u16, u32, u64, u128, i8, i16, i32, i64, i128, String, Vec<u8>, bool, Decimal, Date, Duration, Timestamp);
+
+// usize and isize are stored as 64 bit values, so keys do not depend on the platform
+impl SortableField for usize {
+    fn decode_type() -> DecodeType {
+        DecodeType::DecodeU64
+    }
+
+    fn to_encode_type(&self) -> EncodeType {
+        EncodeType::SortU64(*self as u64)
+    }
+
+    fn from_encode_type(value: EncodeType) -> Option<Self> {
+        match value {
+            EncodeType::SortU64(value) => usize::try_from(value).ok(),
+            _ => None,
+        }
+    }
+}
+
+impl SortableField for isize {
+    fn decode_type() -> DecodeType {
+        DecodeType::DecodeI64
+    }
+
+    fn to_encode_type(&self) -> EncodeType {
+        EncodeType::SortI64(*self as i64)
+    }
+
+    fn from_encode_type(value: EncodeType) -> Option<Self> {
+        match value {
+            EncodeType::SortI64(value) => isize::try_from(value).ok(),
+            _ => None,
+        }
+    }
+}
+
+/// A nullable field, `None` sorts first. Use `#[key(nulls_last)]` or `KeySchema::nulls_last` to sort it last.
+impl<T: SortableField> SortableField for Option<T> {
+    fn decode_type() -> DecodeType {
+        DecodeType::DecodeOption(Box::new(T::decode_type()), NullOrder::NullsFirst)
+    }
+
+    fn to_encode_type(&self) -> EncodeType {
+        EncodeType::SortOption(self.as_ref().map(|value| Box::new(value.to_encode_type())))
+    }
+
+    fn from_encode_type(value: EncodeType) -> Option<Self> {
+        match value {
+            EncodeType::SortOption(None) => Some(None),
+            EncodeType::SortOption(Some(value)) => T::from_encode_type(*value).map(Some),
+            _ => None,
+        }
+    }
+}
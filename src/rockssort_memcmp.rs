@@ -0,0 +1,662 @@
+// Order preserving ("memcomparable") encoding of compound keys.
+//
+// Keys encoded with `encode_memcomparable` sort correctly with a plain `memcmp`, so RocksDB can
+// use its default bytewise comparator instead of `compare_bytes`. The field layout is:
+//
+//   unsigned integers   big endian
+//   signed integers     big endian with the sign bit flipped
+//   floats              as in `encode_keys`, bytes that sort in the order of the float type
+//   strings and bytes   0x00 escaped as 0x00 0xff, terminated by 0x00 0x01
+//   bool                a single 0 or 1 byte
+//   timestamp, date     as signed integers, duration as in `encode_keys`
+//   fixed bytes, uuid   the raw bytes
+//   var ints            as in `encode_keys`, a length header and the big endian bytes
+//   decimal             as in `encode_keys`, a sign byte, the exponent and the digits with a terminator
+//   collated string     the sort key and then the string, both escaped like strings,
+//                       the sort key is written even when `encode_keys` leaves it out
+//   tuple               the fields one after the other, a reversed field inside the tuple is inverted on its own
+//   list                0x01 in front of every element, 0x00 after the last one, as in `encode_keys`
+//   union               the index of the variant as one byte, then the value
+//   nullable            0x01 followed by the value, a null is a single 0x00 or 0x02 byte,
+//                       chosen so the nulls end up first or last after a reverse
+//
+// A field preceded by `DecodeType::Reverse` has all of its bytes inverted, which makes it sort descending.
+use crate::rocks_sortable_keys::{duration_from_parts, try_validate_types, LIST_ELEMENT, LIST_END, encode_var_i64, encode_var_u64, var_i64_from_bytes, var_int_len, var_u64_from_bytes, DecodeType, EncodeType, NullOrder};
+use crate::rockssort_time::{Date, Timestamp};
+use crate::rockssort_decimal::Decimal;
+use crate::rockssort_collation::CollatedString;
+use crate::rockssort_float::{F32struct, F32total, F64struct, F64total};
+use crate::rockssort_error::KeyError;
+
+const ESCAPE: u8 = 0x00;
+const ESCAPED_ZERO: u8 = 0xff;
+const TERMINATOR: u8 = 0x01;
+const PRESENT: u8 = 0x01;
+
+// the null marker before the bytes of a reversed field are inverted
+#[inline]
+fn null_marker(null_order: NullOrder, is_reverse: bool) -> u8 {
+    if (null_order == NullOrder::NullsFirst) != is_reverse {
+        0x00
+    } else {
+        0x02
+    }
+}
+
+#[inline]
+fn encode_escaped(value: &[u8], out: &mut Vec<u8>) {
+    for byte in value {
+        out.push(*byte);
+        if *byte == ESCAPE {
+            out.push(ESCAPED_ZERO);
+        }
+    }
+    out.push(ESCAPE);
+    out.push(TERMINATOR);
+}
+
+#[inline]
+fn encode_field(key: &EncodeType, the_type: &DecodeType, is_reverse: bool, out: &mut Vec<u8>) {
+    match key {
+        EncodeType::SortU8(value) => out.extend(value.to_be_bytes()),
+        EncodeType::SortU16(value) => out.extend(value.to_be_bytes()),
+        EncodeType::SortU32(value) => out.extend(value.to_be_bytes()),
+        EncodeType::SortU64(value) => out.extend(value.to_be_bytes()),
+        EncodeType::SortU128(value) => out.extend(value.to_be_bytes()),
+        EncodeType::SortI8(value) => out.push((*value as u8) ^ 0x80),
+        EncodeType::SortI16(value) => out.extend(((*value as u16) ^ 0x8000).to_be_bytes()),
+        EncodeType::SortI32(value) => out.extend(((*value as u32) ^ 0x8000_0000).to_be_bytes()),
+        EncodeType::SortI64(value) => out.extend(((*value as u64) ^ 0x8000_0000_0000_0000).to_be_bytes()),
+        EncodeType::SortI128(value) => out.extend(((*value as u128) ^ (1 << 127)).to_be_bytes()),
+        EncodeType::SortString(value) => encode_escaped(value.as_bytes(), out),
+        EncodeType::SortBytes(value) => encode_escaped(value, out),
+        EncodeType::SortBool(value) => out.push(*value as u8),
+        EncodeType::SortF32(value) => out.extend(value.to_sortable_bits().to_be_bytes()),
+        EncodeType::SortF64(value) => out.extend(value.to_sortable_bits().to_be_bytes()),
+        EncodeType::SortF32Total(value) => out.extend(value.to_sortable_bits().to_be_bytes()),
+        EncodeType::SortF64Total(value) => out.extend(value.to_sortable_bits().to_be_bytes()),
+        EncodeType::SortTimestamp(value) => out.extend(((value.since_epoch as u64) ^ 0x8000_0000_0000_0000).to_be_bytes()),
+        EncodeType::SortDate(value) => out.extend(((value.days_since_epoch as u32) ^ 0x8000_0000).to_be_bytes()),
+        EncodeType::SortDuration(value) => {
+            out.extend(value.as_secs().to_be_bytes());
+            out.extend(value.subsec_nanos().to_be_bytes());
+        },
+        EncodeType::SortFixedBytes(value) => out.extend(value),
+        EncodeType::SortUuid(value) => out.extend(value),
+        EncodeType::SortVarU64(value) => encode_var_u64(*value, out),
+        EncodeType::SortVarI64(value) => encode_var_i64(*value, out),
+        EncodeType::SortDecimal(value) => value.encode(out),
+        EncodeType::SortCollatedString(value) => {
+            encode_escaped(value.sort_key().as_bytes(), out);
+            encode_escaped(value.value.as_bytes(), out);
+        },
+        EncodeType::SortTuple(values) => {
+            let inner = match the_type {
+                DecodeType::DecodeTuple(inner) => inner,
+                _ => panic!("wrong type"),
+            };
+            let mut values = values.iter();
+            let mut inner_reverse = false;
+            for the_type in inner {
+                if the_type == &DecodeType::Reverse {
+                    inner_reverse = true;
+                    continue;
+                }
+                let value = match values.next() {
+                    Some(value) => value,
+                    None => break,
+                };
+                let start = out.len();
+                encode_field(value, the_type, is_reverse != inner_reverse, out);
+                if inner_reverse {
+                    for byte in &mut out[start..] {
+                        *byte = !*byte;
+                    }
+                }
+                inner_reverse = false;
+            }
+        },
+        EncodeType::SortList(values) => {
+            let element = match the_type {
+                DecodeType::DecodeList(element) => element,
+                _ => panic!("wrong type"),
+            };
+            for value in values {
+                out.push(LIST_ELEMENT);
+                encode_field(value, element, is_reverse, out);
+            }
+            out.push(LIST_END);
+        },
+        EncodeType::SortUnion(variant, value) => {
+            let variants = match the_type {
+                DecodeType::DecodeUnion(variants) => variants,
+                _ => panic!("wrong type"),
+            };
+            out.push(*variant);
+            encode_field(value, &variants[*variant as usize], is_reverse, out);
+        },
+        EncodeType::SortOption(value) => {
+            let (inner, null_order) = match the_type {
+                DecodeType::DecodeOption(inner, null_order) => (inner, *null_order),
+                _ => panic!("wrong type"),
+            };
+            match value {
+                Some(value) => {
+                    out.push(PRESENT);
+                    encode_field(value, inner, is_reverse, out);
+                },
+                None => out.push(null_marker(null_order, is_reverse)),
+            }
+        },
+    }
+}
+
+/// Encodes `keys` so that comparing the results with `memcmp` gives the same ordering as `compare` with `the_types`.
+/// Fails when `keys` do not match `the_types`, as checked by `try_validate_types`.
+#[inline]
+pub fn encode_memcomparable(the_types: &[DecodeType], keys: &[EncodeType]) -> Result<Vec<u8>, KeyError> {
+    try_validate_types(the_types, keys)?;
+    let mut encoded_data = Vec::new();
+    let mut keys = keys.iter();
+    let mut is_reverse = false;
+    for the_type in the_types {
+        if the_type == &DecodeType::Reverse {
+            is_reverse = true;
+            continue;
+        }
+        let key = match keys.next() {
+            Some(key) => key,
+            None => break,
+        };
+        let start = encoded_data.len();
+        encode_field(key, the_type, is_reverse, &mut encoded_data);
+        if is_reverse {
+            for byte in &mut encoded_data[start..] {
+                *byte = !*byte;
+            }
+        }
+        is_reverse = false;
+    }
+    Ok(encoded_data)
+}
+
+#[inline]
+fn read_byte(data: &[u8], pos: &mut usize, mask: u8) -> Result<u8, KeyError> {
+    match data.get(*pos) {
+        Some(byte) => {
+            *pos += 1;
+            Ok(byte ^ mask)
+        },
+        None => Err(KeyError::Truncated { field: 0, offset: *pos, needed: 1, available: 0 }),
+    }
+}
+
+#[inline]
+fn read_fixed<const N: usize>(data: &[u8], pos: &mut usize, mask: u8) -> Result<[u8; N], KeyError> {
+    let available = data.len().saturating_sub(*pos);
+    if available < N {
+        return Err(KeyError::Truncated { field: 0, offset: *pos, needed: N, available });
+    }
+    let mut value = [0u8; N];
+    for (i, byte) in value.iter_mut().enumerate() {
+        *byte = data[*pos + i] ^ mask;
+    }
+    *pos += N;
+    Ok(value)
+}
+
+#[inline]
+fn read_escaped(data: &[u8], pos: &mut usize, mask: u8) -> Result<Vec<u8>, KeyError> {
+    let mut value = Vec::new();
+    loop {
+        let byte = read_byte(data, pos, mask)?;
+        if byte != ESCAPE {
+            value.push(byte);
+            continue;
+        }
+        match read_byte(data, pos, mask)? {
+            ESCAPED_ZERO => value.push(ESCAPE),
+            TERMINATOR => return Ok(value),
+            _ => return Err(KeyError::InvalidEncoding { field: 0, offset: *pos - 1 }),
+        }
+    }
+}
+
+// the header and the bytes of a variable length integer, with the mask removed
+#[inline]
+fn read_var_int(data: &[u8], pos: &mut usize, mask: u8, signed: bool) -> Result<Vec<u8>, KeyError> {
+    let start = *pos;
+    let header = read_byte(data, pos, mask)?;
+    let len = var_int_len(header, signed).ok_or(KeyError::InvalidEncoding { field: 0, offset: start })?;
+    let mut value = vec![header];
+    for _ in 0..len {
+        value.push(read_byte(data, pos, mask)?);
+    }
+    Ok(value)
+}
+
+#[inline]
+fn read_decimal(data: &[u8], pos: &mut usize, mask: u8) -> Result<Decimal, KeyError> {
+    let start = *pos;
+    let rest = data.get(start..).unwrap_or_default();
+    let len = Decimal::encoded_len(rest, mask).ok_or(KeyError::InvalidEncoding { field: 0, offset: start })?;
+    if rest.len() < len {
+        return Err(KeyError::Truncated { field: 0, offset: start, needed: len, available: rest.len() });
+    }
+    let encoded: Vec<u8> = rest[..len].iter().map(|byte| byte ^ mask).collect();
+    *pos += len;
+    Decimal::decode(&encoded).ok_or(KeyError::InvalidEncoding { field: 0, offset: start })
+}
+
+#[inline]
+fn decode_field(data: &[u8], the_type: &DecodeType, pos: &mut usize, mask: u8) -> Result<EncodeType, KeyError> {
+    let value = match the_type {
+        DecodeType::DecodeU8 => EncodeType::SortU8(u8::from_be_bytes(read_fixed(data, pos, mask)?)),
+        DecodeType::DecodeU16 => EncodeType::SortU16(u16::from_be_bytes(read_fixed(data, pos, mask)?)),
+        DecodeType::DecodeU32 => EncodeType::SortU32(u32::from_be_bytes(read_fixed(data, pos, mask)?)),
+        DecodeType::DecodeU64 => EncodeType::SortU64(u64::from_be_bytes(read_fixed(data, pos, mask)?)),
+        DecodeType::DecodeU128 => EncodeType::SortU128(u128::from_be_bytes(read_fixed(data, pos, mask)?)),
+        DecodeType::DecodeI8 => EncodeType::SortI8((read_byte(data, pos, mask)? ^ 0x80) as i8),
+        DecodeType::DecodeI16 => EncodeType::SortI16((u16::from_be_bytes(read_fixed(data, pos, mask)?) ^ 0x8000) as i16),
+        DecodeType::DecodeI32 => EncodeType::SortI32((u32::from_be_bytes(read_fixed(data, pos, mask)?) ^ 0x8000_0000) as i32),
+        DecodeType::DecodeI64 => EncodeType::SortI64((u64::from_be_bytes(read_fixed(data, pos, mask)?) ^ 0x8000_0000_0000_0000) as i64),
+        DecodeType::DecodeI128 => EncodeType::SortI128((u128::from_be_bytes(read_fixed(data, pos, mask)?) ^ (1 << 127)) as i128),
+        DecodeType::DecodeString => {
+            let start = *pos;
+            EncodeType::SortString(String::from_utf8(read_escaped(data, pos, mask)?).map_err(|_| KeyError::InvalidUtf8 { field: 0, offset: start })?)
+        },
+        DecodeType::DecodeBytes => EncodeType::SortBytes(read_escaped(data, pos, mask)?),
+        DecodeType::DecodeBool => EncodeType::SortBool(read_byte(data, pos, mask)? == 1),
+        DecodeType::DecodeF32 => EncodeType::SortF32(F32struct::from_sortable_bits(u32::from_be_bytes(read_fixed(data, pos, mask)?))),
+        DecodeType::DecodeF64 => EncodeType::SortF64(F64struct::from_sortable_bits(u64::from_be_bytes(read_fixed(data, pos, mask)?))),
+        DecodeType::DecodeF32Total => EncodeType::SortF32Total(F32total::from_sortable_bits(u32::from_be_bytes(read_fixed(data, pos, mask)?))),
+        DecodeType::DecodeF64Total => EncodeType::SortF64Total(F64total::from_sortable_bits(u64::from_be_bytes(read_fixed(data, pos, mask)?))),
+        DecodeType::DecodeTimestamp(unit) => EncodeType::SortTimestamp(Timestamp::new((u64::from_be_bytes(read_fixed(data, pos, mask)?) ^ 0x8000_0000_0000_0000) as i64, *unit)),
+        DecodeType::DecodeDate => EncodeType::SortDate(Date::new((u32::from_be_bytes(read_fixed(data, pos, mask)?) ^ 0x8000_0000) as i32)),
+        DecodeType::DecodeDuration => {
+            let seconds = read_fixed(data, pos, mask)?;
+            let offset = *pos;
+            EncodeType::SortDuration(duration_from_parts(seconds, read_fixed(data, pos, mask)?, offset)?)
+        },
+        DecodeType::DecodeFixedBytes(len) => {
+            let available = data.len().saturating_sub(*pos);
+            if available < *len {
+                return Err(KeyError::Truncated { field: 0, offset: *pos, needed: *len, available });
+            }
+            let value = data[*pos..*pos + len].iter().map(|byte| byte ^ mask).collect();
+            *pos += len;
+            EncodeType::SortFixedBytes(value)
+        },
+        DecodeType::DecodeUuid => EncodeType::SortUuid(read_fixed(data, pos, mask)?),
+        DecodeType::DecodeVarU64 => {
+            let start = *pos;
+            EncodeType::SortVarU64(var_u64_from_bytes(&read_var_int(data, pos, mask, false)?).ok_or(KeyError::InvalidEncoding { field: 0, offset: start })?)
+        },
+        DecodeType::DecodeVarI64 => {
+            let start = *pos;
+            EncodeType::SortVarI64(var_i64_from_bytes(&read_var_int(data, pos, mask, true)?).ok_or(KeyError::InvalidEncoding { field: 0, offset: start })?)
+        },
+        DecodeType::DecodeDecimal => EncodeType::SortDecimal(read_decimal(data, pos, mask)?),
+        DecodeType::DecodeCollatedString(collation, stored_sort_key) => {
+            let start = *pos;
+            let sort_key = read_escaped(data, pos, mask)?;
+            let value_start = *pos;
+            let value = String::from_utf8(read_escaped(data, pos, mask)?).map_err(|_| KeyError::InvalidUtf8 { field: 0, offset: value_start })?;
+            let value = CollatedString::new(value, *collation, *stored_sort_key);
+            if sort_key != value.sort_key().as_bytes() {
+                return Err(KeyError::InvalidEncoding { field: 0, offset: start });
+            }
+            EncodeType::SortCollatedString(value)
+        },
+        DecodeType::DecodeTuple(inner) => {
+            let mut values = Vec::new();
+            let mut inner_reverse = false;
+            for the_type in inner {
+                if the_type == &DecodeType::Reverse {
+                    inner_reverse = true;
+                    continue;
+                }
+                let inner_mask = if inner_reverse { !mask } else { mask };
+                values.push(decode_field(data, the_type, pos, inner_mask)?);
+                inner_reverse = false;
+            }
+            EncodeType::SortTuple(values)
+        },
+        DecodeType::DecodeList(element) => {
+            let mut values = Vec::new();
+            loop {
+                let start = *pos;
+                match read_byte(data, pos, mask)? {
+                    LIST_ELEMENT => values.push(decode_field(data, element, pos, mask)?),
+                    LIST_END => break,
+                    _ => return Err(KeyError::InvalidEncoding { field: 0, offset: start }),
+                }
+            }
+            EncodeType::SortList(values)
+        },
+        DecodeType::DecodeUnion(variants) => {
+            let start = *pos;
+            let variant = read_byte(data, pos, mask)?;
+            let the_type = variants.get(variant as usize).ok_or(KeyError::InvalidEncoding { field: 0, offset: start })?;
+            EncodeType::SortUnion(variant, Box::new(decode_field(data, the_type, pos, mask)?))
+        },
+        DecodeType::DecodeOption(inner, null_order) => {
+            let start = *pos;
+            let marker = read_byte(data, pos, mask)?;
+            if marker == PRESENT {
+                EncodeType::SortOption(Some(Box::new(decode_field(data, inner, pos, mask)?)))
+            } else if marker == null_marker(*null_order, mask != 0) {
+                EncodeType::SortOption(None)
+            } else {
+                return Err(KeyError::InvalidEncoding { field: 0, offset: start });
+            }
+        },
+        // handled by the caller
+        DecodeType::Reverse => EncodeType::SortU32(0),
+    };
+    Ok(value)
+}
+
+/// Decodes a key written by `encode_memcomparable` with the same `the_types`.
+#[inline]
+pub fn decode_memcomparable(data: &[u8], the_types: &[DecodeType]) -> Vec<EncodeType> {
+    match try_decode_memcomparable(data, the_types) {
+        Ok(decoded_data) => decoded_data,
+        Err(error) => panic!("{}", error),
+    }
+}
+
+#[inline]
+pub fn try_decode_memcomparable(data: &[u8], the_types: &[DecodeType]) -> Result<Vec<EncodeType>, KeyError> {
+    let mut pos = 0;
+    let mut decoded_data = Vec::new();
+    let mut is_reverse = false;
+    for the_type in the_types {
+        if the_type == &DecodeType::Reverse {
+            is_reverse = true;
+            continue;
+        }
+        let mask = if is_reverse { 0xff } else { 0x00 };
+        let field = decoded_data.len();
+        decoded_data.push(decode_field(data, the_type, &mut pos, mask).map_err(|e| e.at_field(field))?);
+        is_reverse = false;
+    }
+    if pos != data.len() {
+        return Err(KeyError::TrailingBytes { offset: pos, remaining: data.len() - pos });
+    }
+    Ok(decoded_data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rocks_sortable_keys::compare;
+    use crate::rockssort_time::TimeUnit;
+    use crate::rockssort_collation::Collation;
+    use std::time::Duration;
+    use std::cmp::Ordering;
+
+    #[test]
+    fn test_memcomparable_round_trip_for_all_types() {
+        let keys = vec![EncodeType::SortU8(7), EncodeType::SortU16(1), EncodeType::SortU32(2), EncodeType::SortU64(3), EncodeType::SortU128(4), EncodeType::SortI32(-5), EncodeType::SortI64(-6), EncodeType::SortString("he\0llo".to_string()), EncodeType::SortBytes(vec![0, 1, 0, 255]), EncodeType::SortBool(true), EncodeType::SortF32(F32struct::new(-1.5)), EncodeType::SortF64(F64struct::new(2.0))];
+        let the_types = vec![DecodeType::DecodeU8, DecodeType::DecodeU16, DecodeType::DecodeU32, DecodeType::Reverse, DecodeType::DecodeU64, DecodeType::DecodeU128, DecodeType::DecodeI32, DecodeType::Reverse, DecodeType::DecodeI64, DecodeType::Reverse, DecodeType::DecodeString, DecodeType::Reverse, DecodeType::DecodeBytes, DecodeType::DecodeBool, DecodeType::Reverse, DecodeType::DecodeF32, DecodeType::DecodeF64];
+        let encoded_data = encode_memcomparable(&the_types, &keys).unwrap();
+        let decoded_data = decode_memcomparable(&encoded_data, &the_types);
+        assert_eq!(keys, decoded_data);
+    }
+
+    #[test]
+    fn test_memcomparable_order_matches_compare() {
+        let the_types = vec![DecodeType::DecodeString, DecodeType::Reverse, DecodeType::DecodeU32, DecodeType::DecodeF64, DecodeType::Reverse, DecodeType::DecodeString];
+        let strings = ["", "a", "a\0", "a\0b", "ab", "b"];
+        let ints = [0, 1, 256, u32::MAX];
+        let floats = [f64::NAN, f64::NEG_INFINITY, -1.5, -0.0, 0.0, 1e-300, 2.0, f64::INFINITY];
+        let mut keys = Vec::new();
+        for s in strings {
+            for i in ints {
+                for f in floats {
+                    for t in strings {
+                        keys.push(vec![EncodeType::SortString(s.to_string()), EncodeType::SortU32(i), EncodeType::SortF64(F64struct::new(f)), EncodeType::SortString(t.to_string())]);
+                    }
+                }
+            }
+        }
+        for key1 in &keys {
+            for key2 in &keys {
+                let encoded_key1 = encode_memcomparable(&the_types, key1).unwrap();
+                let encoded_key2 = encode_memcomparable(&the_types, key2).unwrap();
+                assert_eq!(encoded_key1.cmp(&encoded_key2), compare(&the_types, key1, key2), "{:?} {:?}", key1, key2);
+            }
+        }
+    }
+
+    #[test]
+    fn test_memcomparable_nullable_order_matches_compare() {
+        let values = [None, Some(0), Some(7)];
+        for null_order in [NullOrder::NullsFirst, NullOrder::NullsLast] {
+            let the_types = vec![DecodeType::DecodeOption(Box::new(DecodeType::DecodeU32), null_order), DecodeType::Reverse, DecodeType::DecodeOption(Box::new(DecodeType::DecodeString), null_order)];
+            let mut keys = Vec::new();
+            for value1 in values {
+                for value2 in values {
+                    keys.push(vec![EncodeType::SortOption(value1.map(|value| Box::new(EncodeType::SortU32(value)))), EncodeType::SortOption(value2.map(|value| Box::new(EncodeType::SortString(value.to_string()))))]);
+                }
+            }
+            for key1 in &keys {
+                let encoded_key1 = encode_memcomparable(&the_types, key1).unwrap();
+                assert_eq!(&decode_memcomparable(&encoded_key1, &the_types), key1);
+                for key2 in &keys {
+                    let encoded_key2 = encode_memcomparable(&the_types, key2).unwrap();
+                    assert_eq!(encoded_key1.cmp(&encoded_key2), compare(&the_types, key1, key2), "{:?} {:?}", key1, key2);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_memcomparable_signed_integers() {
+        let the_types = vec![DecodeType::DecodeI64];
+        let ints = [i64::MIN, -256, -1, 0, 1, 255, i64::MAX];
+        let encoded: Vec<Vec<u8>> = ints.iter().map(|i| encode_memcomparable(&the_types, &[EncodeType::SortI64(*i)]).unwrap()).collect();
+        let mut sorted = encoded.clone();
+        sorted.sort();
+        assert_eq!(encoded, sorted);
+        let the_types = vec![DecodeType::DecodeI8, DecodeType::DecodeI16, DecodeType::Reverse, DecodeType::DecodeI128];
+        let keys: Vec<Vec<EncodeType>> = [(i8::MIN, i16::MIN, i128::MAX), (-1, -1, 1), (0, 0, 0), (0, 1, -1), (i8::MAX, i16::MAX, i128::MIN)].iter()
+            .map(|(a, b, c)| vec![EncodeType::SortI8(*a), EncodeType::SortI16(*b), EncodeType::SortI128(*c)]).collect();
+        let encoded: Vec<Vec<u8>> = keys.iter().map(|key| encode_memcomparable(&the_types, key).unwrap()).collect();
+        let mut sorted = encoded.clone();
+        sorted.sort();
+        assert_eq!(encoded, sorted);
+        for (key, encoded_key) in keys.iter().zip(&encoded) {
+            assert_eq!(&decode_memcomparable(encoded_key, &the_types), key);
+        }
+    }
+
+    // every 65521st bit pattern, the bytes must sort in the order of F32struct / F32total
+    #[test]
+    fn test_memcomparable_float_bit_range() {
+        let the_types = vec![DecodeType::DecodeF32, DecodeType::DecodeF32Total];
+        let mut keys: Vec<Vec<EncodeType>> = (0..=u32::MAX).step_by(65_521).map(|bits| {
+            let value = f32::from_bits(bits);
+            vec![EncodeType::SortF32(F32struct::new(value)), EncodeType::SortF32Total(F32total::new(value))]
+        }).collect();
+        keys.sort_by(|one, two| compare(&the_types, one, two));
+        for pair in keys.windows(2) {
+            let encoded_key1 = encode_memcomparable(&the_types, &pair[0]).unwrap();
+            let encoded_key2 = encode_memcomparable(&the_types, &pair[1]).unwrap();
+            assert_eq!(encoded_key1.cmp(&encoded_key2), compare(&the_types, &pair[0], &pair[1]), "{:?} {:?}", pair[0], pair[1]);
+            assert_eq!(try_decode_memcomparable(&encoded_key1, &the_types).unwrap()[1], pair[0][1]);
+        }
+    }
+
+    #[test]
+    fn test_memcomparable_time_fields() {
+        let the_types = vec![DecodeType::DecodeDate, DecodeType::Reverse, DecodeType::DecodeTimestamp(TimeUnit::Nanos), DecodeType::DecodeDuration];
+        let keys: Vec<Vec<EncodeType>> = [(i32::MIN, i64::MAX, 5), (-1, 0, 0), (0, 7, 2), (0, -7, 1), (20_000, -1, 0)].iter()
+            .map(|(days, nanos, seconds)| vec![EncodeType::SortDate(Date::new(*days)), EncodeType::SortTimestamp(Timestamp::new(*nanos, TimeUnit::Nanos)), EncodeType::SortDuration(Duration::new(*seconds, 1))]).collect();
+        let encoded: Vec<Vec<u8>> = keys.iter().map(|key| encode_memcomparable(&the_types, key).unwrap()).collect();
+        let mut sorted = encoded.clone();
+        sorted.sort();
+        assert_eq!(encoded, sorted);
+        for (key, encoded_key) in keys.iter().zip(&encoded) {
+            assert_eq!(&decode_memcomparable(encoded_key, &the_types), key);
+        }
+    }
+
+    #[test]
+    fn test_memcomparable_fixed_bytes_and_uuid() {
+        let the_types = vec![DecodeType::DecodeFixedBytes(2), DecodeType::Reverse, DecodeType::DecodeUuid];
+        let keys: Vec<Vec<EncodeType>> = [([0, 0], 255), ([0, 0], 1), ([0, 1], 0), ([255, 0], 7)].iter()
+            .map(|(bytes, uuid)| vec![EncodeType::SortFixedBytes(bytes.to_vec()), EncodeType::SortUuid([*uuid; 16])]).collect();
+        let encoded: Vec<Vec<u8>> = keys.iter().map(|key| encode_memcomparable(&the_types, key).unwrap()).collect();
+        let mut sorted = encoded.clone();
+        sorted.sort();
+        assert_eq!(encoded, sorted);
+        for (key, encoded_key) in keys.iter().zip(&encoded) {
+            assert_eq!(encoded_key.len(), 18);
+            assert_eq!(&decode_memcomparable(encoded_key, &the_types), key);
+        }
+    }
+
+    #[test]
+    fn test_memcomparable_var_ints() {
+        let the_types = vec![DecodeType::DecodeVarI64, DecodeType::Reverse, DecodeType::DecodeVarU64];
+        let keys: Vec<Vec<EncodeType>> = [(i64::MIN, 0), (-300, u64::MAX), (-300, 1), (0, 7), (1, 256), (1, 255), (i64::MAX, 0)].iter()
+            .map(|(a, b)| vec![EncodeType::SortVarI64(*a), EncodeType::SortVarU64(*b)]).collect();
+        let encoded: Vec<Vec<u8>> = keys.iter().map(|key| encode_memcomparable(&the_types, key).unwrap()).collect();
+        let mut sorted = encoded.clone();
+        sorted.sort();
+        assert_eq!(encoded, sorted);
+        for (key, encoded_key) in keys.iter().zip(&encoded) {
+            assert_eq!(&decode_memcomparable(encoded_key, &the_types), key);
+        }
+    }
+
+    #[test]
+    fn test_memcomparable_decimals() {
+        let the_types = vec![DecodeType::DecodeDecimal, DecodeType::Reverse, DecodeType::DecodeDecimal];
+        let keys: Vec<Vec<EncodeType>> = [("-99.9", "0"), ("-1", "7"), ("-1", "-7"), ("0", "0.5"), ("0.5", "1e9"), ("0.5", "-1e9"), ("123.45", "0")].iter()
+            .map(|(a, b)| vec![EncodeType::SortDecimal(a.parse().unwrap()), EncodeType::SortDecimal(b.parse().unwrap())]).collect();
+        let encoded: Vec<Vec<u8>> = keys.iter().map(|key| encode_memcomparable(&the_types, key).unwrap()).collect();
+        let mut sorted = encoded.clone();
+        sorted.sort();
+        assert_eq!(encoded, sorted);
+        for (key, encoded_key) in keys.iter().zip(&encoded) {
+            assert_eq!(&decode_memcomparable(encoded_key, &the_types), key);
+        }
+        let encoded_key = &encoded[1];
+        assert!(matches!(try_decode_memcomparable(&encoded_key[..encoded_key.len() - 1], &the_types), Err(KeyError::Truncated { field: 1, .. })));
+    }
+
+    #[test]
+    fn test_memcomparable_collated_strings() {
+        let the_types = vec![DecodeType::DecodeCollatedString(Collation::NfkcCaseFold, false), DecodeType::Reverse, DecodeType::DecodeCollatedString(Collation::AsciiCaseFold, true)];
+        let keys: Vec<Vec<EncodeType>> = [("Apple", "b"), ("Apple", "B"), ("Apple", "a"), ("apple", "z"), ("FI", "x"), ("\u{fb01}", "x"), ("Zebra", "")].iter()
+            .map(|(a, b)| vec![EncodeType::SortCollatedString(CollatedString::new(*a, Collation::NfkcCaseFold, false)), EncodeType::SortCollatedString(CollatedString::new(*b, Collation::AsciiCaseFold, true))]).collect();
+        let encoded: Vec<Vec<u8>> = keys.iter().map(|key| encode_memcomparable(&the_types, key).unwrap()).collect();
+        let mut sorted = encoded.clone();
+        sorted.sort();
+        assert_eq!(encoded, sorted);
+        for (key, encoded_key) in keys.iter().zip(&encoded) {
+            assert_eq!(&decode_memcomparable(encoded_key, &the_types), key);
+        }
+        for pair in keys.windows(2) {
+            assert_eq!(compare(&the_types, &pair[0], &pair[1]), Ordering::Less);
+        }
+    }
+
+    #[test]
+    fn test_memcomparable_tuples() {
+        let inner = vec![DecodeType::DecodeString, DecodeType::Reverse, DecodeType::DecodeOption(Box::new(DecodeType::DecodeU8), NullOrder::NullsLast)];
+        let the_types = vec![DecodeType::Reverse, DecodeType::DecodeTuple(inner.clone()), DecodeType::DecodeTuple(inner)];
+        let tuple = |name: &str, value: Option<u8>| EncodeType::SortTuple(vec![EncodeType::SortString(name.to_string()), EncodeType::SortOption(value.map(|value| Box::new(EncodeType::SortU8(value))))]);
+        let keys: Vec<Vec<EncodeType>> = [(("b", Some(9)), ("a", None)), (("b", None), ("a", Some(2))), (("b", None), ("a", Some(1))), (("a", Some(1)), ("a", None)), (("a", Some(2)), ("b", None)), (("a", None), ("a", Some(1)))].iter()
+            .map(|((a, b), (c, d))| vec![tuple(a, *b), tuple(c, *d)]).collect();
+        let encoded: Vec<Vec<u8>> = keys.iter().map(|key| encode_memcomparable(&the_types, key).unwrap()).collect();
+        for (pair, encoded_pair) in keys.windows(2).zip(encoded.windows(2)) {
+            assert_eq!(compare(&the_types, &pair[0], &pair[1]), Ordering::Less, "{:?}", pair);
+            assert_eq!(encoded_pair[0].cmp(&encoded_pair[1]), Ordering::Less, "{:?}", pair);
+        }
+        for (key, encoded_key) in keys.iter().zip(&encoded) {
+            assert_eq!(&decode_memcomparable(encoded_key, &the_types), key);
+        }
+    }
+
+    #[test]
+    fn test_memcomparable_unions() {
+        let variants = vec![DecodeType::DecodeI32, DecodeType::DecodeOption(Box::new(DecodeType::DecodeString), NullOrder::NullsFirst)];
+        let the_types = vec![DecodeType::Reverse, DecodeType::DecodeUnion(variants.clone()), DecodeType::DecodeUnion(variants)];
+        let handle = |name: Option<&str>| EncodeType::SortUnion(1, Box::new(EncodeType::SortOption(name.map(|name| Box::new(EncodeType::SortString(name.to_string()))))));
+        let number = |value: i32| EncodeType::SortUnion(0, Box::new(EncodeType::SortI32(value)));
+        let keys: Vec<Vec<EncodeType>> = vec![
+            vec![handle(None), number(5)],
+            vec![handle(Some("b")), number(-5)],
+            vec![handle(Some("b")), handle(None)],
+            vec![handle(Some("a")), number(0)],
+            vec![number(7), handle(Some("x"))],
+            vec![number(-7), number(1)],
+        ];
+        let encoded: Vec<Vec<u8>> = keys.iter().map(|key| encode_memcomparable(&the_types, key).unwrap()).collect();
+        for (pair, encoded_pair) in keys.windows(2).zip(encoded.windows(2)) {
+            assert_eq!(compare(&the_types, &pair[0], &pair[1]), Ordering::Less, "{:?}", pair);
+            assert_eq!(encoded_pair[0].cmp(&encoded_pair[1]), Ordering::Less, "{:?}", pair);
+        }
+        for (key, encoded_key) in keys.iter().zip(&encoded) {
+            assert_eq!(&decode_memcomparable(encoded_key, &the_types), key);
+        }
+        assert_eq!(try_decode_memcomparable(&[0xfd, 0], &the_types), Err(KeyError::InvalidEncoding { field: 0, offset: 0 }));
+    }
+
+    #[test]
+    fn test_memcomparable_lists() {
+        let the_types = vec![DecodeType::DecodeList(Box::new(DecodeType::DecodeBytes)), DecodeType::Reverse, DecodeType::DecodeList(Box::new(DecodeType::DecodeI16))];
+        let bytes = |values: &[&[u8]]| EncodeType::SortList(values.iter().map(|value| EncodeType::SortBytes(value.to_vec())).collect());
+        let numbers = |values: &[i16]| EncodeType::SortList(values.iter().map(|value| EncodeType::SortI16(*value)).collect());
+        let keys: Vec<Vec<EncodeType>> = vec![
+            vec![bytes(&[]), numbers(&[1])],
+            vec![bytes(&[b""]), numbers(&[])],
+            vec![bytes(&[b"", b""]), numbers(&[5, -1])],
+            vec![bytes(&[b"", b""]), numbers(&[5])],
+            vec![bytes(&[b"", b""]), numbers(&[-3, 7])],
+            vec![bytes(&[b"\x00"]), numbers(&[])],
+            vec![bytes(&[b"a", b"b"]), numbers(&[])],
+        ];
+        let encoded: Vec<Vec<u8>> = keys.iter().map(|key| encode_memcomparable(&the_types, key).unwrap()).collect();
+        for (pair, encoded_pair) in keys.windows(2).zip(encoded.windows(2)) {
+            assert_eq!(compare(&the_types, &pair[0], &pair[1]), Ordering::Less, "{:?}", pair);
+            assert_eq!(encoded_pair[0].cmp(&encoded_pair[1]), Ordering::Less, "{:?}", pair);
+        }
+        for (key, encoded_key) in keys.iter().zip(&encoded) {
+            assert_eq!(&decode_memcomparable(encoded_key, &the_types), key);
+        }
+    }
+
+    #[test]
+    fn test_memcomparable_negative_zero_and_nan() {
+        let the_types = vec![DecodeType::DecodeF32];
+        let zero = encode_memcomparable(&the_types, &[EncodeType::SortF32(F32struct::new(0.0))]).unwrap();
+        let negative_zero = encode_memcomparable(&the_types, &[EncodeType::SortF32(F32struct::new(-0.0))]).unwrap();
+        let nan = encode_memcomparable(&the_types, &[EncodeType::SortF32(F32struct::new(f32::NAN))]).unwrap();
+        let negative_infinity = encode_memcomparable(&the_types, &[EncodeType::SortF32(F32struct::new(f32::NEG_INFINITY))]).unwrap();
+        assert_eq!(zero, negative_zero);
+        assert_eq!(nan.cmp(&negative_infinity), Ordering::Less);
+    }
+
+    #[test]
+    fn test_try_decode_memcomparable_errors() {
+        let the_types = vec![DecodeType::DecodeU8, DecodeType::Reverse, DecodeType::DecodeString];
+        let encoded_data = encode_memcomparable(&the_types, &[EncodeType::SortU8(1), EncodeType::SortString("ab".to_string())]).unwrap();
+        assert_eq!(try_decode_memcomparable(&encoded_data[..3], &the_types), Err(KeyError::Truncated { field: 1, offset: 3, needed: 1, available: 0 }));
+        let mut invalid_escape = encoded_data.clone();
+        invalid_escape[4] = !0x07;
+        assert_eq!(try_decode_memcomparable(&invalid_escape, &the_types), Err(KeyError::InvalidEncoding { field: 1, offset: 4 }));
+    }
+    #[test]
+    fn test_encode_memcomparable_checks_types() {
+        let the_types = vec![DecodeType::DecodeU8, DecodeType::Reverse, DecodeType::DecodeString];
+        assert_eq!(encode_memcomparable(&the_types, &[EncodeType::SortU8(1), EncodeType::SortU32(2)]),
+            Err(KeyError::SchemaMismatch { field: 1, expected: Some(DecodeType::DecodeString), found: Some(EncodeType::SortU32(2)) }));
+        assert_eq!(encode_memcomparable(&the_types, &[EncodeType::SortU8(1)]), Err(KeyError::SchemaMismatch { field: 1, expected: Some(DecodeType::DecodeString), found: None }));
+        assert_eq!(encode_memcomparable(&[DecodeType::DecodeU8, DecodeType::Reverse], &[EncodeType::SortU8(1)]), Err(KeyError::MisplacedReverse { index: 1 }));
+    }
+}
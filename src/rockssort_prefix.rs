@@ -0,0 +1,219 @@
+// RocksDB prefix extractors for keys written by `encode_keys`.
+//
+// The prefix of a key is its first `field_count` fields. Their length depends on the values of the
+// variable length fields, so the extractor walks the fields of every key with the types of the schema.
+// `SliceTransform::create` of the rocksdb crate only takes plain functions, which can not hold the
+// types, so the transform is created through the C api with the types as its state. RocksDB owns the
+// transform once it is set in the options and calls the destructor when it is done with it.
+use std::ffi::CString;
+use std::slice;
+use libc::{c_char, c_uchar, c_void, size_t};
+use rocksdb::SliceTransform;
+use crate::rocks_sortable_keys::{skip_field, DecodeType};
+
+/// The byte length of the first `field_count` fields of a key written by `encode_keys`,
+/// `None` when the key has fewer fields or can not be read.
+pub fn prefix_len(the_types: &[DecodeType], key: &[u8], field_count: usize) -> Option<usize> {
+    let mut pos = 0;
+    let mut fields = 0;
+    for the_type in the_types {
+        if fields == field_count {
+            break;
+        }
+        if the_type == &DecodeType::Reverse {
+            continue;
+        }
+        // like the comparator, a key that ends here has no more fields, unless the field is written without bytes
+        if pos == key.len() && needs_bytes(the_type) {
+            return None;
+        }
+        skip_field(the_type, key, &mut pos).ok()?;
+        fields += 1;
+    }
+    (fields == field_count).then_some(pos)
+}
+
+// only fixed bytes of length 0 and tuples of such fields are written without bytes, so a prefix that ends
+// with them is still in the domain of the extractor
+fn needs_bytes(the_type: &DecodeType) -> bool {
+    match the_type {
+        DecodeType::DecodeFixedBytes(len) => *len > 0,
+        DecodeType::DecodeTuple(inner) => inner.iter().any(needs_bytes),
+        DecodeType::Reverse => false,
+        _ => true,
+    }
+}
+
+/// A RocksDB prefix extractor that cuts keys written by `encode_keys` after their first `field_count` fields,
+/// for prefix bloom filters and `ReadOptions::set_prefix_same_as_start`. Keys with fewer fields are outside of its domain.
+///
+/// The keys starting with the same fields sort next to each other with the comparator of `the_types`, which is what
+/// RocksDB needs from a prefix extractor. `name` is stored with the data and should change whenever the types or the
+/// count change. `KeySchema::set_comparator_with_prefix` installs the comparator and the extractor together.
+pub fn prefix_extractor(name: &str, the_types: &[DecodeType], field_count: usize) -> SliceTransform {
+    let state = Box::new(PrefixState {
+        name: CString::new(name).expect("the name of a prefix extractor can not contain a zero byte"),
+        the_types: the_types.to_vec(),
+        field_count,
+    });
+    let inner = unsafe {
+        librocksdb_sys::rocksdb_slicetransform_create(
+            Box::into_raw(state) as *mut c_void,
+            Some(destroy_callback),
+            Some(transform_callback),
+            Some(in_domain_callback),
+            // the deprecated InRange callback
+            None,
+            Some(name_callback),
+        )
+    };
+    SliceTransform { inner }
+}
+
+struct PrefixState {
+    name: CString,
+    the_types: Vec<DecodeType>,
+    field_count: usize,
+}
+
+impl PrefixState {
+    fn prefix_len(&self, key: &[u8]) -> Option<usize> {
+        prefix_len(&self.the_types, key, self.field_count)
+    }
+}
+
+// an empty key may come with any pointer
+unsafe fn key_slice<'a>(key: *const c_char, length: size_t) -> &'a [u8] {
+    if length == 0 {
+        &[]
+    } else {
+        slice::from_raw_parts(key as *const u8, length)
+    }
+}
+
+unsafe extern "C" fn transform_callback(state: *mut c_void, key: *const c_char, length: size_t, dst_length: *mut size_t) -> *mut c_char {
+    let state = &*(state as *const PrefixState);
+    // RocksDB only asks for the prefix of keys in the domain, any other key is its own prefix
+    *dst_length = state.prefix_len(key_slice(key, length)).unwrap_or(length);
+    key as *mut c_char
+}
+
+unsafe extern "C" fn in_domain_callback(state: *mut c_void, key: *const c_char, length: size_t) -> c_uchar {
+    let state = &*(state as *const PrefixState);
+    c_uchar::from(state.prefix_len(key_slice(key, length)).is_some())
+}
+
+unsafe extern "C" fn name_callback(state: *mut c_void) -> *const c_char {
+    (*(state as *const PrefixState)).name.as_ptr()
+}
+
+unsafe extern "C" fn destroy_callback(state: *mut c_void) {
+    drop(Box::from_raw(state as *mut PrefixState));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CStr;
+    use crate::rocks_sortable_keys::{encode_keys, EncodeType, NullOrder};
+    use crate::rockssort_collation::{CollatedString, Collation};
+    use crate::rockssort_decimal::Decimal;
+
+    #[test]
+    fn test_prefix_len() {
+        let the_types = vec![
+            DecodeType::DecodeString,
+            DecodeType::Reverse,
+            DecodeType::DecodeVarI64,
+            DecodeType::DecodeOption(Box::new(DecodeType::DecodeBytes), NullOrder::NullsLast),
+            DecodeType::DecodeList(Box::new(DecodeType::DecodeTuple(vec![DecodeType::DecodeU8, DecodeType::Reverse, DecodeType::DecodeDecimal]))),
+            DecodeType::DecodeUnion(vec![DecodeType::DecodeBool, DecodeType::DecodeCollatedString(Collation::UnicodeCaseFold, true)]),
+            DecodeType::DecodeCollatedString(Collation::Nfc, false),
+            DecodeType::DecodeU32,
+        ];
+        let keys = [
+            vec![
+                EncodeType::SortString("tenant".to_string()),
+                EncodeType::SortVarI64(-300),
+                EncodeType::SortOption(Some(Box::new(EncodeType::SortBytes(vec![0, 1, 2])))),
+                EncodeType::SortList(vec![
+                    EncodeType::SortTuple(vec![EncodeType::SortU8(1), EncodeType::SortDecimal(Decimal::new(-1995, 2))]),
+                    EncodeType::SortTuple(vec![EncodeType::SortU8(2), EncodeType::SortDecimal(Decimal::zero())]),
+                ]),
+                EncodeType::SortUnion(1, Box::new(EncodeType::SortCollatedString(CollatedString::new("Straße", Collation::UnicodeCaseFold, true)))),
+                EncodeType::SortCollatedString(CollatedString::new("caf\u{e9}", Collation::Nfc, false)),
+                EncodeType::SortU32(7),
+            ],
+            vec![
+                EncodeType::SortString(String::new()),
+                EncodeType::SortVarI64(0),
+                EncodeType::SortOption(None),
+                EncodeType::SortList(vec![]),
+                EncodeType::SortUnion(0, Box::new(EncodeType::SortBool(true))),
+                EncodeType::SortCollatedString(CollatedString::new("", Collation::Nfc, false)),
+                EncodeType::SortU32(0),
+            ],
+        ];
+        for key in &keys {
+            let encoded = encode_keys(key);
+            for field_count in 0..=key.len() {
+                assert_eq!(prefix_len(&the_types, &encoded, field_count), Some(encode_keys(&key[..field_count]).len()));
+                // a shorter key is outside of the domain of every longer prefix
+                let shorter = encode_keys(&key[..field_count]);
+                assert_eq!(prefix_len(&the_types, &shorter, field_count + 1), None);
+            }
+            assert_eq!(prefix_len(&the_types, &encoded, key.len() + 1), None);
+            assert_eq!(prefix_len(&the_types, &encoded[..encoded.len() - 1], key.len()), None);
+        }
+        // a list marker that is neither an element nor the end
+        let mut corrupt = encode_keys(&keys[1][..3]);
+        corrupt.push(7);
+        assert_eq!(prefix_len(&the_types, &corrupt, 4), None);
+    }
+
+    #[test]
+    fn test_prefix_len_with_empty_fields() {
+        let the_types = vec![
+            DecodeType::DecodeU8,
+            DecodeType::DecodeFixedBytes(0),
+            DecodeType::Reverse,
+            DecodeType::DecodeTuple(vec![DecodeType::Reverse, DecodeType::DecodeFixedBytes(0), DecodeType::DecodeTuple(vec![])]),
+            DecodeType::DecodeString,
+        ];
+        let key = [
+            EncodeType::SortU8(1),
+            EncodeType::SortFixedBytes(vec![]),
+            EncodeType::SortTuple(vec![EncodeType::SortFixedBytes(vec![]), EncodeType::SortTuple(vec![])]),
+            EncodeType::SortString("a".to_string()),
+        ];
+        for field_count in 0..=key.len() {
+            let prefix = encode_keys(&key[..field_count]);
+            assert_eq!(prefix_len(&the_types, &encode_keys(&key), field_count), Some(prefix.len()));
+            // the prefix of a key is in the domain
+            assert_eq!(prefix_len(&the_types, &prefix, field_count), Some(prefix.len()));
+        }
+        assert_eq!(prefix_len(&the_types, &encode_keys(&key[..3]), 4), None);
+        assert_eq!(prefix_len(&the_types, &[], 1), None);
+    }
+
+    #[test]
+    fn test_callbacks() {
+        let the_types = vec![DecodeType::DecodeString, DecodeType::Reverse, DecodeType::DecodeU16, DecodeType::DecodeU8];
+        let state = Box::into_raw(Box::new(PrefixState { name: CString::new("events.prefix2").unwrap(), the_types, field_count: 2 })) as *mut c_void;
+        let key = encode_keys(&[EncodeType::SortString("abc".to_string()), EncodeType::SortU16(1), EncodeType::SortU8(2)]);
+        let short = encode_keys(&[EncodeType::SortString("abc".to_string())]);
+        unsafe {
+            assert_eq!(CStr::from_ptr(name_callback(state)).to_str(), Ok("events.prefix2"));
+            assert_eq!(in_domain_callback(state, key.as_ptr() as *const c_char, key.len()), 1);
+            assert_eq!(in_domain_callback(state, short.as_ptr() as *const c_char, short.len()), 0);
+            assert_eq!(in_domain_callback(state, std::ptr::null(), 0), 0);
+            let mut length = 0;
+            let prefix = transform_callback(state, key.as_ptr() as *const c_char, key.len(), &mut length);
+            assert_eq!(prefix as *const u8, key.as_ptr());
+            assert_eq!(length, 4 + 3 + 2);
+            transform_callback(state, short.as_ptr() as *const c_char, short.len(), &mut length);
+            assert_eq!(length, short.len());
+            destroy_callback(state);
+        }
+    }
+}
@@ -0,0 +1,652 @@
+use std::cmp::Ordering;
+use rocksdb::{Options, SliceTransform};
+use crate::rocks_sortable_keys::{check_reverse, compare_encoded_with, encode_keys, encode_prefix, serialize_decode_types, try_decode_byte_array, try_deserialize_decode_types, try_validate_types, DecodeType, EncodeType, NullOrder, PrefixOrder};
+use crate::rockssort_error::KeyError;
+use crate::rockssort_prefix::prefix_extractor;
+
+// descriptor layout, all integers big endian:
+//   magic "RSKS", version u8, field count u16, version 2 only: key flags u8,
+//   per field: flags u8, type tag length u8, type tags, name length u16, name (utf-8),
+//   a type tag length of 255 or more is written as 0xff followed by the length as u32,
+//   crc32 of everything before it
+// version 1 is still written when no key flag is set, so older readers can open the database
+const DESCRIPTOR_MAGIC: &[u8; 4] = b"RSKS";
+const DESCRIPTOR_VERSION: u8 = 1;
+const DESCRIPTOR_VERSION_KEY_FLAGS: u8 = 2;
+const KEY_FLAG_SHORTER_LAST: u8 = 1;
+const FLAG_DESCENDING: u8 = 1;
+const FLAG_NULLABLE: u8 = 2;
+const FLAG_NULLS_LAST: u8 = 4;
+const LONG_TYPE_TAGS: u8 = 0xff;
+
+/// The comparator type taken by `rocksdb::Options::set_comparator`.
+pub type CompareFn = dyn Fn(&[u8], &[u8]) -> Ordering;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyField {
+    pub name: String,
+    pub the_type: DecodeType,
+    pub descending: bool,
+    pub nullable: bool,
+    /// Only used for nullable fields.
+    pub nulls_last: bool,
+}
+
+/// The fields of a compound key, in sort order.
+///
+/// ```
+/// use rocks_sortable_keys::{DecodeType, KeySchema};
+///
+/// let schema = KeySchema::new()
+///     .field("customer", DecodeType::DecodeString)
+///     .field("created", DecodeType::DecodeU64).desc();
+/// assert_eq!(schema.decode_types(), vec![DecodeType::DecodeString, DecodeType::Reverse, DecodeType::DecodeU64]);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct KeySchema {
+    fields: Vec<KeyField>,
+    prefix_order: PrefixOrder,
+    // the fields as types, rebuilt by every method that changes the fields
+    the_types: Vec<DecodeType>,
+}
+
+impl KeySchema {
+    pub fn new() -> Self {
+        Self {
+            fields: Vec::new(),
+            prefix_order: PrefixOrder::ShorterFirst,
+            the_types: Vec::new(),
+        }
+    }
+
+    /// Adds an ascending field. It is not nullable, unless `the_type` is a `DecodeType::DecodeOption`.
+    pub fn field(mut self, name: &str, the_type: DecodeType) -> Self {
+        assert!(the_type != DecodeType::Reverse, "use desc() to reverse a field");
+        let (the_type, nullable, nulls_last) = match the_type {
+            DecodeType::DecodeOption(inner, null_order) => (*inner, true, null_order == NullOrder::NullsLast),
+            the_type => (the_type, false, false),
+        };
+        self.fields.push(KeyField { name: name.to_string(), the_type, descending: false, nullable, nulls_last });
+        self.with_types()
+    }
+
+    /// Adds a field holding all fields of `schema` as one `DecodeType::DecodeTuple`, so a group of fields
+    /// can be shared between schemas. `desc()` then reverses the whole group.
+    pub fn tuple(self, name: &str, schema: &KeySchema) -> Self {
+        self.field(name, DecodeType::DecodeTuple(schema.decode_types()))
+    }
+
+    /// Sorts the last added field descending.
+    pub fn desc(mut self) -> Self {
+        self.last_field().descending = true;
+        self.with_types()
+    }
+
+    /// Sorts the last added field ascending, which is the default.
+    pub fn asc(mut self) -> Self {
+        self.last_field().descending = false;
+        self.with_types()
+    }
+
+    /// Marks the last added field as nullable, its values are then `EncodeType::SortOption`.
+    /// Nulls sort first unless `nulls_last` is set.
+    pub fn nullable(mut self) -> Self {
+        self.last_field().nullable = true;
+        self.with_types()
+    }
+
+    /// Makes the last added field nullable with the nulls sorted before all values, also when the field is descending.
+    pub fn nulls_first(mut self) -> Self {
+        let field = self.last_field();
+        field.nullable = true;
+        field.nulls_last = false;
+        self.with_types()
+    }
+
+    /// Makes the last added field nullable with the nulls sorted after all values, also when the field is descending.
+    pub fn nulls_last(mut self) -> Self {
+        let field = self.last_field();
+        field.nullable = true;
+        field.nulls_last = true;
+        self.with_types()
+    }
+
+    /// Keys that hold only the leading fields sort before the longer keys they start, which is the default.
+    pub fn shorter_first(mut self) -> Self {
+        self.prefix_order = PrefixOrder::ShorterFirst;
+        self
+    }
+
+    /// Keys that hold only the leading fields sort after the longer keys they start.
+    pub fn shorter_last(mut self) -> Self {
+        self.prefix_order = PrefixOrder::ShorterLast;
+        self
+    }
+
+    /// Flips the direction of every field and moves the nulls to the other end, also those nested in tuples,
+    /// unions and lists, and moves the keys with only the leading fields to the other side of the longer keys they start,
+    /// so all keys iterate in the opposite order.
+    pub fn reversed(mut self) -> Self {
+        for field in &mut self.fields {
+            field.descending = !field.descending;
+            field.nulls_last = field.nullable && !field.nulls_last;
+            field.the_type = flip_null_orders(&field.the_type);
+        }
+        self.prefix_order = match self.prefix_order {
+            PrefixOrder::ShorterFirst => PrefixOrder::ShorterLast,
+            PrefixOrder::ShorterLast => PrefixOrder::ShorterFirst,
+        };
+        self.with_types()
+    }
+
+    /// Builds a schema from types in the positional format, where `DecodeType::Reverse` makes the next field descending,
+    /// e.g. the types of an existing database. The fields are named by their position, starting at "0".
+    /// The schema gives the keys the same order as `compare_bytes` with `the_types`.
+    pub fn from_decode_types(the_types: &[DecodeType]) -> Result<KeySchema, KeyError> {
+        check_reverse(the_types)?;
+        let mut schema = KeySchema::new();
+        let mut is_reverse = false;
+        for the_type in the_types {
+            if the_type == &DecodeType::Reverse {
+                is_reverse = true;
+                continue;
+            }
+            let name = schema.fields.len().to_string();
+            schema = schema.field(&name, the_type.clone());
+            if is_reverse {
+                schema = schema.desc();
+            }
+            is_reverse = false;
+        }
+        Ok(schema)
+    }
+
+    /// Like `from_decode_types`, for types written by `serialize_decode_types`.
+    pub fn from_serialized_types(data: &[u8]) -> Result<KeySchema, KeyError> {
+        Self::from_decode_types(&try_deserialize_decode_types(data)?)
+    }
+
+    fn last_field(&mut self) -> &mut KeyField {
+        self.fields.last_mut().expect("add a field before setting its options")
+    }
+
+    fn with_types(mut self) -> Self {
+        let mut the_types = Vec::new();
+        for field in &self.fields {
+            if field.descending {
+                the_types.push(DecodeType::Reverse);
+            }
+            the_types.push(field.decode_type());
+        }
+        self.the_types = the_types;
+        self
+    }
+
+    pub fn fields(&self) -> &[KeyField] {
+        &self.fields
+    }
+
+    pub fn prefix_order(&self) -> PrefixOrder {
+        self.prefix_order
+    }
+
+    /// The schema as a list of types, with `DecodeType::Reverse` in front of descending fields
+    /// and nullable fields wrapped in `DecodeType::DecodeOption`.
+    pub fn decode_types(&self) -> Vec<DecodeType> {
+        self.the_types.clone()
+    }
+
+    pub fn encode_key(&self, keys: &[EncodeType]) -> Result<Vec<u8>, KeyError> {
+        try_validate_types(&self.the_types, keys)?;
+        Ok(encode_keys(keys))
+    }
+
+    /// Encodes the values of the first `keys.len()` fields. With `PrefixOrder::ShorterFirst` the key sorts
+    /// right before the keys starting with these values, so it can be used to seek to the first of them.
+    pub fn encode_prefix(&self, keys: &[EncodeType]) -> Result<Vec<u8>, KeyError> {
+        encode_prefix(&self.the_types, keys)
+    }
+
+    pub fn decode_key(&self, data: &[u8]) -> Result<Vec<EncodeType>, KeyError> {
+        try_decode_byte_array(data, &self.the_types)
+    }
+
+    pub fn compare(&self, key1: &[u8], key2: &[u8]) -> Ordering {
+        compare_encoded_with(&self.the_types, self.prefix_order, key1, key2)
+    }
+
+    fn key_flags(&self) -> u8 {
+        match self.prefix_order {
+            PrefixOrder::ShorterFirst => 0,
+            PrefixOrder::ShorterLast => KEY_FLAG_SHORTER_LAST,
+        }
+    }
+
+    // the parts of the schema that decide the order of the keys, field names are left out,
+    // the key flags only when set so the names of existing comparators stay the same
+    fn ordering_bytes(&self) -> Vec<u8> {
+        let mut result = Vec::new();
+        for field in &self.fields {
+            field.write_ordering(&mut result);
+        }
+        if self.key_flags() != 0 {
+            result.push(self.key_flags());
+        }
+        result
+    }
+
+    /// True when both schemas give the keys the same order, field names are not compared.
+    /// Unlike the comparator names, which are only a checksum of the order, this can not collide.
+    pub(crate) fn same_order(&self, other: &KeySchema) -> bool {
+        self.ordering_bytes() == other.ordering_bytes()
+    }
+
+    /// A name for the RocksDB comparator. It only changes when the order of the keys changes,
+    /// so renaming a field does not stop an existing database from opening.
+    pub fn comparator_name(&self) -> String {
+        format!("rocks_sortable_keys.v{}.{:08x}", DESCRIPTOR_VERSION, crc32(&self.ordering_bytes()))
+    }
+
+    /// A comparator for keys written with `encode_key`, ready for `Options::set_comparator`.
+    pub fn comparator(&self) -> Box<CompareFn> {
+        let the_types = self.the_types.clone();
+        let prefix_order = self.prefix_order;
+        Box::new(move |one: &[u8], two: &[u8]| compare_encoded_with(&the_types, prefix_order, one, two))
+    }
+
+    pub fn set_comparator(&self, opts: &mut Options) {
+        opts.set_comparator(&self.comparator_name(), self.comparator());
+    }
+
+    /// A RocksDB prefix extractor for the first `field_count` fields of keys written with `encode_key`.
+    /// Its name is the comparator name with the count, so it changes with the order of the keys.
+    pub fn prefix_extractor(&self, field_count: usize) -> SliceTransform {
+        assert!(field_count <= self.fields.len(), "the prefix can not have more fields than the schema");
+        prefix_extractor(&format!("{}.prefix{}", self.comparator_name(), field_count), &self.the_types, field_count)
+    }
+
+    /// Sets the comparator and a prefix extractor for the first `field_count` fields, so reads with
+    /// `ReadOptions::set_prefix_same_as_start` stay within the keys that start with the same values.
+    pub fn set_comparator_with_prefix(&self, opts: &mut Options, field_count: usize) {
+        self.set_comparator(opts);
+        opts.set_prefix_extractor(self.prefix_extractor(field_count));
+    }
+
+    /// Serializes the schema, including field names, into the versioned descriptor format.
+    pub fn to_descriptor(&self) -> Vec<u8> {
+        let mut result = DESCRIPTOR_MAGIC.to_vec();
+        let key_flags = self.key_flags();
+        result.push(if key_flags == 0 { DESCRIPTOR_VERSION } else { DESCRIPTOR_VERSION_KEY_FLAGS });
+        result.extend((self.fields.len() as u16).to_be_bytes());
+        if key_flags != 0 {
+            result.push(key_flags);
+        }
+        for field in &self.fields {
+            field.write_ordering(&mut result);
+            result.extend((field.name.len() as u16).to_be_bytes());
+            result.extend(field.name.as_bytes());
+        }
+        let checksum = crc32(&result);
+        result.extend(checksum.to_be_bytes());
+        result
+    }
+
+    pub fn from_descriptor(data: &[u8]) -> Result<KeySchema, KeyError> {
+        if data.len() < DESCRIPTOR_MAGIC.len() + 7 || &data[..4] != DESCRIPTOR_MAGIC {
+            return Err(KeyError::InvalidDescriptor { reason: "missing magic bytes" });
+        }
+        let (body, checksum) = data.split_at(data.len() - 4);
+        if crc32(body) != u32::from_be_bytes(checksum.try_into().unwrap()) {
+            return Err(KeyError::InvalidDescriptor { reason: "checksum mismatch" });
+        }
+        if body[4] != DESCRIPTOR_VERSION && body[4] != DESCRIPTOR_VERSION_KEY_FLAGS {
+            return Err(KeyError::InvalidDescriptor { reason: "unsupported version" });
+        }
+        let mut reader = DescriptorReader { data: body, pos: 5 };
+        let field_count = u16::from_be_bytes([reader.byte()?, reader.byte()?]);
+        let key_flags = if body[4] == DESCRIPTOR_VERSION_KEY_FLAGS { reader.byte()? } else { 0 };
+        if key_flags & !KEY_FLAG_SHORTER_LAST != 0 {
+            return Err(KeyError::InvalidDescriptor { reason: "unknown key flags" });
+        }
+        let prefix_order = if key_flags & KEY_FLAG_SHORTER_LAST != 0 { PrefixOrder::ShorterLast } else { PrefixOrder::ShorterFirst };
+        let mut fields = Vec::new();
+        for _ in 0..field_count {
+            let flags = reader.byte()?;
+            if flags & !(FLAG_DESCENDING | FLAG_NULLABLE | FLAG_NULLS_LAST) != 0 {
+                return Err(KeyError::InvalidDescriptor { reason: "unknown field flags" });
+            }
+            if flags & FLAG_NULLS_LAST != 0 && flags & FLAG_NULLABLE == 0 {
+                return Err(KeyError::InvalidDescriptor { reason: "nulls last on a field that is not nullable" });
+            }
+            let tag_len = match reader.byte()? {
+                LONG_TYPE_TAGS => u32::from_be_bytes(reader.bytes(4)?.try_into().unwrap()) as usize,
+                tag_len => tag_len as usize,
+            };
+            let the_types = try_deserialize_decode_types(reader.bytes(tag_len)?)?;
+            let the_type = match the_types.as_slice() {
+                [the_type] if *the_type != DecodeType::Reverse => the_type.clone(),
+                _ => return Err(KeyError::InvalidDescriptor { reason: "invalid field type" }),
+            };
+            // `field()` moves an outer option into the flags, so only a nullable field holds an option type
+            if flags & FLAG_NULLABLE == 0 && matches!(the_type, DecodeType::DecodeOption(..)) {
+                return Err(KeyError::InvalidDescriptor { reason: "option type on a field that is not nullable" });
+            }
+            let name_len = u16::from_be_bytes([reader.byte()?, reader.byte()?]) as usize;
+            let name = String::from_utf8(reader.bytes(name_len)?.to_vec()).map_err(|_| KeyError::InvalidDescriptor { reason: "field name is not utf-8" })?;
+            fields.push(KeyField { name, the_type, descending: flags & FLAG_DESCENDING != 0, nullable: flags & FLAG_NULLABLE != 0, nulls_last: flags & FLAG_NULLS_LAST != 0 });
+        }
+        if reader.pos != body.len() {
+            return Err(KeyError::InvalidDescriptor { reason: "trailing bytes" });
+        }
+        Ok(KeySchema { fields, prefix_order, the_types: Vec::new() }.with_types())
+    }
+}
+
+// `desc()` keeps the nulls in place, reversing a type also needs the nested nulls at the other end
+fn flip_null_orders(the_type: &DecodeType) -> DecodeType {
+    match the_type {
+        DecodeType::DecodeOption(inner, null_order) => {
+            let null_order = match null_order {
+                NullOrder::NullsFirst => NullOrder::NullsLast,
+                NullOrder::NullsLast => NullOrder::NullsFirst,
+            };
+            DecodeType::DecodeOption(Box::new(flip_null_orders(inner)), null_order)
+        },
+        DecodeType::DecodeTuple(inner) => DecodeType::DecodeTuple(inner.iter().map(flip_null_orders).collect()),
+        DecodeType::DecodeUnion(variants) => DecodeType::DecodeUnion(variants.iter().map(flip_null_orders).collect()),
+        DecodeType::DecodeList(element) => DecodeType::DecodeList(Box::new(flip_null_orders(element))),
+        the_type => the_type.clone(),
+    }
+}
+
+impl KeyField {
+    /// The type of the values of this field, `the_type` wrapped in `DecodeType::DecodeOption` when the field is nullable.
+    pub fn decode_type(&self) -> DecodeType {
+        if !self.nullable {
+            return self.the_type.clone();
+        }
+        let null_order = if self.nulls_last { NullOrder::NullsLast } else { NullOrder::NullsFirst };
+        DecodeType::DecodeOption(Box::new(self.the_type.clone()), null_order)
+    }
+
+    fn flags(&self) -> u8 {
+        let mut flags = 0;
+        if self.descending {
+            flags |= FLAG_DESCENDING;
+        }
+        if self.nullable {
+            flags |= FLAG_NULLABLE;
+            if self.nulls_last {
+                flags |= FLAG_NULLS_LAST;
+            }
+        }
+        flags
+    }
+
+    // flags, type tag length and type tags
+    fn write_ordering(&self, out: &mut Vec<u8>) {
+        let the_type = serialize_decode_types(std::slice::from_ref(&self.the_type));
+        out.push(self.flags());
+        if the_type.len() < LONG_TYPE_TAGS as usize {
+            out.push(the_type.len() as u8);
+        } else {
+            out.push(LONG_TYPE_TAGS);
+            out.extend((the_type.len() as u32).to_be_bytes());
+        }
+        out.extend(the_type);
+    }
+}
+
+struct DescriptorReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> DescriptorReader<'a> {
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], KeyError> {
+        if self.data.len() - self.pos < len {
+            return Err(KeyError::InvalidDescriptor { reason: "truncated" });
+        }
+        let value = &self.data[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(value)
+    }
+
+    fn byte(&mut self) -> Result<u8, KeyError> {
+        Ok(self.bytes(1)?[0])
+    }
+}
+
+// crc-32 (ieee), bitwise since descriptors are tiny
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xffff_ffffu32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xedb8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_schema() -> KeySchema {
+        KeySchema::new()
+            .field("customer", DecodeType::DecodeString)
+            .field("created", DecodeType::DecodeU64).desc()
+            .field("note", DecodeType::DecodeBytes).nullable()
+    }
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+    }
+
+    #[test]
+    fn test_builder() {
+        let schema = test_schema();
+        assert_eq!(schema.fields().len(), 3);
+        assert!(schema.fields()[1].descending);
+        assert!(schema.fields()[2].nullable);
+        assert_eq!(schema.decode_types(), vec![DecodeType::DecodeString, DecodeType::Reverse, DecodeType::DecodeU64, DecodeType::DecodeOption(Box::new(DecodeType::DecodeBytes), NullOrder::NullsFirst)]);
+        let option_type = KeySchema::new().field("note", DecodeType::DecodeOption(Box::new(DecodeType::DecodeBytes), NullOrder::NullsFirst));
+        assert_eq!(option_type.fields(), &schema.fields()[2..]);
+    }
+
+    #[test]
+    fn test_nulls_last() {
+        let schema = KeySchema::new().field("deleted", DecodeType::DecodeU64).desc().nulls_last();
+        let descriptor = schema.to_descriptor();
+        assert_eq!(KeySchema::from_descriptor(&descriptor), Ok(schema.clone()));
+        assert_ne!(schema.comparator_name(), KeySchema::new().field("deleted", DecodeType::DecodeU64).desc().nulls_first().comparator_name());
+        let null = schema.encode_key(&[EncodeType::SortOption(None)]).unwrap();
+        let one = schema.encode_key(&[EncodeType::SortOption(Some(Box::new(EncodeType::SortU64(1))))]).unwrap();
+        let two = schema.encode_key(&[EncodeType::SortOption(Some(Box::new(EncodeType::SortU64(2))))]).unwrap();
+        assert_eq!(schema.compare(&two, &one), Ordering::Less);
+        assert_eq!(schema.compare(&one, &null), Ordering::Less);
+        assert_eq!(schema.decode_key(&null), Ok(vec![EncodeType::SortOption(None)]));
+    }
+
+    #[test]
+    fn test_tuple_fields() {
+        let location = KeySchema::new()
+            .field("region", DecodeType::DecodeString)
+            .field("zone", DecodeType::DecodeU16).desc();
+        let schema = KeySchema::new()
+            .field("tenant", DecodeType::DecodeU32)
+            .tuple("location", &location).desc()
+            .field("id", DecodeType::DecodeU64);
+        assert_eq!(schema.decode_types()[1..3], [DecodeType::Reverse, DecodeType::DecodeTuple(location.decode_types())]);
+        assert_eq!(KeySchema::from_descriptor(&schema.to_descriptor()), Ok(schema.clone()));
+        let key = |region: &str, zone: u16, id: u64| {
+            let location = EncodeType::SortTuple(vec![EncodeType::SortString(region.to_string()), EncodeType::SortU16(zone)]);
+            schema.encode_key(&[EncodeType::SortU32(1), location, EncodeType::SortU64(id)]).unwrap()
+        };
+        // the region sorts descending, the zone ascending
+        assert_eq!(schema.compare(&key("b", 9, 1), &key("a", 1, 1)), Ordering::Less);
+        assert_eq!(schema.compare(&key("a", 1, 1), &key("a", 2, 1)), Ordering::Less);
+        assert_eq!(schema.compare(&key("a", 1, 1), &key("a", 1, 2)), Ordering::Less);
+        assert!(schema.encode_key(&[EncodeType::SortU32(1), EncodeType::SortTuple(vec![EncodeType::SortString("a".to_string())]), EncodeType::SortU64(1)]).is_err());
+        // type tags longer than 254 bytes
+        let wide = KeySchema::new().field("wide", DecodeType::DecodeTuple(vec![DecodeType::DecodeU8; 300]));
+        assert_eq!(KeySchema::from_descriptor(&wide.to_descriptor()), Ok(wide));
+    }
+
+    #[test]
+    fn test_reversed() {
+        let schema = test_schema().reversed();
+        assert_eq!(schema.decode_types(), vec![DecodeType::Reverse, DecodeType::DecodeString, DecodeType::DecodeU64, DecodeType::Reverse, DecodeType::DecodeOption(Box::new(DecodeType::DecodeBytes), NullOrder::NullsLast)]);
+        assert_eq!(schema.clone().reversed(), test_schema());
+        let note = |note: Option<&[u8]>| EncodeType::SortOption(note.map(|note| Box::new(EncodeType::SortBytes(note.to_vec()))));
+        let keys: Vec<Vec<u8>> = [("a", 2, None), ("a", 2, Some(&b""[..])), ("a", 2, Some(&b"x"[..])), ("a", 1, Some(&b"x"[..])), ("a", 1, None), ("b", 3, None)].iter()
+            .map(|(name, number, value)| test_schema().encode_key(&[EncodeType::SortString(name.to_string()), EncodeType::SortU64(*number), note(*value)]).unwrap()).collect();
+        let mut sorted = keys.clone();
+        sorted.sort_by(|key1, key2| test_schema().compare(key1, key2));
+        let mut reversed = keys.clone();
+        reversed.sort_by(|key1, key2| schema.compare(key1, key2));
+        sorted.reverse();
+        assert_eq!(reversed, sorted);
+        // keys of different lengths, also a key and the shorter keys it starts
+        assert_eq!(schema.prefix_order(), PrefixOrder::ShorterLast);
+        let values = [EncodeType::SortString("a".to_string()), EncodeType::SortU64(2), note(Some(b"x"))];
+        let keys: Vec<Vec<u8>> = (0..=values.len()).map(|len| test_schema().encode_prefix(&values[..len]).unwrap())
+            .chain([test_schema().encode_prefix(&[EncodeType::SortString("a".to_string()), EncodeType::SortU64(1)]).unwrap(), test_schema().encode_prefix(&[EncodeType::SortString("b".to_string())]).unwrap()])
+            .collect();
+        for key1 in &keys {
+            for key2 in &keys {
+                assert_eq!(schema.compare(key1, key2), test_schema().compare(key2, key1));
+            }
+        }
+        assert_eq!(test_schema().shorter_last().reversed().prefix_order(), PrefixOrder::ShorterFirst);
+    }
+
+    #[test]
+    fn test_reversed_nested_nulls() {
+        let pair = DecodeType::DecodeTuple(vec![DecodeType::DecodeU8, DecodeType::DecodeOption(Box::new(DecodeType::DecodeU8), NullOrder::NullsFirst)]);
+        let schema = KeySchema::new().field("pair", pair).field("tags", DecodeType::DecodeList(Box::new(DecodeType::DecodeOption(Box::new(DecodeType::DecodeString), NullOrder::NullsLast))));
+        let key = |first: u8, second: Option<u8>, tag: Option<&str>| {
+            let second = EncodeType::SortOption(second.map(|second| Box::new(EncodeType::SortU8(second))));
+            let tags = EncodeType::SortList(vec![EncodeType::SortOption(tag.map(|tag| Box::new(EncodeType::SortString(tag.to_string()))))]);
+            schema.encode_key(&[EncodeType::SortTuple(vec![EncodeType::SortU8(first), second]), tags]).unwrap()
+        };
+        let keys = [key(1, None, Some("a")), key(1, None, None), key(1, Some(0), Some("a")), key(2, None, Some("b"))];
+        let reversed = schema.clone().reversed();
+        for pair in keys.windows(2) {
+            assert_eq!(schema.compare(&pair[0], &pair[1]), Ordering::Less);
+            assert_eq!(reversed.compare(&pair[0], &pair[1]), Ordering::Greater);
+        }
+        assert_eq!(reversed.reversed(), schema);
+    }
+
+    #[test]
+    fn test_from_decode_types() {
+        let the_types = vec![DecodeType::DecodeString, DecodeType::Reverse, DecodeType::DecodeU64, DecodeType::DecodeOption(Box::new(DecodeType::DecodeBytes), NullOrder::NullsFirst)];
+        let schema = KeySchema::from_decode_types(&the_types).unwrap();
+        assert_eq!(schema.decode_types(), the_types);
+        let names: Vec<&str> = schema.fields().iter().map(|field| field.name.as_str()).collect();
+        assert_eq!(names, vec!["0", "1", "2"]);
+        assert_eq!(schema.comparator_name(), test_schema().comparator_name());
+        assert_eq!(KeySchema::from_serialized_types(&serialize_decode_types(&the_types)), Ok(schema));
+        assert_eq!(KeySchema::from_decode_types(&[DecodeType::DecodeU8, DecodeType::Reverse]), Err(KeyError::MisplacedReverse { index: 1 }));
+        assert_eq!(KeySchema::from_decode_types(&[DecodeType::Reverse, DecodeType::Reverse, DecodeType::DecodeU8]), Err(KeyError::MisplacedReverse { index: 0 }));
+    }
+
+    #[test]
+    fn test_descriptor_round_trip() {
+        let schema = test_schema();
+        let descriptor = schema.to_descriptor();
+        assert_eq!(&descriptor[..4], b"RSKS");
+        assert_eq!(KeySchema::from_descriptor(&descriptor), Ok(schema));
+    }
+
+    #[test]
+    fn test_prefix_order() {
+        let schema = test_schema().shorter_last();
+        assert_eq!(schema.prefix_order(), PrefixOrder::ShorterLast);
+        assert_eq!(KeySchema::from_descriptor(&schema.to_descriptor()), Ok(schema.clone()));
+        // the descriptors and comparator names of existing schemas do not change
+        assert_eq!(test_schema().to_descriptor()[4], DESCRIPTOR_VERSION);
+        assert_eq!(schema.to_descriptor()[4], DESCRIPTOR_VERSION_KEY_FLAGS);
+        assert_eq!(schema.clone().shorter_first().comparator_name(), test_schema().comparator_name());
+        assert_ne!(schema.comparator_name(), test_schema().comparator_name());
+
+        let prefix = schema.encode_prefix(&[EncodeType::SortString("a".to_string())]).unwrap();
+        let key = schema.encode_key(&[EncodeType::SortString("a".to_string()), EncodeType::SortU64(1), EncodeType::SortOption(None)]).unwrap();
+        assert_eq!(test_schema().compare(&prefix, &key), Ordering::Less);
+        assert_eq!(schema.compare(&prefix, &key), Ordering::Greater);
+        assert_eq!((schema.comparator())(&prefix, &key), Ordering::Greater);
+        assert!(schema.encode_prefix(&[EncodeType::SortU64(1)]).is_err());
+    }
+
+    #[test]
+    fn test_descriptor_rejects_corruption() {
+        let mut descriptor = test_schema().to_descriptor();
+        descriptor[8] ^= 1;
+        assert_eq!(KeySchema::from_descriptor(&descriptor), Err(KeyError::InvalidDescriptor { reason: "checksum mismatch" }));
+        assert_eq!(KeySchema::from_descriptor(b"nope"), Err(KeyError::InvalidDescriptor { reason: "missing magic bytes" }));
+    }
+
+    #[test]
+    fn test_descriptor_refuses_raw_floats() {
+        let schema = KeySchema::new().field("price", DecodeType::DecodeF64);
+        let mut descriptor = schema.to_descriptor();
+        // magic, version, field count, flags and tag length come before the tag
+        assert_eq!(descriptor[9], 33);
+        // the tag an earlier version wrote for the raw IEEE bytes
+        descriptor[9] = 8;
+        let body = descriptor.len() - 4;
+        let checksum = crc32(&descriptor[..body]);
+        descriptor[body..].copy_from_slice(&checksum.to_be_bytes());
+        assert_eq!(KeySchema::from_descriptor(&descriptor), Err(KeyError::InvalidDescriptor { reason: "f32 or f64 field written as raw IEEE bytes by an earlier version" }));
+    }
+
+    #[test]
+    fn test_descriptor_refuses_invalid_field_flags() {
+        let with_flags = |schema: KeySchema, flags: u8| {
+            let mut descriptor = schema.to_descriptor();
+            // magic, version and field count come before the flags of the first field
+            descriptor[7] = flags;
+            let body = descriptor.len() - 4;
+            let checksum = crc32(&descriptor[..body]);
+            descriptor[body..].copy_from_slice(&checksum.to_be_bytes());
+            KeySchema::from_descriptor(&descriptor)
+        };
+        let schema = KeySchema::new().field("count", DecodeType::DecodeU8);
+        assert_eq!(with_flags(schema.clone(), FLAG_DESCENDING | FLAG_NULLABLE | FLAG_NULLS_LAST), Ok(schema.clone().desc().nulls_last()));
+        assert_eq!(with_flags(schema.clone(), 8), Err(KeyError::InvalidDescriptor { reason: "unknown field flags" }));
+        assert_eq!(with_flags(schema.clone(), FLAG_DESCENDING | FLAG_NULLS_LAST), Err(KeyError::InvalidDescriptor { reason: "nulls last on a field that is not nullable" }));
+        // an option inside a nullable field
+        let nested = KeySchema::new().field("count", DecodeType::DecodeOption(Box::new(DecodeType::DecodeOption(Box::new(DecodeType::DecodeU8), NullOrder::NullsFirst)), NullOrder::NullsFirst));
+        assert_eq!(with_flags(nested.clone(), FLAG_NULLABLE), Ok(nested.clone()));
+        assert_eq!(with_flags(nested, 0), Err(KeyError::InvalidDescriptor { reason: "option type on a field that is not nullable" }));
+    }
+
+    #[test]
+    fn test_comparator_name_ignores_field_names() {
+        let renamed = KeySchema::new()
+            .field("client", DecodeType::DecodeString)
+            .field("created", DecodeType::DecodeU64).desc()
+            .field("note", DecodeType::DecodeBytes).nullable();
+        let ascending = KeySchema::new()
+            .field("customer", DecodeType::DecodeString)
+            .field("created", DecodeType::DecodeU64)
+            .field("note", DecodeType::DecodeBytes).nullable();
+        assert_eq!(test_schema().comparator_name(), renamed.comparator_name());
+        assert_ne!(test_schema().comparator_name(), ascending.comparator_name());
+    }
+
+    #[test]
+    fn test_encode_and_compare() {
+        let schema = test_schema();
+        let key1 = schema.encode_key(&[EncodeType::SortString("a".to_string()), EncodeType::SortU64(2), EncodeType::SortOption(None)]).unwrap();
+        let key2 = schema.encode_key(&[EncodeType::SortString("a".to_string()), EncodeType::SortU64(1), EncodeType::SortOption(Some(Box::new(EncodeType::SortBytes(vec![]))))]).unwrap();
+        assert_eq!(schema.compare(&key1, &key2), Ordering::Less);
+        assert_eq!((schema.comparator())(&key1, &key2), Ordering::Less);
+        assert_eq!(schema.decode_key(&key1).unwrap()[1], EncodeType::SortU64(2));
+        assert!(schema.encode_key(&[EncodeType::SortString("a".to_string())]).is_err());
+    }
+}
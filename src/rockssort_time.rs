@@ -0,0 +1,236 @@
+// Time field types. All of them are stored as integers, so they sort in time order:
+//
+//   Timestamp   i64 count of `TimeUnit` since 1970-01-01 00:00:00 UTC, the unit is part of the field type
+//   Date        i32 count of days since 1970-01-01
+//   Duration    u64 seconds followed by u32 nanoseconds (std::time::Duration)
+//
+// Conversions to chrono and time types are behind the `chrono` and `time` features.
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const NANOS_PER_SECOND: i128 = 1_000_000_000;
+// 1970-01-01 counted from 0001-01-01 (chrono) and as julian day (time)
+#[cfg(feature = "chrono")]
+const EPOCH_DAYS_FROM_CE: i32 = 719_163;
+#[cfg(feature = "time")]
+const EPOCH_JULIAN_DAY: i32 = 2_440_588;
+
+/// The unit of a `Timestamp`. Keys of one field always use the same unit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum TimeUnit {
+    Seconds,
+    Millis,
+    Micros,
+    Nanos,
+}
+
+impl TimeUnit {
+    fn nanos(self) -> i128 {
+        match self {
+            TimeUnit::Seconds => 1_000_000_000,
+            TimeUnit::Millis => 1_000_000,
+            TimeUnit::Micros => 1_000,
+            TimeUnit::Nanos => 1,
+        }
+    }
+}
+
+/// A point in time as a count of `unit` since 1970-01-01 00:00:00 UTC, negative before that.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Timestamp {
+    pub since_epoch: i64,
+    pub unit: TimeUnit,
+}
+
+impl Timestamp {
+    pub fn new(since_epoch: i64, unit: TimeUnit) -> Self {
+        Self {
+            since_epoch,
+            unit
+        }
+    }
+
+    // rounds down, so a truncated timestamp never sorts after the exact one
+    fn from_nanos(nanos: i128, unit: TimeUnit) -> Option<Self> {
+        Some(Self::new(i64::try_from(nanos.div_euclid(unit.nanos())).ok()?, unit))
+    }
+
+    fn nanos(&self) -> i128 {
+        self.since_epoch as i128 * self.unit.nanos()
+    }
+
+    /// `None` when the time does not fit into an i64 of `unit`, e.g. nanoseconds after the year 2262.
+    pub fn from_system_time(time: SystemTime, unit: TimeUnit) -> Option<Self> {
+        let nanos = match time.duration_since(UNIX_EPOCH) {
+            Ok(duration) => duration.as_nanos() as i128,
+            Err(error) => -(error.duration().as_nanos() as i128),
+        };
+        Self::from_nanos(nanos, unit)
+    }
+
+    pub fn to_system_time(&self) -> Option<SystemTime> {
+        let nanos = self.nanos();
+        let duration = Duration::new((nanos.unsigned_abs() / NANOS_PER_SECOND as u128) as u64, (nanos.unsigned_abs() % NANOS_PER_SECOND as u128) as u32);
+        if nanos >= 0 {
+            UNIX_EPOCH.checked_add(duration)
+        } else {
+            UNIX_EPOCH.checked_sub(duration)
+        }
+    }
+
+    /// The same point in time in another unit, rounded down.
+    pub fn to_unit(&self, unit: TimeUnit) -> Option<Self> {
+        Self::from_nanos(self.nanos(), unit)
+    }
+
+    #[cfg(feature = "chrono")]
+    pub fn from_chrono<Tz: chrono::TimeZone>(time: &chrono::DateTime<Tz>, unit: TimeUnit) -> Option<Self> {
+        Self::from_nanos(time.timestamp() as i128 * NANOS_PER_SECOND + time.timestamp_subsec_nanos() as i128, unit)
+    }
+
+    #[cfg(feature = "chrono")]
+    pub fn to_chrono(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+        let nanos = self.nanos();
+        let seconds = i64::try_from(nanos.div_euclid(NANOS_PER_SECOND)).ok()?;
+        chrono::DateTime::from_timestamp(seconds, nanos.rem_euclid(NANOS_PER_SECOND) as u32)
+    }
+
+    #[cfg(feature = "time")]
+    pub fn from_time(time: &time::OffsetDateTime, unit: TimeUnit) -> Option<Self> {
+        Self::from_nanos(time.unix_timestamp_nanos(), unit)
+    }
+
+    #[cfg(feature = "time")]
+    pub fn to_time(&self) -> Option<time::OffsetDateTime> {
+        time::OffsetDateTime::from_unix_timestamp_nanos(self.nanos()).ok()
+    }
+}
+
+/// A calendar date as the number of days since 1970-01-01, negative before that.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Date {
+    pub days_since_epoch: i32,
+}
+
+impl Date {
+    pub fn new(days_since_epoch: i32) -> Self {
+        Self {
+            days_since_epoch
+        }
+    }
+
+    /// A date of the proleptic gregorian calendar, `None` when the date does not exist.
+    pub fn from_ymd(year: i32, month: u32, day: u32) -> Option<Self> {
+        if !(1..=12).contains(&month) || day == 0 || day > days_in_month(year, month) {
+            return None;
+        }
+        // days_from_civil from http://howardhinnant.github.io/date_algorithms.html
+        let year = year as i64 - if month <= 2 { 1 } else { 0 };
+        let era = year.div_euclid(400);
+        let year_of_era = year.rem_euclid(400);
+        let day_of_year = (153 * ((month as i64 + 9) % 12) + 2) / 5 + day as i64 - 1;
+        let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+        let days = era * 146_097 + day_of_era - 719_468;
+        Some(Self::new(i32::try_from(days).ok()?))
+    }
+
+    /// The year, month (1 to 12) and day (1 to 31) of the date.
+    pub fn to_ymd(&self) -> (i32, u32, u32) {
+        // civil_from_days from the same page
+        let days = self.days_since_epoch as i64 + 719_468;
+        let era = days.div_euclid(146_097);
+        let day_of_era = days.rem_euclid(146_097);
+        let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let month_index = (5 * day_of_year + 2) / 153;
+        let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+        let month = if month_index < 10 { month_index + 3 } else { month_index - 9 } as u32;
+        let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+        (year as i32, month, day)
+    }
+
+    #[cfg(feature = "chrono")]
+    pub fn from_chrono(date: chrono::NaiveDate) -> Self {
+        use chrono::Datelike;
+        Self::new(date.num_days_from_ce() - EPOCH_DAYS_FROM_CE)
+    }
+
+    #[cfg(feature = "chrono")]
+    pub fn to_chrono(&self) -> Option<chrono::NaiveDate> {
+        chrono::NaiveDate::from_num_days_from_ce_opt(self.days_since_epoch.checked_add(EPOCH_DAYS_FROM_CE)?)
+    }
+
+    #[cfg(feature = "time")]
+    pub fn from_time(date: time::Date) -> Self {
+        Self::new(date.to_julian_day() - EPOCH_JULIAN_DAY)
+    }
+
+    #[cfg(feature = "time")]
+    pub fn to_time(&self) -> Option<time::Date> {
+        time::Date::from_julian_day(self.days_since_epoch.checked_add(EPOCH_JULIAN_DAY)?).ok()
+    }
+}
+
+fn days_in_month(year: i32, month: u32) -> u32 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dates() {
+        assert_eq!(Date::from_ymd(1970, 1, 1), Some(Date::new(0)));
+        assert_eq!(Date::from_ymd(1969, 12, 31), Some(Date::new(-1)));
+        assert_eq!(Date::from_ymd(2000, 3, 1), Some(Date::new(11_017)));
+        assert_eq!(Date::from_ymd(2023, 2, 29), None);
+        assert_eq!(Date::from_ymd(2024, 13, 1), None);
+        for days in (-800_000..800_000).step_by(997) {
+            let (year, month, day) = Date::new(days).to_ymd();
+            assert_eq!(Date::from_ymd(year, month, day), Some(Date::new(days)));
+        }
+    }
+
+    #[test]
+    fn test_timestamps() {
+        let time = UNIX_EPOCH + Duration::new(1_700_000_000, 123_456_789);
+        let millis = Timestamp::from_system_time(time, TimeUnit::Millis).unwrap();
+        assert_eq!(millis, Timestamp::new(1_700_000_000_123, TimeUnit::Millis));
+        assert_eq!(millis.to_system_time(), Some(UNIX_EPOCH + Duration::new(1_700_000_000, 123_000_000)));
+        assert_eq!(millis.to_unit(TimeUnit::Seconds), Some(Timestamp::new(1_700_000_000, TimeUnit::Seconds)));
+        let before = UNIX_EPOCH - Duration::new(1, 500_000_000);
+        let seconds = Timestamp::from_system_time(before, TimeUnit::Seconds).unwrap();
+        assert_eq!(seconds, Timestamp::new(-2, TimeUnit::Seconds));
+        assert_eq!(Timestamp::from_system_time(before, TimeUnit::Nanos).unwrap().to_system_time(), Some(before));
+        assert_eq!(Timestamp::new(i64::MAX, TimeUnit::Seconds).to_unit(TimeUnit::Nanos), None);
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn test_chrono_conversions() {
+        let date = chrono::NaiveDate::from_ymd_opt(2024, 2, 29).unwrap();
+        assert_eq!(Date::from_chrono(date), Date::from_ymd(2024, 2, 29).unwrap());
+        assert_eq!(Date::from_chrono(date).to_chrono(), Some(date));
+        let time = chrono::DateTime::from_timestamp(-5, 250_000_000).unwrap();
+        let micros = Timestamp::from_chrono(&time, TimeUnit::Micros).unwrap();
+        assert_eq!(micros, Timestamp::new(-4_750_000, TimeUnit::Micros));
+        assert_eq!(micros.to_chrono(), Some(time));
+    }
+
+    #[cfg(feature = "time")]
+    #[test]
+    fn test_time_conversions() {
+        let date = time::Date::from_calendar_date(1901, time::Month::December, 13).unwrap();
+        assert_eq!(Date::from_time(date), Date::from_ymd(1901, 12, 13).unwrap());
+        assert_eq!(Date::from_time(date).to_time(), Some(date));
+        let time = time::OffsetDateTime::from_unix_timestamp_nanos(1_700_000_000_123_456_789).unwrap();
+        let nanos = Timestamp::from_time(&time, TimeUnit::Nanos).unwrap();
+        assert_eq!(nanos.since_epoch, 1_700_000_000_123_456_789);
+        assert_eq!(nanos.to_time(), Some(time));
+    }
+}
@@ -2,10 +2,17 @@
 
 #[cfg(test)]
 mod tests {
-    use super::*;
-    use rocksdb::{Options, DB};
+    use rocksdb::{Options, ReadOptions, DB};
+    use std::ops::Bound;
     use std::iter::FromIterator;
     use crate::rocks_sortable_keys::*;
+    use crate::rockssort_memcmp::*;
+    use crate::rockssort_float::*;
+    use crate::rockssort_schema::*;
+    use crate::rockssort_db::*;
+    use crate::rockssort_key::*;
+    use crate::rockssort_error::DbError;
+    use crate::rockssort_decimal::Decimal;
     #[test]
     fn test_compound_comparator() {
     let mut result_vec = Vec::new();
@@ -43,4 +50,359 @@ mod tests {
     // assure that key2 comes before key1, and key3 is last
     assert_eq!(result_vec, vec!["key2", "key1", "key3"]);
     }
+
+    #[test]
+    fn test_memcomparable_keys_with_default_comparator() {
+    let mut result_vec = Vec::new();
+    let the_types = vec![DecodeType::DecodeString, DecodeType::Reverse, DecodeType::DecodeU32];
+    let path = "_path_for_rocksdb_storage_memcmp";
+    {
+        let mut db_opts = Options::default();
+        db_opts.create_if_missing(true);
+        let db = DB::open(&db_opts, path).unwrap();
+        // same keys as above, but no comparator is installed, the bytewise order of the keys is enough
+        let key1 = vec![EncodeType::SortString("a".to_string()), EncodeType::SortU32(0)];
+        let key2 = vec![EncodeType::SortString("a".to_string()), EncodeType::SortU32(1)]; // this one should be at top
+        let key3 = vec![EncodeType::SortString("b".to_string()), EncodeType::SortU32(0)];
+        let key4 = vec![EncodeType::SortString("a\0".to_string()), EncodeType::SortU32(0)];
+        db.put(encode_memcomparable(&the_types, &key1).unwrap(), b"key1").unwrap();
+        db.put(encode_memcomparable(&the_types, &key2).unwrap(), b"key2").unwrap();
+        db.put(encode_memcomparable(&the_types, &key3).unwrap(), b"key3").unwrap();
+        db.put(encode_memcomparable(&the_types, &key4).unwrap(), b"key4").unwrap();
+        let mut iter = db.raw_iterator();
+        iter.seek_to_first();
+        while iter.valid() {
+            assert_eq!(decode_memcomparable(iter.key().unwrap(), &the_types).len(), 2);
+            let val = iter.value().unwrap();
+            let val_str = val.iter().map(|b| *b as char).collect::<Vec<_>>();
+            result_vec.push(String::from_iter(val_str));
+            iter.next();
+        }
+    }
+    let _ = DB::destroy(&Options::default(), path);
+    assert_eq!(result_vec, vec!["key2", "key1", "key4", "key3"]);
+    }
+
+    #[test]
+    fn test_comparator_for_all_types() {
+    let the_types = vec![DecodeType::DecodeU8, DecodeType::DecodeU16, DecodeType::DecodeU32, DecodeType::DecodeU64, DecodeType::DecodeU128, DecodeType::DecodeI8, DecodeType::DecodeI16, DecodeType::DecodeI32, DecodeType::DecodeI64, DecodeType::DecodeI128, DecodeType::DecodeString, DecodeType::DecodeBytes, DecodeType::DecodeBool, DecodeType::DecodeF32, DecodeType::Reverse, DecodeType::DecodeF64];
+    let base = vec![EncodeType::SortU8(1), EncodeType::SortU16(1), EncodeType::SortU32(1), EncodeType::SortU64(1), EncodeType::SortU128(1), EncodeType::SortI8(1), EncodeType::SortI16(1), EncodeType::SortI32(1), EncodeType::SortI64(1), EncodeType::SortI128(1), EncodeType::SortString("b".to_string()), EncodeType::SortBytes(vec![1]), EncodeType::SortBool(false), EncodeType::SortF32(F32struct::new(1.0)), EncodeType::SortF64(F64struct::new(1.0))];
+    let smaller = vec![EncodeType::SortU8(0), EncodeType::SortU16(0), EncodeType::SortU32(0), EncodeType::SortU64(0), EncodeType::SortU128(0), EncodeType::SortI8(-1), EncodeType::SortI16(-1), EncodeType::SortI32(-1), EncodeType::SortI64(-1), EncodeType::SortI128(-1), EncodeType::SortString("a".to_string()), EncodeType::SortBytes(vec![0, 9]), EncodeType::SortBool(false), EncodeType::SortF32(F32struct::new(-1.0)), EncodeType::SortF64(F64struct::new(2.0))];
+    let larger = vec![EncodeType::SortU8(2), EncodeType::SortU16(2), EncodeType::SortU32(2), EncodeType::SortU64(2), EncodeType::SortU128(2), EncodeType::SortI8(2), EncodeType::SortI16(2), EncodeType::SortI32(2), EncodeType::SortI64(2), EncodeType::SortI128(2), EncodeType::SortString("c".to_string()), EncodeType::SortBytes(vec![1, 0]), EncodeType::SortBool(true), EncodeType::SortF32(F32struct::new(2.0)), EncodeType::SortF64(F64struct::new(-2.0))];
+    // one key per field that is smaller and one that is larger than the base key in just that field
+    let mut keys = vec![base.clone()];
+    for field in 0..base.len() {
+        for other in [&smaller, &larger] {
+            let mut key = base.clone();
+            key[field] = other[field].clone();
+            if key != base {
+                keys.push(key);
+            }
+        }
+    }
+    let mut expected = keys.clone();
+    expected.sort_by(|one, two| compare(&the_types, one, two));
+    let comparator_types = the_types.clone();
+    let compare_fn = move |one: &[u8], two: &[u8]| compare_bytes(&comparator_types, one, two);
+    let path = "_path_for_rocksdb_storage_all_types";
+    let mut result_vec = Vec::new();
+    {
+        let mut db_opts = Options::default();
+        db_opts.create_if_missing(true);
+        db_opts.set_comparator("all_types", Box::new(compare_fn));
+        let db = DB::open(&db_opts, path).unwrap();
+        for key in keys.iter().rev() {
+            db.put(encode_keys(key), b"").unwrap();
+        }
+        let mut iter = db.raw_iterator();
+        iter.seek_to_first();
+        while iter.valid() {
+            result_vec.push(decode_byte_array(iter.key().unwrap(), &the_types));
+            iter.next();
+        }
+    }
+    let _ = DB::destroy(&Options::default(), path);
+    assert_eq!(result_vec.len(), keys.len());
+    assert_eq!(result_vec, expected);
+    }
+
+    #[test]
+    fn test_schema_comparator() {
+    let mut result_vec = Vec::new();
+    let schema = KeySchema::new().field("name", DecodeType::DecodeString).field("version", DecodeType::DecodeU32).desc();
+    let path = "_path_for_rocksdb_storage_schema";
+    {
+        let mut db_opts = Options::default();
+        db_opts.create_if_missing(true);
+        schema.set_comparator(&mut db_opts);
+        let db = DB::open(&db_opts, path).unwrap();
+        db.put(schema.encode_key(&[EncodeType::SortString("a".to_string()), EncodeType::SortU32(0)]).unwrap(), b"key1").unwrap();
+        db.put(schema.encode_key(&[EncodeType::SortString("a".to_string()), EncodeType::SortU32(1)]).unwrap(), b"key2").unwrap();
+        db.put(schema.encode_key(&[EncodeType::SortString("b".to_string()), EncodeType::SortU32(0)]).unwrap(), b"key3").unwrap();
+        let mut iter = db.raw_iterator();
+        iter.seek_to_first();
+        while iter.valid() {
+            result_vec.push(String::from_utf8(iter.value().unwrap().to_vec()).unwrap());
+            iter.next();
+        }
+    }
+    let _ = DB::destroy(&Options::default(), path);
+    assert_eq!(result_vec, vec!["key2", "key1", "key3"]);
+    }
+
+    #[test]
+    fn test_schema_prefix_extractor() {
+    let mut result_vec = Vec::new();
+    let schema = KeySchema::new().field("tenant", DecodeType::DecodeString).field("version", DecodeType::DecodeU32).desc().field("name", DecodeType::DecodeString);
+    let path = "_path_for_rocksdb_storage_prefix";
+    {
+        let mut db_opts = Options::default();
+        db_opts.create_if_missing(true);
+        schema.set_comparator_with_prefix(&mut db_opts, 1);
+        let db = DB::open(&db_opts, path).unwrap();
+        for (tenant, version, name) in [("a", 1, "x"), ("a", 2, "y"), ("ab", 1, "z"), ("b", 0, "w")] {
+            let key = schema.encode_key(&[EncodeType::SortString(tenant.to_string()), EncodeType::SortU32(version), EncodeType::SortString(name.to_string())]).unwrap();
+            db.put(key, name).unwrap();
+        }
+        let mut read_opts = ReadOptions::default();
+        read_opts.set_prefix_same_as_start(true);
+        let mut iter = db.raw_iterator_opt(read_opts);
+        iter.seek(schema.encode_prefix(&[EncodeType::SortString("a".to_string())]).unwrap());
+        while iter.valid() {
+            result_vec.push(String::from_utf8(iter.value().unwrap().to_vec()).unwrap());
+            iter.next();
+        }
+    }
+    let _ = DB::destroy(&Options::default(), path);
+    assert_eq!(result_vec, vec!["y", "x"]);
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct Version {
+        name: String,
+        version: u32,
+    }
+
+    impl KeyValues for Version {
+        fn to_encode_types(&self) -> Vec<EncodeType> {
+            vec![EncodeType::SortString(self.name.clone()), EncodeType::SortU32(self.version)]
+        }
+
+        fn from_encode_types(values: Vec<EncodeType>) -> Result<Self, crate::rockssort_error::KeyError> {
+            let mut values = values.into_iter();
+            Ok(Version { name: next_field(&mut values, 0)?, version: next_field(&mut values, 1)? })
+        }
+    }
+
+    impl SortableKey for Version {
+        fn key_schema() -> KeySchema {
+            KeySchema::new().field("name", DecodeType::DecodeString).field("version", DecodeType::DecodeU32).desc()
+        }
+    }
+
+    #[test]
+    fn test_sorted_db_typed_keys() {
+    let path = "_path_for_rocksdb_storage_sorted_db";
+    let result_vec: Vec<(Version, Vec<u8>)>;
+    {
+        let db = SortedDb::<Version>::open_typed(path).unwrap();
+        db.put(&Version { name: "a".to_string(), version: 0 }, b"key1").unwrap();
+        db.put(&Version { name: "a".to_string(), version: 1 }, b"key2").unwrap();
+        db.put(&Version { name: "b".to_string(), version: 0 }, b"key3").unwrap();
+        db.put(&Version { name: "c".to_string(), version: 0 }, b"key4").unwrap();
+        db.delete(&Version { name: "c".to_string(), version: 0 }).unwrap();
+        assert_eq!(db.get(&Version { name: "a".to_string(), version: 1 }).unwrap(), Some(b"key2".to_vec()));
+        assert_eq!(db.get(&Version { name: "c".to_string(), version: 0 }).unwrap(), None);
+        result_vec = db.iter().collect::<Result<_, _>>().unwrap();
+    }
+    let _ = SortedDb::<Version>::destroy(&Options::default(), path);
+    assert_eq!(result_vec, vec![
+        (Version { name: "a".to_string(), version: 1 }, b"key2".to_vec()),
+        (Version { name: "a".to_string(), version: 0 }, b"key1".to_vec()),
+        (Version { name: "b".to_string(), version: 0 }, b"key3".to_vec()),
+    ]);
+    }
+
+    #[test]
+    fn test_sorted_db_untyped_keys() {
+    let path = "_path_for_rocksdb_storage_sorted_db_untyped";
+    let schema = KeySchema::new().field("id", DecodeType::DecodeI64).desc();
+    let keys: Vec<Vec<EncodeType>>;
+    {
+        let db = SortedDb::<Vec<EncodeType>>::open(path, schema).unwrap();
+        for id in [-1, 5, 2] {
+            db.put(&vec![EncodeType::SortI64(id)], b"").unwrap();
+        }
+        assert!(matches!(db.put(&vec![EncodeType::SortU8(1)], b""), Err(DbError::Key(_))));
+        keys = db.iter().map(|item| item.unwrap().0).collect();
+    }
+    let _ = SortedDb::<Vec<EncodeType>>::destroy(&Options::default(), path);
+    assert_eq!(keys, vec![vec![EncodeType::SortI64(5)], vec![EncodeType::SortI64(2)], vec![EncodeType::SortI64(-1)]]);
+    }
+
+    #[test]
+    fn test_sorted_db_range_scans() {
+    let path = "_path_for_rocksdb_storage_range";
+    let schema = KeySchema::new().field("name", DecodeType::DecodeString).field("size", DecodeType::DecodeU32).field("version", DecodeType::DecodeU32).desc();
+    let sizes = |iter: SortedIter<Vec<EncodeType>>| -> Vec<(String, u32, u32)> {
+        iter.map(|item| match item.unwrap().0.as_slice() {
+            [EncodeType::SortString(name), EncodeType::SortU32(size), EncodeType::SortU32(version)] => (name.clone(), *size, *version),
+            other => panic!("unexpected key {:?}", other),
+        }).collect()
+    };
+    {
+        let db = SortedDb::<Vec<EncodeType>>::open(path, schema).unwrap();
+        for name in ["a", "ab", "b"] {
+            for size in [5, 10, 15, 20, 25] {
+                for version in [1, 2] {
+                    db.put(&vec![EncodeType::SortString(name.to_string()), EncodeType::SortU32(size), EncodeType::SortU32(version)], b"").unwrap();
+                }
+            }
+        }
+        let a = [EncodeType::SortString("a".to_string())];
+        let included = sizes(db.range(&a, Bound::Included(EncodeType::SortU32(10)), Bound::Included(EncodeType::SortU32(20))).unwrap());
+        assert_eq!(included, vec![("a".to_string(), 10, 2), ("a".to_string(), 10, 1), ("a".to_string(), 15, 2), ("a".to_string(), 15, 1), ("a".to_string(), 20, 2), ("a".to_string(), 20, 1)]);
+        let excluded = sizes(db.range(&a, Bound::Excluded(EncodeType::SortU32(10)), Bound::Excluded(EncodeType::SortU32(20))).unwrap());
+        assert_eq!(excluded, vec![("a".to_string(), 15, 2), ("a".to_string(), 15, 1)]);
+        // no size follows the largest one, so the scan starts after the prefix
+        assert_eq!(db.range(&a, Bound::Excluded(EncodeType::SortU32(u32::MAX)), Bound::Unbounded).unwrap().count(), 0);
+        let open_end = sizes(db.range(&a, Bound::Included(EncodeType::SortU32(25)), Bound::Unbounded).unwrap());
+        assert_eq!(open_end, vec![("a".to_string(), 25, 2), ("a".to_string(), 25, 1)]);
+        assert_eq!(db.prefix(&a).unwrap().count(), 10);
+        assert_eq!(db.prefix(&[]).unwrap().count(), 30);
+        // the version field is descending, so the scan runs from the upper to the lower bound
+        let versions = sizes(db.range(&[EncodeType::SortString("b".to_string()), EncodeType::SortU32(5)], Bound::Included(EncodeType::SortU32(1)), Bound::Excluded(EncodeType::SortU32(3))).unwrap());
+        assert_eq!(versions, vec![("b".to_string(), 5, 2), ("b".to_string(), 5, 1)]);
+        let versions = sizes(db.range(&[EncodeType::SortString("b".to_string()), EncodeType::SortU32(5)], Bound::Unbounded, Bound::Excluded(EncodeType::SortU32(2))).unwrap());
+        assert_eq!(versions, vec![("b".to_string(), 5, 1)]);
+        assert!(db.range(&[EncodeType::SortU32(1)], Bound::Unbounded, Bound::Unbounded).is_err());
+    }
+    let _ = SortedDb::<Vec<EncodeType>>::destroy(&Options::default(), path);
+    }
+
+    #[test]
+    fn test_sorted_db_range_scans_shorter_last() {
+    let path = "_path_for_rocksdb_storage_range_shorter_last";
+    // shorter keys sort last, so the scans seek to the first complete key of the range
+    let schema = KeySchema::new().field("name", DecodeType::DecodeString).field("size", DecodeType::DecodeU32).field("version", DecodeType::DecodeU32).desc().shorter_last();
+    let sizes = |iter: SortedIter<Vec<EncodeType>>| -> Vec<(String, u32, u32)> {
+        iter.map(|item| match item.unwrap().0.as_slice() {
+            [EncodeType::SortString(name), EncodeType::SortU32(size), EncodeType::SortU32(version)] => (name.clone(), *size, *version),
+            other => panic!("unexpected key {:?}", other),
+        }).collect()
+    };
+    {
+        let db = SortedDb::<Vec<EncodeType>>::open(path, schema).unwrap();
+        for name in ["a", "ab", "b"] {
+            for size in [5, 10, 15] {
+                for version in [1, 2] {
+                    db.put(&vec![EncodeType::SortString(name.to_string()), EncodeType::SortU32(size), EncodeType::SortU32(version)], b"").unwrap();
+                }
+            }
+        }
+        let a = [EncodeType::SortString("a".to_string())];
+        let included = sizes(db.range(&a, Bound::Included(EncodeType::SortU32(10)), Bound::Unbounded).unwrap());
+        assert_eq!(included, vec![("a".to_string(), 10, 2), ("a".to_string(), 10, 1), ("a".to_string(), 15, 2), ("a".to_string(), 15, 1)]);
+        let excluded = sizes(db.range(&a, Bound::Excluded(EncodeType::SortU32(5)), Bound::Excluded(EncodeType::SortU32(15))).unwrap());
+        assert_eq!(excluded, vec![("a".to_string(), 10, 2), ("a".to_string(), 10, 1)]);
+        assert_eq!(db.range(&a, Bound::Excluded(EncodeType::SortU32(u32::MAX)), Bound::Unbounded).unwrap().count(), 0);
+        assert_eq!(db.prefix(&[EncodeType::SortString("ab".to_string())]).unwrap().count(), 6);
+        assert_eq!(db.prefix(&[]).unwrap().count(), 18);
+        let versions = sizes(db.range(&[EncodeType::SortString("b".to_string()), EncodeType::SortU32(5)], Bound::Unbounded, Bound::Excluded(EncodeType::SortU32(2))).unwrap());
+        assert_eq!(versions, vec![("b".to_string(), 5, 1)]);
+    }
+    let _ = SortedDb::<Vec<EncodeType>>::destroy(&Options::default(), path);
+    }
+
+    #[test]
+    fn test_sorted_db_excluded_start_without_next_value() {
+    let path = "_path_for_rocksdb_storage_excluded_decimal";
+    // a decimal has no next value, so the keys equal to the excluded start are skipped
+    let schema = KeySchema::new().field("name", DecodeType::DecodeString).field("amount", DecodeType::DecodeDecimal).field("version", DecodeType::DecodeU32);
+    let amounts: Vec<Decimal>;
+    {
+        let db = SortedDb::<Vec<EncodeType>>::open(path, schema).unwrap();
+        for amount in [Decimal::new(15, 1), Decimal::new(2, 0), Decimal::new(-1, 0)] {
+            for version in [1, 2, 3] {
+                db.put(&vec![EncodeType::SortString("a".to_string()), EncodeType::SortDecimal(amount.clone()), EncodeType::SortU32(version)], b"").unwrap();
+            }
+        }
+        amounts = db.range(&[EncodeType::SortString("a".to_string())], Bound::Excluded(EncodeType::SortDecimal(Decimal::new(15, 1))), Bound::Unbounded).unwrap().map(|item| match item.unwrap().0.as_slice() {
+            [_, EncodeType::SortDecimal(amount), _] => amount.clone(),
+            other => panic!("unexpected key {:?}", other),
+        }).collect();
+    }
+    let _ = SortedDb::<Vec<EncodeType>>::destroy(&Options::default(), path);
+    assert_eq!(amounts, vec![Decimal::new(2, 0); 3]);
+    }
+
+    #[test]
+    fn test_sorted_db_shorter_last_without_seek_key() {
+    let path = "_path_for_rocksdb_storage_shorter_last";
+    // a descending string has no first value, so the scan steps back from the prefix key over the keys starting with it
+    let schema = KeySchema::new().field("group", DecodeType::DecodeU8).field("name", DecodeType::DecodeString).desc().shorter_last();
+    {
+        let db = SortedDb::<Vec<EncodeType>>::open(path, schema).unwrap();
+        for group in [0, 1, 2] {
+            for name in ["a", "b"] {
+                db.put(&vec![EncodeType::SortU8(group), EncodeType::SortString(name.to_string())], b"").unwrap();
+            }
+        }
+        let keys: Vec<Vec<EncodeType>> = db.prefix(&[EncodeType::SortU8(1)]).unwrap().map(|item| item.unwrap().0).collect();
+        assert_eq!(keys, vec![vec![EncodeType::SortU8(1), EncodeType::SortString("b".to_string())], vec![EncodeType::SortU8(1), EncodeType::SortString("a".to_string())]]);
+        let keys: Vec<Vec<EncodeType>> = db.range(&[EncodeType::SortU8(2)], Bound::Unbounded, Bound::Excluded(EncodeType::SortString("b".to_string()))).unwrap().map(|item| item.unwrap().0).collect();
+        assert_eq!(keys, vec![vec![EncodeType::SortU8(2), EncodeType::SortString("a".to_string())]]);
+        // the first group, where stepping back leaves the keys
+        assert_eq!(db.prefix(&[EncodeType::SortU8(0)]).unwrap().count(), 2);
+        assert_eq!(db.prefix(&[]).unwrap().count(), 6);
+        assert_eq!(db.prefix(&[EncodeType::SortU8(3)]).unwrap().count(), 0);
+        let keys: Vec<Vec<EncodeType>> = db.range(&[], Bound::Excluded(EncodeType::SortU8(0)), Bound::Included(EncodeType::SortU8(1))).unwrap().map(|item| item.unwrap().0).collect();
+        assert_eq!(keys, vec![vec![EncodeType::SortU8(1), EncodeType::SortString("b".to_string())], vec![EncodeType::SortU8(1), EncodeType::SortString("a".to_string())]]);
+    }
+    let _ = SortedDb::<Vec<EncodeType>>::destroy(&Options::default(), path);
+    }
+
+    #[test]
+    fn test_sorted_db_checks_stored_schema() {
+    let path = "_path_for_rocksdb_storage_stored_schema";
+    let schema = KeySchema::new().field("name", DecodeType::DecodeString).field("size", DecodeType::DecodeU32).desc();
+    {
+        let db = SortedDb::<Vec<EncodeType>>::open(path, schema.clone()).unwrap();
+        db.put(&vec![EncodeType::SortString("a".to_string()), EncodeType::SortU32(1)], b"").unwrap();
+    }
+    assert_eq!(SortedDb::<Vec<EncodeType>>::stored_schema(path).unwrap(), Some(schema.clone()));
+    let ascending = KeySchema::new().field("name", DecodeType::DecodeString).field("size", DecodeType::DecodeU32);
+    match SortedDb::<Vec<EncodeType>>::open(path, ascending.clone()) {
+        Err(DbError::SchemaMismatch { stored, requested }) => {
+            assert_eq!(stored, schema);
+            assert_eq!(requested, ascending);
+        },
+        other => panic!("expected a schema mismatch, got {:?}", other.err()),
+    }
+    let other_type = KeySchema::new().field("name", DecodeType::DecodeString).field("size", DecodeType::DecodeU64).desc();
+    assert!(matches!(SortedDb::<Vec<EncodeType>>::open(path, other_type), Err(DbError::SchemaMismatch { .. })));
+    // renaming a field keeps the order, the stored schema takes the new name
+    let renamed = KeySchema::new().field("name", DecodeType::DecodeString).field("bytes", DecodeType::DecodeU32).desc();
+    {
+        let db = SortedDb::<Vec<EncodeType>>::open(path, renamed.clone()).unwrap();
+        assert_eq!(db.iter().count(), 1);
+    }
+    assert_eq!(SortedDb::<Vec<EncodeType>>::stored_schema(path).unwrap(), Some(renamed));
+    SortedDb::<Vec<EncodeType>>::destroy(&Options::default(), path).unwrap();
+    assert!(!std::path::Path::new(path).exists());
+    assert_eq!(SortedDb::<Vec<EncodeType>>::stored_schema(path).unwrap(), None);
+    }
+
+    #[test]
+    fn test_sorted_db_refuses_schema_with_same_comparator_name() {
+    let path = "_path_for_rocksdb_storage_name_collision";
+    // two orders whose comparator names collide
+    let schema = KeySchema::new().field("a", DecodeType::DecodeFixedBytes(20623)).field("b", DecodeType::DecodeFixedBytes(3070511583));
+    let colliding = KeySchema::new().field("a", DecodeType::DecodeFixedBytes(44355)).field("b", DecodeType::DecodeFixedBytes(3854661203));
+    assert_eq!(schema.comparator_name(), colliding.comparator_name());
+    drop(SortedDb::<Vec<EncodeType>>::open(path, schema).unwrap());
+    let result = SortedDb::<Vec<EncodeType>>::open(path, colliding);
+    let _ = SortedDb::<Vec<EncodeType>>::destroy(&Options::default(), path);
+    assert!(matches!(result, Err(DbError::SchemaMismatch { .. })));
+    }
 }
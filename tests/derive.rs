@@ -0,0 +1,295 @@
+use rocks_sortable_keys::{compare_bytes, encode_keys, DecodeType, EncodeType, KeyError, KeySchema, KeyValues, NullOrder, SortableField, SortableKey, TimeUnit, Timestamp};
+use std::cmp::Ordering;
+
+#[derive(sortable_rocksdb_keys_derive::SortableKey, Debug, Clone, PartialEq)]
+struct Event {
+    customer: String,
+    #[key(desc)]
+    created: u64,
+    amount: f64,
+    payload: Vec<u8>,
+}
+
+#[derive(sortable_rocksdb_keys_derive::SortableKey, Debug, PartialEq)]
+struct Pair(i64, #[key(desc)] bool);
+
+#[test]
+fn test_derive_round_trip() {
+    let event = Event { customer: "a".to_string(), created: 10, amount: -1.5, payload: vec![1, 2] };
+    let encoded = event.encode_key();
+    assert_eq!(Event::decode_key(&encoded), Ok(event.clone()));
+    assert_eq!(encoded, encode_keys(&event.to_encode_types()));
+}
+
+#[test]
+fn test_derive_schema() {
+    let schema = Event::key_schema();
+    let names: Vec<&str> = schema.fields().iter().map(|field| field.name.as_str()).collect();
+    assert_eq!(names, vec!["customer", "created", "amount", "payload"]);
+    assert_eq!(schema.decode_types(), vec![DecodeType::DecodeString, DecodeType::Reverse, DecodeType::DecodeU64, DecodeType::DecodeF64, DecodeType::DecodeBytes]);
+    assert_eq!(Pair::key_schema().decode_types(), vec![DecodeType::DecodeI64, DecodeType::Reverse, DecodeType::DecodeBool]);
+}
+
+#[test]
+fn test_derive_ordering() {
+    let the_types = Event::key_schema().decode_types();
+    let older = Event { customer: "a".to_string(), created: 1, amount: 0.0, payload: vec![] };
+    let newer = Event { customer: "a".to_string(), created: 2, amount: 0.0, payload: vec![] };
+    assert_eq!(compare_bytes(&the_types, &newer.encode_key(), &older.encode_key()), Ordering::Less);
+    assert_eq!(compare_bytes(&Pair::key_schema().decode_types(), &Pair(1, true).encode_key(), &Pair(1, false).encode_key()), Ordering::Less);
+}
+
+#[test]
+fn test_derive_rejects_wrong_values() {
+    assert_eq!(Pair::from_encode_types(vec![EncodeType::SortI64(1)]), Err(KeyError::SchemaMismatch { field: 1, expected: Some(DecodeType::DecodeBool), found: None }));
+    assert_eq!(Pair::from_encode_types(vec![EncodeType::SortI32(1), EncodeType::SortBool(true)]), Err(KeyError::SchemaMismatch { field: 0, expected: Some(DecodeType::DecodeI64), found: Some(EncodeType::SortI32(1)) }));
+    assert_eq!(Pair::from_encode_types(vec![EncodeType::SortI64(1), EncodeType::SortBool(true), EncodeType::SortU8(0)]), Err(KeyError::SchemaMismatch { field: 2, expected: None, found: Some(EncodeType::SortU8(0)) }));
+}
+
+// written by hand, the values do not match the schema
+struct Mismatched;
+
+impl KeyValues for Mismatched {
+    fn to_encode_types(&self) -> Vec<EncodeType> {
+        vec![EncodeType::SortU32(1)]
+    }
+
+    fn from_encode_types(_values: Vec<EncodeType>) -> Result<Self, KeyError> {
+        Ok(Mismatched)
+    }
+}
+
+impl SortableKey for Mismatched {
+    fn key_schema() -> KeySchema {
+        KeySchema::new().field("id", DecodeType::DecodeU64)
+    }
+}
+
+#[test]
+fn test_try_encode_key() {
+    let event = Event { customer: "a".to_string(), created: 10, amount: -1.5, payload: vec![1, 2] };
+    assert_eq!(event.try_encode_key(), Ok(event.encode_key()));
+    assert_eq!(Mismatched.try_encode_key(), Err(KeyError::SchemaMismatch { field: 0, expected: Some(DecodeType::DecodeU64), found: Some(EncodeType::SortU32(1)) }));
+}
+
+#[derive(sortable_rocksdb_keys_derive::SortableKey, Debug, PartialEq)]
+struct Tagged<T: SortableField> {
+    tag: String,
+    value: T,
+}
+
+#[test]
+fn test_derive_generic_keys() {
+    // each instance decodes with its own schema
+    let number = Tagged { tag: "a".to_string(), value: 7u32 };
+    let text = Tagged { tag: "a".to_string(), value: "b".to_string() };
+    assert_eq!(Tagged::<u32>::decode_key(&number.encode_key()), Ok(number));
+    assert_eq!(Tagged::<String>::decode_key(&text.encode_key()), Ok(text));
+}
+
+#[derive(sortable_rocksdb_keys_derive::SortableKey, Debug, Clone, PartialEq)]
+struct Task {
+    #[key(nulls_last)]
+    finished: Option<u64>,
+    name: Option<String>,
+}
+
+#[test]
+fn test_derive_nullable_fields() {
+    let the_types = Task::key_schema().decode_types();
+    assert_eq!(the_types, vec![DecodeType::DecodeOption(Box::new(DecodeType::DecodeU64), NullOrder::NullsLast), DecodeType::DecodeOption(Box::new(DecodeType::DecodeString), NullOrder::NullsFirst)]);
+    let open = Task { finished: None, name: Some("b".to_string()) };
+    let unnamed = Task { finished: Some(5), name: None };
+    let named = Task { finished: Some(5), name: Some("a".to_string()) };
+    assert_eq!(Task::decode_key(&open.encode_key()), Ok(open.clone()));
+    assert_eq!(Task::decode_key(&unnamed.encode_key()), Ok(unnamed.clone()));
+    assert_eq!(compare_bytes(&the_types, &named.encode_key(), &open.encode_key()), Ordering::Less);
+    assert_eq!(compare_bytes(&the_types, &unnamed.encode_key(), &named.encode_key()), Ordering::Less);
+}
+
+#[derive(sortable_rocksdb_keys_derive::SortableKey, Debug, Clone, PartialEq)]
+struct Transfer {
+    from: [u8; 20],
+    #[key(desc)]
+    hash: [u8; 32],
+}
+
+#[test]
+fn test_derive_fixed_bytes() {
+    assert_eq!(Transfer::key_schema().decode_types(), vec![DecodeType::DecodeFixedBytes(20), DecodeType::Reverse, DecodeType::DecodeFixedBytes(32)]);
+    let transfer = Transfer { from: [7; 20], hash: [9; 32] };
+    let encoded = transfer.encode_key();
+    assert_eq!(encoded.len(), 52);
+    assert_eq!(Transfer::decode_key(&encoded), Ok(transfer));
+}
+
+#[cfg(feature = "uuid")]
+#[test]
+fn test_derive_uuid() {
+    #[derive(sortable_rocksdb_keys_derive::SortableKey, Debug, PartialEq)]
+    struct Session {
+        user: uuid::Uuid,
+        started: u64,
+    }
+    let session = Session { user: uuid::Uuid::from_u128(0x1234), started: 5 };
+    assert_eq!(Session::key_schema().decode_types(), vec![DecodeType::DecodeUuid, DecodeType::DecodeU64]);
+    assert_eq!(&session.encode_key()[..16], session.user.as_bytes());
+    assert_eq!(Session::decode_key(&session.encode_key()), Ok(session));
+}
+
+#[derive(sortable_rocksdb_keys_derive::SortableKey, Debug, Clone, PartialEq)]
+struct Reading {
+    sensor: u32,
+    #[key(desc)]
+    taken: Timestamp,
+}
+
+#[test]
+fn test_derive_timestamp_desc() {
+    assert_eq!(Reading::key_schema().decode_types(), vec![DecodeType::DecodeU32, DecodeType::Reverse, DecodeType::DecodeTimestamp(TimeUnit::Micros)]);
+    let readings: Vec<Reading> = [(1, 2_000_000), (1, 1_000_000), (1, -5), (2, 3_000_000)]
+        .into_iter()
+        .map(|(sensor, taken)| Reading { sensor, taken: Timestamp::new(taken, TimeUnit::Micros) })
+        .collect();
+    // newest first within a sensor
+    for pair in readings.windows(2) {
+        assert_eq!(compare_bytes(&Reading::key_schema().decode_types(), &pair[0].encode_key(), &pair[1].encode_key()), Ordering::Less);
+    }
+    for reading in &readings {
+        assert_eq!(Reading::decode_key(&reading.encode_key()).as_ref(), Ok(reading));
+    }
+    // other units are stored in microseconds
+    let seconds = Reading { sensor: 1, taken: Timestamp::new(2, TimeUnit::Seconds) };
+    assert_eq!(seconds.encode_key(), readings[0].encode_key());
+    let nanos = Reading { sensor: 1, taken: Timestamp::new(2_000_000_999, TimeUnit::Nanos) };
+    assert_eq!(Reading::decode_key(&nanos.encode_key()), Ok(readings[0].clone()));
+}
+
+#[test]
+fn test_derive_system_time() {
+    #[derive(sortable_rocksdb_keys_derive::SortableKey, Debug, PartialEq)]
+    struct Upload {
+        bucket: String,
+        #[key(desc)]
+        at: std::time::SystemTime,
+    }
+    let at = std::time::UNIX_EPOCH + std::time::Duration::from_micros(1_700_000_000_123_456);
+    let upload = Upload { bucket: "b".to_string(), at };
+    assert_eq!(Upload::decode_key(&upload.encode_key()), Ok(upload));
+    let before = Upload { bucket: "b".to_string(), at: std::time::UNIX_EPOCH - std::time::Duration::from_secs(1) };
+    assert_eq!(Upload::decode_key(&before.encode_key()), Ok(before));
+}
+
+#[cfg(feature = "chrono")]
+#[test]
+fn test_derive_chrono() {
+    #[derive(sortable_rocksdb_keys_derive::SortableKey, Debug, PartialEq)]
+    struct Order {
+        customer: u64,
+        #[key(desc)]
+        placed: chrono::DateTime<chrono::Utc>,
+        due: chrono::NaiveDate,
+    }
+    let order = |placed: i64| Order {
+        customer: 7,
+        placed: chrono::DateTime::from_timestamp_micros(placed).unwrap(),
+        due: chrono::NaiveDate::from_ymd_opt(2024, 2, 29).unwrap(),
+    };
+    let (newer, older) = (order(1_700_000_000_000_001), order(1_700_000_000_000_000));
+    assert_eq!(compare_bytes(&Order::key_schema().decode_types(), &newer.encode_key(), &older.encode_key()), Ordering::Less);
+    assert_eq!(Order::decode_key(&newer.encode_key()), Ok(newer));
+}
+
+#[cfg(feature = "time")]
+#[test]
+fn test_derive_time() {
+    #[derive(sortable_rocksdb_keys_derive::SortableKey, Debug, PartialEq)]
+    struct Shift {
+        #[key(desc)]
+        start: time::OffsetDateTime,
+        day: time::Date,
+    }
+    let start = time::OffsetDateTime::from_unix_timestamp(1_700_000_000).unwrap();
+    let shift = Shift { start, day: start.date() };
+    assert_eq!(Shift::key_schema().decode_types(), vec![DecodeType::Reverse, DecodeType::DecodeTimestamp(TimeUnit::Micros), DecodeType::DecodeDate]);
+    assert_eq!(Shift::decode_key(&shift.encode_key()), Ok(shift));
+}
+
+#[derive(sortable_rocksdb_keys_derive::SortableKey, Debug, Clone, PartialEq)]
+struct Location {
+    region: String,
+    zone: u16,
+}
+
+#[derive(sortable_rocksdb_keys_derive::SortableKey, Debug, Clone, PartialEq)]
+struct Machine {
+    tenant: u32,
+    #[key(desc)]
+    location: Location,
+    slot: (u8, Option<i16>),
+}
+
+#[test]
+fn test_derive_nested_keys() {
+    let the_types = Machine::key_schema().decode_types();
+    let location_types = vec![DecodeType::DecodeString, DecodeType::DecodeU16];
+    assert_eq!(the_types[1..3], [DecodeType::Reverse, DecodeType::DecodeTuple(location_types)]);
+    let machine = |region: &str, zone: u16, slot: Option<i16>| Machine { tenant: 7, location: Location { region: region.to_string(), zone }, slot: (1, slot) };
+    let keys = [machine("eu", 2, None), machine("eu", 2, Some(-1)), machine("eu", 1, None), machine("asia", 9, Some(3))];
+    for pair in keys.windows(2) {
+        assert_eq!(compare_bytes(&the_types, &pair[0].encode_key(), &pair[1].encode_key()), Ordering::Less, "{:?}", pair);
+    }
+    for key in &keys {
+        assert_eq!(Machine::decode_key(&key.encode_key()).as_ref(), Ok(key));
+    }
+}
+
+#[derive(sortable_rocksdb_keys_derive::SortableField, Debug, Clone, PartialEq)]
+enum UserId {
+    Id(u64),
+    Handle(String),
+    Anonymous,
+}
+
+#[derive(sortable_rocksdb_keys_derive::SortableKey, Debug, Clone, PartialEq)]
+struct Login {
+    #[key(desc)]
+    user: UserId,
+    at: u64,
+}
+
+#[test]
+fn test_derive_union_fields() {
+    let the_types = Login::key_schema().decode_types();
+    assert_eq!(the_types[1], DecodeType::DecodeUnion(vec![DecodeType::DecodeU64, DecodeType::DecodeString, DecodeType::DecodeTuple(vec![])]));
+    let login = |user: UserId| Login { user, at: 1 };
+    // descending: the last variant first, inside a variant the larger value first
+    let logins = [login(UserId::Anonymous), login(UserId::Handle("b".to_string())), login(UserId::Handle("a".to_string())), login(UserId::Id(2)), login(UserId::Id(1))];
+    for pair in logins.windows(2) {
+        assert_eq!(compare_bytes(&the_types, &pair[0].encode_key(), &pair[1].encode_key()), Ordering::Less, "{:?}", pair);
+    }
+    for login in &logins {
+        assert_eq!(Login::decode_key(&login.encode_key()).as_ref(), Ok(login));
+    }
+    assert_eq!(Login::from_encode_types(vec![EncodeType::SortUnion(3, Box::new(EncodeType::SortU64(1))), EncodeType::SortU64(1)]).unwrap_err(), KeyError::SchemaMismatch { field: 0, expected: Some(the_types[1].clone()), found: Some(EncodeType::SortUnion(3, Box::new(EncodeType::SortU64(1)))) });
+}
+
+#[derive(sortable_rocksdb_keys_derive::SortableKey, Debug, Clone, PartialEq)]
+struct Document {
+    path: Vec<String>,
+    version: u32,
+}
+
+#[test]
+fn test_derive_list_fields() {
+    let the_types = Document::key_schema().decode_types();
+    assert_eq!(the_types[0], DecodeType::DecodeList(Box::new(DecodeType::DecodeString)));
+    let document = |path: &[&str], version: u32| Document { path: path.iter().map(|segment| segment.to_string()).collect(), version };
+    let documents = [document(&["docs"], 9), document(&["docs", "a"], 1), document(&["docs", "a", "b"], 1), document(&["docs", "b"], 0), document(&["src"], 0)];
+    for pair in documents.windows(2) {
+        assert_eq!(compare_bytes(&the_types, &pair[0].encode_key(), &pair[1].encode_key()), Ordering::Less, "{:?}", pair);
+    }
+    for document in &documents {
+        assert_eq!(Document::decode_key(&document.encode_key()).as_ref(), Ok(document));
+    }
+}